@@ -0,0 +1,125 @@
+use crate::{config::IroConfig, ColorScheme};
+use anyhow::{Context, Result};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Extracted schemes in ~/.cache/iro/palettes, so re-running on a wallpaper skips
+/// decoding and palette generation
+pub struct PaletteCache {
+    dir: PathBuf,
+}
+
+impl PaletteCache {
+    pub fn new() -> Result<Self> {
+        Ok(Self { dir: Self::dir()? })
+    }
+
+    /// ~/.cache/iro/palettes
+    pub fn dir() -> Result<PathBuf> {
+        let cache_dir = dirs::cache_dir().context("Failed to get cache directory")?;
+        Ok(cache_dir.join("iro").join("palettes"))
+    }
+
+    /// Cache key for extracting `image_path` with `theme` under `config`. Editing the
+    /// image or changing any setting (palette style, thresholds, background styles...)
+    /// gives a new key, so stale entries are never hit.
+    pub fn key(image_path: &Path, theme: &str, config: &IroConfig) -> Result<String> {
+        let settings = toml::to_string(config).context("Failed to serialize config")?;
+        file_key(image_path, &format!("{}\0{}", theme, settings))
+    }
+
+    pub fn load(&self, key: &str) -> Option<ColorScheme> {
+        let content = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn store(&self, key: &str, scheme: &ColorScheme) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).context("Failed to create palette cache directory")?;
+        let content = serde_json::to_string(scheme).context("Failed to serialize scheme")?;
+        std::fs::write(self.entry_path(key), content).context("Failed to write palette cache")?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Hex hash of `path`, its size and modification time and `settings`, for naming
+/// files derived from it. Any edit to the file or change of settings gives a new key.
+pub fn file_key(path: &Path, settings: &str) -> Result<String> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let metadata =
+        std::fs::metadata(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|age| age.as_nanos())
+        .unwrap_or_default();
+
+    // Bytes laid out here rather than through `Hash`, whose output isn't guaranteed to
+    // stay the same between Rust releases. The size and time are fixed width, so only
+    // the path needs a terminator.
+    let mut hasher = Fnv1a::default();
+    hasher.write(path.as_os_str().as_encoded_bytes());
+    hasher.write(&[0]);
+    hasher.write(&metadata.len().to_le_bytes());
+    hasher.write(&modified.to_le_bytes());
+    hasher.write(settings.as_bytes());
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// 64-bit FNV-1a. `DefaultHasher`'s algorithm may change between Rust releases, which
+/// would silently invalidate every cached file after a toolchain upgrade.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv1a::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn file_keys_follow_the_file_and_settings() {
+        let path = std::env::temp_dir().join(format!("iro-file-key-{}", std::process::id()));
+        std::fs::write(&path, "wallpaper").unwrap();
+
+        let key = file_key(&path, "dark").unwrap();
+        assert_eq!(file_key(&path, "dark").unwrap(), key);
+        assert_ne!(file_key(&path, "light").unwrap(), key);
+        std::fs::write(&path, "another wallpaper").unwrap();
+        assert_ne!(file_key(&path, "dark").unwrap(), key);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,101 +1,484 @@
 use crate::{
-    config::{IroConfig, PaletteStyle},
+    cache::PaletteCache,
+    color_math::{
+        downsample, hex_to_rgb, oklch_to_rgb, quantize, relative_luminance, rgb_to_hex, to_srgb,
+    },
+    config::{BrightnessSpace, IroConfig, PaletteAlgorithm, PaletteStyle, Pin},
     palette::PaletteGenerator,
     ColorScheme,
 };
 use anyhow::{Context, Result};
-use image::{ImageReader, Rgb};
-use palette::{Hsl, IntoColor, Srgb};
-use std::path::PathBuf;
+use image::{ImageFormat, ImageReader, Rgb, RgbImage};
+use palette::{Hsl, IntoColor, Oklch, Srgb};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Wallpaper file extensions picked up by the CLI and the GUI. AVIF needs the `avif`
+/// feature, the image crate can only decode it with the system dav1d library.
+#[cfg(not(feature = "avif"))]
+pub const WALLPAPER_EXTENSIONS: &[&str] =
+    &["jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff"];
+#[cfg(feature = "avif")]
+pub const WALLPAPER_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff", "avif",
+];
+
+/// Whether a path has one of the wallpaper extensions, ignoring case
+pub fn is_wallpaper_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| WALLPAPER_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Canonical hues of the ANSI colors color1 to color6: red, green, yellow, blue,
+/// magenta and cyan
+const ANSI_HUES: [f32; 6] = [0.0, 120.0, 60.0, 240.0, 300.0, 180.0];
+
+/// Mean relative luminance above which the "auto" theme turns light: mid grey, L* 50
+const AUTO_LIGHT_LUMINANCE: f32 = 0.18;
+
+/// Mean chroma (largest minus smallest channel, 0-1) below which a wallpaper counts
+/// as monochrome and gets grayscale terminal colors
+const MONOCHROME_CHROMA: f32 = 0.06;
+
+/// HSL saturation below which a color counts as grey and has no hue worth boosting
+const GREY_SATURATION: f32 = 0.02;
+
+/// Something the extractor noticed that its caller may want to show, see
+/// `ColorExtractor::take_notes`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractionNote {
+    /// `palette.style` names no style, lofi is used instead
+    UnknownStyle(String),
+    /// A `[palette.pins]` key that isn't color0 to color15
+    UnknownPin(String),
+    /// The scheme was extracted but couldn't be written to the palette cache
+    CacheFailed(String),
+    /// What the "auto" theme resolved to
+    AutoTheme(String),
+    /// What the "auto" palette style resolved to
+    AutoStyle(String),
+    /// The wallpaper has (nearly) no color, so the terminal colors are grey
+    Monochrome,
+}
+
+impl fmt::Display for ExtractionNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractionNote::UnknownStyle(style) => write!(
+                f,
+                "  ⚠ Unknown palette style '{}', using lofi (see iro --list-styles)",
+                style
+            ),
+            ExtractionNote::UnknownPin(key) => {
+                write!(f, "  ⚠ Unknown pin '{}', use color0 to color15", key)
+            }
+            ExtractionNote::CacheFailed(error) => {
+                write!(f, "  ⚠ Failed to cache palette: {}", error)
+            }
+            ExtractionNote::AutoTheme(theme) => write!(f, "🌓 Auto theme: {}", theme),
+            ExtractionNote::AutoStyle(style) => write!(f, "🌡️  Auto style: {}", style),
+            ExtractionNote::Monochrome => {
+                f.write_str("🔳 Monochrome wallpaper: grayscale terminal colors")
+            }
+        }
+    }
+}
 
 pub struct ColorExtractor {
     config: IroConfig,
+    /// Reuse schemes extracted earlier from the same image and settings
+    use_cache: bool,
+    /// Notes not yet taken by `take_notes`
+    notes: Mutex<Vec<ExtractionNote>>,
 }
 
 impl ColorExtractor {
+    /// Extractor with the settings from `~/.config/iro/config.toml`
     pub fn new() -> Result<Self> {
-        let config = IroConfig::load()?;
-        Ok(Self { config })
+        Ok(Self::new_with_config(IroConfig::load()?))
+    }
+
+    /// Extractor with `config` instead of the user's config file
+    pub fn new_with_config(config: IroConfig) -> Self {
+        let mut notes = Vec::new();
+        if PaletteStyle::from_name(&config.palette.style).is_none() {
+            notes.push(ExtractionNote::UnknownStyle(config.palette.style.clone()));
+        }
+        for key in config.palette.pins.keys() {
+            if Pin::slot(key).is_none() {
+                notes.push(ExtractionNote::UnknownPin(key.clone()));
+            }
+        }
+        Self {
+            config,
+            use_cache: true,
+            notes: Mutex::new(notes),
+        }
+    }
+
+    /// What the extractor noticed since it was created or last asked, oldest first:
+    /// config and cache warnings, and what "auto" resolved to. Nothing is printed, so
+    /// it's up to the caller to show them.
+    pub fn take_notes(&self) -> Vec<ExtractionNote> {
+        std::mem::take(&mut *self.notes.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn note(&self, note: ExtractionNote) {
+        self.notes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(note);
+    }
+
+    /// `resolve_theme`, noting what "auto" became
+    fn resolved_theme<'a>(
+        &self,
+        theme: &'a str,
+        pixels: impl Iterator<Item = &'a Rgb<u8>>,
+    ) -> &'a str {
+        let resolved = resolve_theme(theme, pixels);
+        if theme == "auto" {
+            self.note(ExtractionNote::AutoTheme(resolved.to_string()));
+        }
+        resolved
+    }
+
+    /// Always extract from the image, neither reading nor updating the palette cache
+    pub fn without_cache(mut self) -> Self {
+        self.use_cache = false;
+        self
+    }
+
+    /// Where extracted schemes are cached, ~/.cache/iro/palettes
+    pub fn cache_dir() -> Result<PathBuf> {
+        PaletteCache::dir()
+    }
+
+    /// Use `style` instead of the configured palette style
+    pub fn with_style(mut self, style: &str) -> Self {
+        self.config.palette.style = style.to_string();
+        self
+    }
+
+    /// Multiply the style's contrast, saturation and brightness, overriding the
+    /// configured multipliers for any that are set
+    pub fn with_adjustments(
+        mut self,
+        contrast: Option<f32>,
+        saturation: Option<f32>,
+        brightness: Option<f32>,
+    ) -> Self {
+        let palette = &mut self.config.palette;
+        palette.contrast = contrast.or(palette.contrast);
+        palette.saturation = saturation.or(palette.saturation);
+        palette.brightness = brightness.or(palette.brightness);
+        self
+    }
+
+    /// Palette style schemes are generated with
+    pub fn style(&self) -> &str {
+        &self.config.palette.style
+    }
+
+    /// Use `algorithm` instead of the configured extraction algorithm
+    pub fn with_algorithm(mut self, algorithm: PaletteAlgorithm) -> Self {
+        self.config.palette.algorithm = algorithm;
+        self
+    }
+
+    /// Palette generator for `style` with the configured extraction settings
+    fn palette_generator(&self, style: &str) -> PaletteGenerator {
+        let palette = &self.config.palette;
+        PaletteGenerator::new(palette.diversity_threshold(), palette.resolve_style(style))
+            .with_algorithm(palette.algorithm)
+            .with_distance_metric(palette.distance_metric)
+            .with_brightness_space(palette.brightness_space)
+            .with_brightness_range(palette.brightness_range())
+    }
+
+    /// Palette generator for extracting from a wallpaper. Monochrome ones keep their
+    /// black and white pixels, which are all the contrast they have.
+    fn extraction_generator(&self, monochrome: bool) -> PaletteGenerator {
+        let palette_gen = self.palette_generator(&self.config.palette.style);
+        if monochrome {
+            palette_gen.with_brightness_range(0..=u8::MAX)
+        } else {
+            palette_gen
+        }
     }
 
     pub fn extract_colors(&self, image_path: &PathBuf, theme: &str) -> Result<ColorScheme> {
-        // Load and resize image for faster processing
-        let img = ImageReader::open(image_path)
-            .context("Failed to open image")?
-            .decode()
-            .context("Failed to decode image")?;
-
-        let rgb_img = img.to_rgb8();
-        // Use smaller size and faster filter for speed
-        let resized =
-            image::imageops::resize(&rgb_img, 128, 128, image::imageops::FilterType::Nearest);
-
-        // Use new palette generator with style
-        let style = PaletteStyle::from_name(&self.config.palette.style);
-        let palette_gen = PaletteGenerator::new(self.config.palette.diversity_threshold, style);
-        let dominant_colors =
-            palette_gen.extract_palette(&resized, self.config.palette.color_count)?;
+        // The cache is only a speedup, so any problem with it falls back to extracting
+        let cache = self
+            .use_cache
+            .then(|| {
+                let key = PaletteCache::key(image_path, theme, &self.config).ok();
+                PaletteCache::new().ok().zip(key)
+            })
+            .flatten();
+        if let Some(scheme) = cache.as_ref().and_then(|(cache, key)| cache.load(key)) {
+            return Ok(scheme);
+        }
 
-        // Generate color scheme based on theme
-        let color_scheme = match theme {
-            "light" => self.generate_light_scheme(dominant_colors, &palette_gen),
-            _ => self.generate_dark_scheme(dominant_colors, &palette_gen),
+        let scheme = self.extract_colors_uncached(image_path, theme)?;
+        if let Some((cache, key)) = &cache {
+            if let Err(e) = cache.store(key, &scheme) {
+                self.note(ExtractionNote::CacheFailed(e.to_string()));
+            }
+        }
+        Ok(scheme)
+    }
+
+    fn extract_colors_uncached(&self, image_path: &PathBuf, theme: &str) -> Result<ColorScheme> {
+        let resized = self.load_image(image_path)?;
+        let theme = self.resolved_theme(theme, resized.pixels());
+        let monochrome = is_monochrome(&resized);
+
+        let palette_gen = self.extraction_generator(monochrome);
+        let mut palette = palette_gen.extract_palette(&resized, self.config.palette.color_count)?;
+        palette_gen.ensure_distinct_hues(&mut palette, self.config.palette.min_distinct_hues);
+
+        Ok(self.generate_scheme(palette, &palette_gen, theme, monochrome))
+    }
+
+    /// The `count` most common colors of a wallpaper as hex, most common first, each
+    /// with the share of the image it covers. None of the scheme building is applied,
+    /// and images with fewer distinct colors give fewer than `count`.
+    pub fn extract_dominant_colors(
+        &self,
+        image_path: &PathBuf,
+        count: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        let resized = self.load_image(image_path)?;
+        let mut palette = self
+            .palette_generator(&self.config.palette.style)
+            .extract_palette(&resized, count)?;
+
+        // Padding colors are made up to reach `count`, they aren't in the image
+        palette.retain(|(_, share)| *share > 0.0);
+        palette.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(palette
+            .into_iter()
+            .map(|(color, share)| (rgb_to_hex(&color), share))
+            .collect())
+    }
+
+    /// Several alternative schemes for the same image, the first being what
+    /// `extract_colors` would produce
+    pub fn extract_color_variants(
+        &self,
+        image_path: &PathBuf,
+        theme: &str,
+        variants: usize,
+    ) -> Result<Vec<ColorScheme>> {
+        let resized = self.load_image(image_path)?;
+        let theme = self.resolved_theme(theme, resized.pixels());
+        let monochrome = is_monochrome(&resized);
+
+        let palette_gen = self.extraction_generator(monochrome);
+
+        Ok(palette_gen
+            .extract_palette_variants(&resized, self.config.palette.color_count, variants)
+            .into_iter()
+            .map(|mut palette| {
+                palette_gen
+                    .ensure_distinct_hues(&mut palette, self.config.palette.min_distinct_hues);
+                self.generate_scheme(palette, &palette_gen, theme, monochrome)
+            })
+            .collect())
+    }
+
+    fn load_image(&self, image_path: &PathBuf) -> Result<RgbImage> {
+        // Sniff the format from the file contents, so a misnamed extension still loads
+        let reader = ImageReader::open(image_path)
+            .with_context(|| format!("Failed to open image {}", image_path.display()))?
+            .with_guessed_format()
+            .with_context(|| format!("Failed to read image {}", image_path.display()))?;
+
+        let format = match reader.format() {
+            #[cfg(not(feature = "avif"))]
+            Some(ImageFormat::Avif) => anyhow::bail!(
+                "AVIF images need iro built with the avif feature, or convert {} to png, jpg or webp",
+                image_path.display()
+            ),
+            Some(format) => format,
+            None => anyhow::bail!(
+                "Unsupported image format for {} (supported: {})",
+                image_path.display(),
+                WALLPAPER_EXTENSIONS.join(", ")
+            ),
         };
 
+        // Animated GIFs and WebPs decode to their first frame
+        let img = reader.decode().with_context(|| {
+            format!(
+                "Failed to decode {:?} image {}",
+                format,
+                image_path.display()
+            )
+        })?;
+
+        // Average every pixel rather than sampling a grid, so detailed wallpapers give
+        // the same dominant colors each run
+        Ok(downsample(
+            &img.to_rgb8(),
+            self.config.palette.sample_size(),
+        ))
+    }
+
+    /// Build a scheme around a single color instead of a wallpaper. The color becomes
+    /// the accent and the rest of the palette is spread around the hue wheel from it.
+    pub fn scheme_from_color(&self, color: &str, theme: &str) -> Result<ColorScheme> {
+        let seed = hex_to_rgb(color).with_context(|| format!("Invalid color: {}", color))?;
+        let theme = self.resolved_theme(theme, std::iter::once(&seed));
+        let seed_hsl: Hsl = Srgb::new(seed[0], seed[1], seed[2])
+            .into_format::<f32>()
+            .into_color();
+
+        // The seed stands in for the whole "image", the rotations cover none of it
+        let seed_colors: Vec<(Rgb<u8>, f32)> = std::iter::once((seed, 1.0))
+            .chain((1..12).map(|step| {
+                let mut hsl = seed_hsl;
+                hsl.hue = (seed_hsl.hue.into_positive_degrees() + step as f32 * 30.0).into();
+                let rgb: Srgb = hsl.into_color();
+                let rgb = rgb.into_format::<u8>();
+                (Rgb([rgb.red, rgb.green, rgb.blue]), 0.0)
+            }))
+            .collect();
+
+        let palette_gen = self.palette_generator(&self.config.palette.style);
+        let mut color_scheme = self.generate_scheme(seed_colors, &palette_gen, theme, false);
+        color_scheme.accent = format!("#{:02x}{:02x}{:02x}", seed[0], seed[1], seed[2]);
+
         Ok(color_scheme)
     }
 
+    fn generate_scheme(
+        &self,
+        palette: Vec<(Rgb<u8>, f32)>,
+        palette_gen: &PaletteGenerator,
+        theme: &str,
+        monochrome: bool,
+    ) -> ColorScheme {
+        let (dominant_colors, areas): (Vec<Rgb<u8>>, Vec<f32>) = palette.into_iter().unzip();
+
+        // "auto" resolves to a concrete style once we know the wallpaper's temperature
+        let style_name = if self.config.palette.style == "auto" {
+            let resolved = self.detect_temperature_style(&dominant_colors, palette_gen);
+            self.note(ExtractionNote::AutoStyle(resolved.to_string()));
+            resolved
+        } else {
+            self.config.palette.style.as_str()
+        };
+        let palette_gen = self.palette_generator(style_name);
+
+        // Synthesized hues were asked for, so only a plain monochrome palette goes grey
+        let grayscale = monochrome && self.config.palette.min_distinct_hues == 0;
+        if grayscale {
+            self.note(ExtractionNote::Monochrome);
+        }
+
+        // Generate color scheme based on theme
+        match theme {
+            "light" => self.generate_light_scheme(
+                dominant_colors,
+                &areas,
+                &palette_gen,
+                style_name,
+                grayscale,
+            ),
+            _ => self.generate_dark_scheme(
+                dominant_colors,
+                &areas,
+                &palette_gen,
+                style_name,
+                grayscale,
+            ),
+        }
+    }
+
+    /// Pick "warm" for red/orange/yellow dominant wallpapers and "nord" for cool ones
+    fn detect_temperature_style(
+        &self,
+        colors: &[Rgb<u8>],
+        palette_gen: &PaletteGenerator,
+    ) -> &'static str {
+        let colors: Vec<Srgb> = colors.iter().map(to_srgb).collect();
+        let hue = palette_gen.get_dominant_hue(&colors);
+        if !(75.0..330.0).contains(&hue) {
+            "warm"
+        } else {
+            "nord"
+        }
+    }
+
     fn generate_dark_scheme(
         &self,
         dominant_colors: Vec<Rgb<u8>>,
+        areas: &[f32],
         palette_gen: &PaletteGenerator,
+        style_name: &str,
+        grayscale: bool,
     ) -> ColorScheme {
-        let harmonized = palette_gen.apply_harmony(&dominant_colors);
+        // Every adjustment stage works in f32 and the result is quantized once,
+        // so rounding errors don't pile up between stages
+        let colors: Vec<Srgb> = dominant_colors.iter().map(to_srgb).collect();
+        let harmonized = palette_gen.apply_style_harmony(&colors);
         let hue_boosted = palette_gen.boost_hue_ranges(&harmonized);
         let target_shifted = palette_gen.apply_target_hue_shift(&hue_boosted);
         let with_coverage = palette_gen.ensure_color_coverage(&target_shifted, false);
 
         let enhanced: Vec<Rgb<u8>> = with_coverage
             .iter()
-            .map(|c| palette_gen.adjust_with_style(c, false))
+            .map(|c| quantize(palette_gen.adjust_with_style(c, false)))
             .collect();
 
         let background_color = match self.config.theme.dark_background_style.as_str() {
+            "extracted" if grayscale => "#000000".to_string(),
             "extracted" => {
                 let bg = palette_gen.generate_background_with_tint(&enhanced, false);
                 format!("#{:02x}{:02x}{:02x}", bg[0], bg[1], bg[2])
             }
+            // Parsed and written back, so #rgb shorthand comes out as #rrggbb
             "custom" => self
                 .config
                 .theme
                 .dark_background_custom
                 .as_deref()
-                .unwrap_or("#1e1e2e")
-                .to_string(),
+                .and_then(|hex| hex_to_rgb(hex).ok())
+                .map(|bg| rgb_to_hex(&bg))
+                .unwrap_or_else(|| "#1e1e2e".to_string()),
             _ => "#1e1e2e".to_string(),
         };
 
         let foreground_color = {
-            let bg = self
-                .hex_to_rgb(&background_color)
-                .unwrap_or(Rgb([30, 30, 46]));
+            let bg = hex_to_rgb(&background_color).unwrap_or(Rgb([30, 30, 46]));
             let fg = palette_gen.generate_foreground(&bg, false);
             format!("#{:02x}{:02x}{:02x}", fg[0], fg[1], fg[2])
         };
 
         // Generate intelligent terminal colors based on hue mapping
-        let terminal_colors = self.generate_terminal_colors(
-            &enhanced,
-            &background_color,
-            &foreground_color,
-            palette_gen,
-            false,
-        );
+        let mut terminal_colors = if grayscale {
+            grayscale_ramp(&background_color, &foreground_color, false)
+        } else {
+            self.generate_terminal_colors(
+                &enhanced,
+                &background_color,
+                &foreground_color,
+                palette_gen,
+                style_name,
+                false,
+            )
+        };
+        self.apply_pins(&mut terminal_colors, &enhanced, style_name, false);
 
         // Pick most vibrant colors for accent and secondary
         let mut sorted_by_vibrance: Vec<_> = enhanced
             .iter()
-            .map(|c| (c, self.calculate_vibrance(c)))
+            .enumerate()
+            .map(|(i, c)| (c, self.accent_score(c, i, areas)))
             .collect();
         sorted_by_vibrance.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
@@ -106,12 +489,18 @@ impl ColorExtractor {
             .map(|(c, _)| *c)
             .find(|c| self.color_distance_simple(c, accent_color) > 80.0)
             .unwrap_or(sorted_by_vibrance[1.min(sorted_by_vibrance.len() - 1)].0);
-
-        // Generate surface color
-        let surface_color = self
-            .hex_to_rgb(&background_color)
-            .map(|c| palette_gen.adjust_brightness(&c, 1.2))
-            .unwrap_or(Rgb([49, 50, 68]));
+        let (accent_color, secondary_color) =
+            self.harmonize_accents(*accent_color, *secondary_color, palette_gen);
+        let accent_color = self.saturate_accent(accent_color);
+        let secondary_color = self.saturate_accent(secondary_color);
+
+        // Generate surface color. Scaling can't lift pure black (the grayscale anchor
+        // or a custom background), so that gets a fixed grey step above it.
+        let surface_color = match hex_to_rgb(&background_color) {
+            Ok(Rgb([0, 0, 0])) => Rgb([31, 31, 31]),
+            Ok(c) => palette_gen.adjust_brightness(&c, 1.2),
+            Err(_) => Rgb([49, 50, 68]),
+        };
 
         ColorScheme {
             background: background_color,
@@ -136,54 +525,68 @@ impl ColorExtractor {
     fn generate_light_scheme(
         &self,
         dominant_colors: Vec<Rgb<u8>>,
+        areas: &[f32],
         palette_gen: &PaletteGenerator,
+        style_name: &str,
+        grayscale: bool,
     ) -> ColorScheme {
-        let harmonized = palette_gen.apply_harmony(&dominant_colors);
+        // Every adjustment stage works in f32 and the result is quantized once,
+        // so rounding errors don't pile up between stages
+        let colors: Vec<Srgb> = dominant_colors.iter().map(to_srgb).collect();
+        let harmonized = palette_gen.apply_style_harmony(&colors);
         let hue_boosted = palette_gen.boost_hue_ranges(&harmonized);
         let target_shifted = palette_gen.apply_target_hue_shift(&hue_boosted);
         let with_coverage = palette_gen.ensure_color_coverage(&target_shifted, true);
 
         let enhanced: Vec<Rgb<u8>> = with_coverage
             .iter()
-            .map(|c| palette_gen.adjust_with_style(c, true))
+            .map(|c| quantize(palette_gen.adjust_with_style(c, true)))
             .collect();
 
         let background_color = match self.config.theme.light_background_style.as_str() {
+            "extracted" if grayscale => "#ffffff".to_string(),
             "extracted" => {
                 let bg = palette_gen.generate_background_with_tint(&enhanced, true);
                 format!("#{:02x}{:02x}{:02x}", bg[0], bg[1], bg[2])
             }
+            // Parsed and written back, so #rgb shorthand comes out as #rrggbb
             "custom" => self
                 .config
                 .theme
                 .light_background_custom
                 .as_deref()
-                .unwrap_or("#eff1f5")
-                .to_string(),
+                .and_then(|hex| hex_to_rgb(hex).ok())
+                .map(|bg| rgb_to_hex(&bg))
+                .unwrap_or_else(|| "#eff1f5".to_string()),
             _ => "#eff1f5".to_string(),
         };
 
         let foreground_color = {
-            let bg = self
-                .hex_to_rgb(&background_color)
-                .unwrap_or(Rgb([239, 241, 245]));
+            let bg = hex_to_rgb(&background_color).unwrap_or(Rgb([239, 241, 245]));
             let fg = palette_gen.generate_foreground(&bg, true);
             format!("#{:02x}{:02x}{:02x}", fg[0], fg[1], fg[2])
         };
 
         // Generate intelligent terminal colors based on hue mapping
-        let terminal_colors = self.generate_terminal_colors(
-            &enhanced,
-            &background_color,
-            &foreground_color,
-            palette_gen,
-            true,
-        );
+        let mut terminal_colors = if grayscale {
+            grayscale_ramp(&background_color, &foreground_color, true)
+        } else {
+            self.generate_terminal_colors(
+                &enhanced,
+                &background_color,
+                &foreground_color,
+                palette_gen,
+                style_name,
+                true,
+            )
+        };
+        self.apply_pins(&mut terminal_colors, &enhanced, style_name, true);
 
         // Pick most vibrant colors for accent and secondary - avoid cloning
         let mut sorted_by_vibrance: Vec<_> = enhanced
             .iter()
-            .map(|c| (c, self.calculate_vibrance(c)))
+            .enumerate()
+            .map(|(i, c)| (c, self.accent_score(c, i, areas)))
             .collect();
         sorted_by_vibrance.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
@@ -194,10 +597,13 @@ impl ColorExtractor {
             .map(|(c, _)| *c)
             .find(|c| self.color_distance_simple(c, accent_color) > 80.0)
             .unwrap_or(sorted_by_vibrance[1.min(sorted_by_vibrance.len() - 1)].0);
+        let (accent_color, secondary_color) =
+            self.harmonize_accents(*accent_color, *secondary_color, palette_gen);
+        let accent_color = self.saturate_accent(accent_color);
+        let secondary_color = self.saturate_accent(secondary_color);
 
         // Generate surface color
-        let surface_color = self
-            .hex_to_rgb(&background_color)
+        let surface_color = hex_to_rgb(&background_color)
             .map(|c| palette_gen.adjust_brightness(&c, 0.92))
             .unwrap_or(Rgb([230, 233, 239]));
 
@@ -227,57 +633,42 @@ impl ColorExtractor {
         background: &str,
         foreground: &str,
         palette_gen: &PaletteGenerator,
+        style_name: &str,
         is_light: bool,
     ) -> Vec<String> {
-        let style = PaletteStyle::from_name(&self.config.palette.style);
+        let style = self.config.palette.resolve_style(style_name);
 
         let mut terminal_colors = Vec::with_capacity(16);
 
         terminal_colors.push(background.to_string());
 
-        let hue_ranges = self.get_style_hue_ranges(&style);
-
-        let mut base_colors = Vec::with_capacity(6);
-        for (hue_start, hue_end, target_hue) in hue_ranges.iter() {
-            let best_color = self.find_color_in_hue_range(colors, *hue_start, *hue_end);
-
-            let color = if let Some(c) = best_color {
-                self.shift_toward_target_hue(&c, *target_hue, 0.3)
-            } else {
-                self.generate_color_at_hue(*target_hue, is_light)
-            };
+        let base_colors = if self.config.palette.semantic_ansi {
+            self.semantic_base_colors(colors, is_light)
+        } else if self.config.palette.stable_ordering {
+            self.hue_sorted_base_colors(colors, is_light)
+        } else {
+            let hue_ranges = self.get_style_hue_ranges(style_name, &style);
 
-            base_colors.push(color);
-        }
+            let mut base_colors = Vec::with_capacity(6);
+            for (hue_start, hue_end, target_hue) in hue_ranges.iter() {
+                let best_color = self.find_color_in_hue_range(colors, *hue_start, *hue_end);
 
-        for color in base_colors.iter() {
-            let rgb = Srgb::new(
-                color[0] as f32 / 255.0,
-                color[1] as f32 / 255.0,
-                color[2] as f32 / 255.0,
-            );
-            let mut hsl: Hsl = rgb.into_color();
-
-            let sat_mult = if is_light {
-                style.light_saturation * 2.5
-            } else {
-                style.dark_saturation * 2.5
-            };
+                let color = if let Some(c) = best_color {
+                    self.shift_toward_target_hue(&c, *target_hue, 0.3)
+                } else {
+                    self.generate_color_at_hue(*target_hue, is_light)
+                };
 
-            if is_light {
-                hsl.saturation = (hsl.saturation * sat_mult).clamp(0.5, 0.85);
-                hsl.lightness = (hsl.lightness * 0.75).clamp(0.35, 0.55);
-            } else {
-                hsl.saturation = (hsl.saturation * sat_mult).clamp(0.55, 0.9);
-                hsl.lightness = (hsl.lightness * 1.15).clamp(0.50, 0.70);
+                base_colors.push(color);
             }
+            base_colors
+        };
 
-            let rgb_out: Srgb = hsl.into_color();
+        for color in base_colors.iter() {
+            let rgb_out = self.normal_tone(color, &style, is_light);
             terminal_colors.push(format!(
                 "#{:02x}{:02x}{:02x}",
-                (rgb_out.red * 255.0) as u8,
-                (rgb_out.green * 255.0) as u8,
-                (rgb_out.blue * 255.0) as u8
+                rgb_out[0], rgb_out[1], rgb_out[2]
             ));
         }
 
@@ -288,38 +679,18 @@ impl ColorExtractor {
 
         let hue_shifts = [3.0_f32, -3.0, 5.0, -5.0, 4.0, -4.0];
         for i in 1..=6 {
-            if let Ok(rgb) = self.hex_to_rgb(&terminal_colors[i]) {
-                let rgb_srgb = Srgb::new(
-                    rgb[0] as f32 / 255.0,
-                    rgb[1] as f32 / 255.0,
-                    rgb[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb_srgb.into_color();
-
-                hsl.hue += hue_shifts[i - 1];
-
-                if is_light {
-                    hsl.saturation = (hsl.saturation * 1.15).min(0.95);
-                    hsl.lightness = (hsl.lightness * 0.85).clamp(0.30, 0.50);
-                } else {
-                    hsl.saturation = (hsl.saturation * 1.2).min(0.95);
-                    hsl.lightness = (hsl.lightness * 1.25).clamp(0.60, 0.85);
-                }
-
-                let rgb_out: Srgb = hsl.into_color();
+            if let Ok(rgb) = hex_to_rgb(&terminal_colors[i]) {
+                let rgb_out = self.bright_tone(&rgb, hue_shifts[i - 1], is_light);
                 terminal_colors.push(format!(
                     "#{:02x}{:02x}{:02x}",
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8
+                    rgb_out[0], rgb_out[1], rgb_out[2]
                 ));
             } else {
                 terminal_colors.push(terminal_colors[i].clone());
             }
         }
 
-        let bright_fg = self
-            .hex_to_rgb(foreground)
+        let bright_fg = hex_to_rgb(foreground)
             .map(|c| {
                 if is_light {
                     palette_gen.adjust_brightness(&c, 0.7)
@@ -340,10 +711,8 @@ impl ColorExtractor {
         terminal_colors
     }
 
-    fn get_style_hue_ranges(&self, style: &PaletteStyle) -> [(f32, f32, f32); 6] {
-        let style_name = &self.config.palette.style;
-
-        match style_name.as_str() {
+    fn get_style_hue_ranges(&self, style_name: &str, style: &PaletteStyle) -> [(f32, f32, f32); 6] {
+        match style_name {
             "kawaii" => [
                 (330.0, 30.0, 350.0),
                 (30.0, 90.0, 50.0),
@@ -416,11 +785,7 @@ impl ColorExtractor {
     }
 
     fn shift_toward_target_hue(&self, color: &Rgb<u8>, target_hue: f32, strength: f32) -> Rgb<u8> {
-        let rgb = Srgb::new(
-            color[0] as f32 / 255.0,
-            color[1] as f32 / 255.0,
-            color[2] as f32 / 255.0,
-        );
+        let rgb = to_srgb(color);
         let mut hsl: Hsl = rgb.into_color();
         let current_hue = hsl.hue.into_positive_degrees();
 
@@ -434,11 +799,7 @@ impl ColorExtractor {
         hsl.hue += diff * strength;
 
         let rgb_out: Srgb = hsl.into_color();
-        Rgb([
-            (rgb_out.red * 255.0) as u8,
-            (rgb_out.green * 255.0) as u8,
-            (rgb_out.blue * 255.0) as u8,
-        ])
+        quantize(rgb_out)
     }
 
     fn generate_style_comment_color(
@@ -447,17 +808,13 @@ impl ColorExtractor {
         style: &PaletteStyle,
         is_light: bool,
     ) -> String {
-        let bg = self.hex_to_rgb(background).unwrap_or(if is_light {
+        let bg = hex_to_rgb(background).unwrap_or(if is_light {
             Rgb([239, 241, 245])
         } else {
             Rgb([30, 30, 46])
         });
 
-        let bg_rgb = Srgb::new(
-            bg[0] as f32 / 255.0,
-            bg[1] as f32 / 255.0,
-            bg[2] as f32 / 255.0,
-        );
+        let bg_rgb = to_srgb(&bg);
         let bg_hsl: Hsl = bg_rgb.into_color();
 
         let warmth = style.warmth_shift;
@@ -474,13 +831,196 @@ impl ColorExtractor {
             comment_hsl.saturation = (bg_hsl.saturation + 0.10).min(0.30);
         }
 
-        let result: Srgb = comment_hsl.into_color();
-        format!(
-            "#{:02x}{:02x}{:02x}",
-            (result.red * 255.0) as u8,
-            (result.green * 255.0) as u8,
-            (result.blue * 255.0) as u8
-        )
+        let result = quantize(comment_hsl.into_color());
+        format!("#{:02x}{:02x}{:02x}", result[0], result[1], result[2])
+    }
+
+    /// Saturation and lightness of the normal terminal colors (color1 to color6)
+    fn normal_tone(&self, color: &Rgb<u8>, style: &PaletteStyle, is_light: bool) -> Rgb<u8> {
+        let rgb = to_srgb(color);
+        let mut hsl: Hsl = rgb.into_color();
+
+        let sat_mult = if is_light {
+            style.light_saturation * 2.5
+        } else {
+            style.dark_saturation * 2.5
+        };
+
+        if is_light {
+            hsl.saturation = (hsl.saturation * sat_mult).clamp(0.5, 0.85);
+            hsl.lightness = (hsl.lightness * 0.75).clamp(0.35, 0.55);
+        } else {
+            hsl.saturation = (hsl.saturation * sat_mult).clamp(0.55, 0.9);
+            hsl.lightness = (hsl.lightness * 1.15).clamp(0.50, 0.70);
+        }
+
+        quantize(hsl.into_color())
+    }
+
+    /// The bright variant (color9 to color14) of a normal terminal color
+    fn bright_tone(&self, color: &Rgb<u8>, hue_shift: f32, is_light: bool) -> Rgb<u8> {
+        let rgb_srgb = to_srgb(color);
+
+        // Same moves in OKLCh, with the lightness bounds on its scale
+        if self.config.palette.brightness_space == BrightnessSpace::Oklab {
+            let mut lch: Oklch = rgb_srgb.into_color();
+            lch.hue += hue_shift;
+            if is_light {
+                lch.chroma *= 1.15;
+                lch.l = (lch.l * 0.85).clamp(0.45, 0.62);
+            } else {
+                lch.chroma *= 1.2;
+                lch.l = (lch.l * 1.25).clamp(0.70, 0.90);
+            }
+            return oklch_to_rgb(lch);
+        }
+
+        let mut hsl: Hsl = rgb_srgb.into_color();
+
+        hsl.hue += hue_shift;
+
+        if is_light {
+            hsl.saturation = (hsl.saturation * 1.15).min(0.95);
+            hsl.lightness = (hsl.lightness * 0.85).clamp(0.30, 0.50);
+        } else {
+            hsl.saturation = (hsl.saturation * 1.2).min(0.95);
+            hsl.lightness = (hsl.lightness * 1.25).clamp(0.60, 0.85);
+        }
+
+        quantize(hsl.into_color())
+    }
+
+    /// Overwrite the `[palette.pins]` slots. Hex pins are used as given; family pins
+    /// take the extracted color nearest the family's hue, pulled into the family if it
+    /// lies outside, and toned like the normal or bright color they replace.
+    fn apply_pins(
+        &self,
+        terminal_colors: &mut [String],
+        colors: &[Rgb<u8>],
+        style_name: &str,
+        is_light: bool,
+    ) {
+        if self.config.palette.pins.is_empty() {
+            return;
+        }
+
+        let style = self.config.palette.resolve_style(style_name);
+        let hue_ranges = self.get_style_hue_ranges(style_name, &style);
+
+        for (key, pin) in &self.config.palette.pins {
+            // Unknown keys were reported when the extractor was created
+            let Some(slot) = Pin::slot(key) else {
+                continue;
+            };
+
+            let color = match *pin {
+                Pin::Hex(rgb) => rgb,
+                Pin::Family(family) => {
+                    let (hue_start, hue_end, target_hue) = hue_ranges[family];
+                    let color = match self.find_color_nearest_hue(colors, target_hue) {
+                        Some(c) if hue_in_range(&c, hue_start, hue_end) => c,
+                        Some(c) => self.shift_toward_target_hue(&c, target_hue, 1.0),
+                        None => self.generate_color_at_hue(target_hue, is_light),
+                    };
+                    let normal = self.normal_tone(&color, &style, is_light);
+                    if slot >= 8 {
+                        self.bright_tone(&normal, 0.0, is_light)
+                    } else {
+                        normal
+                    }
+                }
+            };
+            terminal_colors[slot] = format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+        }
+    }
+
+    /// color1 to color6 for `palette.semantic_ansi`: every extracted color goes to the
+    /// ANSI slot whose hue it's closest to, the most vibrant winning a contested slot.
+    /// Empty slots get the nearest extracted color turned to the slot's hue.
+    fn semantic_base_colors(&self, colors: &[Rgb<u8>], is_light: bool) -> Vec<Rgb<u8>> {
+        let mut slots: [Option<(Rgb<u8>, f32)>; 6] = [None; 6];
+        for color in colors {
+            let hsl: Hsl = to_srgb(color).into_color();
+            // Greys have no meaningful hue
+            if hsl.saturation < 0.1 {
+                continue;
+            }
+            let hue = hsl.hue.into_positive_degrees();
+            let slot = (0..ANSI_HUES.len())
+                .min_by(|&a, &b| {
+                    hue_distance(hue, ANSI_HUES[a]).total_cmp(&hue_distance(hue, ANSI_HUES[b]))
+                })
+                .unwrap_or_default();
+            let score = hsl.saturation * self.calculate_vibrance(color);
+            if slots[slot].is_none_or(|(_, best)| score > best) {
+                slots[slot] = Some((*color, score));
+            }
+        }
+
+        slots
+            .iter()
+            .zip(ANSI_HUES)
+            .map(|(slot, hue)| match slot {
+                Some((color, _)) => *color,
+                None => match self.find_color_nearest_hue(colors, hue) {
+                    Some(c) => self.shift_toward_target_hue(&c, hue, 1.0),
+                    None => self.generate_color_at_hue(hue, is_light),
+                },
+            })
+            .collect()
+    }
+
+    /// color1 to color6 for `palette.stable_ordering`: the six most common colors, kept
+    /// as they are and sorted around the hue wheel from red, so similar wallpapers put
+    /// similar hues in the same slot. Greys come last, darkest first.
+    fn hue_sorted_base_colors(&self, colors: &[Rgb<u8>], is_light: bool) -> Vec<Rgb<u8>> {
+        // Style adjustments can merge close colors, so skip repeats
+        let mut base_colors: Vec<Rgb<u8>> = Vec::with_capacity(6);
+        for color in colors {
+            if base_colors.len() == 6 {
+                break;
+            }
+            if !base_colors.contains(color) {
+                base_colors.push(*color);
+            }
+        }
+        // Too few colors extracted: make up the rest at the ANSI hues
+        for hue in ANSI_HUES.iter().skip(base_colors.len()) {
+            base_colors.push(self.generate_color_at_hue(*hue, is_light));
+        }
+
+        let sort_key = |color: &Rgb<u8>| {
+            let hsl: Hsl = to_srgb(color).into_color();
+            // Start the wheel where the red slot's range does, so 350° sorts as red
+            let hue = if hsl.saturation < 0.1 {
+                f32::INFINITY
+            } else {
+                (hsl.hue.into_positive_degrees() + 15.0)
+                    .rem_euclid(360.0)
+                    .round()
+            };
+            (hue, hsl.lightness)
+        };
+        base_colors.sort_by(|a, b| {
+            let (a, b) = (sort_key(a), sort_key(b));
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        });
+        base_colors
+    }
+
+    /// The colored (not greyish) color whose hue is closest to `hue`
+    fn find_color_nearest_hue(&self, colors: &[Rgb<u8>], hue: f32) -> Option<Rgb<u8>> {
+        colors
+            .iter()
+            .filter_map(|color| {
+                let hsl: Hsl = to_srgb(color).into_color();
+                if hsl.saturation < 0.1 {
+                    return None;
+                }
+                Some((*color, hue_distance(hsl.hue.into_positive_degrees(), hue)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(color, _)| color)
     }
 
     /// Find the best color in a hue range
@@ -493,23 +1033,8 @@ impl ColorExtractor {
         let mut best_color: Option<(Rgb<u8>, f32)> = None;
 
         for color in colors {
-            let rgb = Srgb::new(
-                color[0] as f32 / 255.0,
-                color[1] as f32 / 255.0,
-                color[2] as f32 / 255.0,
-            );
-            let hsl: Hsl = rgb.into_color();
-            let hue = hsl.hue.into_positive_degrees();
-
-            // Check if hue is in range (handle wraparound)
-            let in_range = if hue_start > hue_end {
-                // Wraparound case (e.g., red: 345-30)
-                hue >= hue_start || hue <= hue_end
-            } else {
-                hue >= hue_start && hue <= hue_end
-            };
-
-            if in_range {
+            if hue_in_range(color, hue_start, hue_end) {
+                let hsl: Hsl = to_srgb(color).into_color();
                 let score = hsl.saturation * self.calculate_vibrance(color);
                 if best_color.is_none() || score > best_color.unwrap().1 {
                     best_color = Some((*color, score));
@@ -533,11 +1058,45 @@ impl ColorExtractor {
         };
 
         let rgb: Srgb = hsl.into_color();
-        Rgb([
-            (rgb.red * 255.0) as u8,
-            (rgb.green * 255.0) as u8,
-            (rgb.blue * 255.0) as u8,
-        ])
+        quantize(rgb)
+    }
+
+    /// Replace the extracted secondary with the accent's partner on the hue wheel,
+    /// unless `palette.harmony` is "natural"
+    fn harmonize_accents(
+        &self,
+        accent: Rgb<u8>,
+        secondary: Rgb<u8>,
+        palette_gen: &PaletteGenerator,
+    ) -> (Rgb<u8>, Rgb<u8>) {
+        let wheel = palette_gen.apply_harmony(accent, self.config.palette.harmony);
+        (accent, wheel.get(1).copied().unwrap_or(secondary))
+    }
+
+    /// Raise `color`'s HSL saturation to `min_accent_saturation`, keeping its hue and
+    /// lightness, so accents on muted wallpapers still stand out. Greys have no hue
+    /// to keep and are left alone.
+    fn saturate_accent(&self, color: Rgb<u8>) -> Rgb<u8> {
+        let floor = self.config.palette.min_accent_saturation.clamp(0.0, 1.0);
+        let mut hsl: Hsl = to_srgb(&color).into_color();
+        if hsl.saturation >= floor || hsl.saturation < GREY_SATURATION {
+            return color;
+        }
+        hsl.saturation = floor;
+        quantize(hsl.into_color())
+    }
+
+    /// Mix vibrance with the area the `index`th palette color covers, per
+    /// `vibrance_area_balance`. Colors added after extraction count as zero area.
+    fn accent_score(&self, color: &Rgb<u8>, index: usize, areas: &[f32]) -> f32 {
+        let balance = self.config.palette.vibrance_area_balance.clamp(0.0, 1.0);
+        let largest = areas.iter().copied().fold(0.0_f32, f32::max);
+        let area = match areas.get(index) {
+            Some(area) if largest > 0.0 => area / largest,
+            _ => 0.0,
+        };
+
+        balance * self.calculate_vibrance(color) + (1.0 - balance) * area
     }
 
     #[inline]
@@ -557,12 +1116,243 @@ impl ColorExtractor {
         let db = (c1[2] as i16 - c2[2] as i16) as f32;
         (dr * dr + dg * dg + db * db).sqrt()
     }
+}
+
+/// Degrees between two hues, going the short way around the wheel
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let diff = (a - b).rem_euclid(360.0);
+    diff.min(360.0 - diff)
+}
+
+/// Whether a color's hue lies in `hue_start..=hue_end`, which may wrap past 360°
+/// (e.g. red: 345-30)
+fn hue_in_range(color: &Rgb<u8>, hue_start: f32, hue_end: f32) -> bool {
+    let hsl: Hsl = to_srgb(color).into_color();
+    let hue = hsl.hue.into_positive_degrees();
+    if hue_start > hue_end {
+        hue >= hue_start || hue <= hue_end
+    } else {
+        hue >= hue_start && hue <= hue_end
+    }
+}
+
+/// Resolve the "auto" theme to "light" for bright images and "dark" for dark ones;
+/// any other theme is returned as is
+fn resolve_theme<'a>(theme: &'a str, pixels: impl Iterator<Item = &'a Rgb<u8>>) -> &'a str {
+    if theme != "auto" {
+        return theme;
+    }
+
+    let (sum, count) = pixels.fold((0.0, 0_usize), |(sum, count), pixel| {
+        (sum + relative_luminance(pixel), count + 1)
+    });
+    if sum / count.max(1) as f32 > AUTO_LIGHT_LUMINANCE {
+        "light"
+    } else {
+        "dark"
+    }
+}
+
+/// Whether `img` is (nearly) without color, like a black and white photo. Chroma
+/// rather than HSL saturation, which runs high for barely tinted near-black pixels.
+fn is_monochrome(img: &RgbImage) -> bool {
+    let (sum, count) = img.pixels().fold((0.0, 0_usize), |(sum, count), pixel| {
+        let max = pixel.0.iter().max().copied().unwrap_or_default();
+        let min = pixel.0.iter().min().copied().unwrap_or_default();
+        (sum + (max - min) as f32 / 255.0, count + 1)
+    });
+    sum / (count.max(1) as f32) < MONOCHROME_CHROMA
+}
+
+/// Terminal colors for a monochrome wallpaper: grey steps between the background
+/// and a pure black or white anchor in color15, darkest first on dark themes
+fn grayscale_ramp(background: &str, foreground: &str, is_light: bool) -> Vec<String> {
+    let grey = |lightness: f32| {
+        let lightness = if is_light { 1.0 - lightness } else { lightness };
+        let value = (lightness * 255.0).round() as u8;
+        rgb_to_hex(&Rgb([value, value, value]))
+    };
+
+    let mut terminal_colors = Vec::with_capacity(16);
+    terminal_colors.push(background.to_string());
+    terminal_colors.extend((0..6).map(|i| grey(0.40 + i as f32 * 0.07)));
+    terminal_colors.push(foreground.to_string());
+    terminal_colors.push(grey(0.30));
+    terminal_colors.extend((0..6).map(|i| grey(0.50 + i as f32 * 0.08)));
+    terminal_colors.push(grey(1.0));
+    terminal_colors
+}
 
-    fn hex_to_rgb(&self, hex: &str) -> Result<Rgb<u8>> {
-        let hex = hex.trim_start_matches('#');
-        let r = u8::from_str_radix(&hex[0..2], 16)?;
-        let g = u8::from_str_radix(&hex[2..4], 16)?;
-        let b = u8::from_str_radix(&hex[4..6], 16)?;
-        Ok(Rgb([r, g, b]))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_math::contrast_ratio;
+
+    /// `img` saved as a png in the temp dir
+    fn wallpaper(name: &str, img: RgbImage) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("iro-{}-{}.png", name, std::process::id()));
+        img.save(&path).unwrap();
+        path
+    }
+
+    fn extract(config: IroConfig, name: &str, img: RgbImage, theme: &str) -> ColorScheme {
+        let path = wallpaper(name, img);
+        let scheme = ColorExtractor::new_with_config(config)
+            .without_cache()
+            .extract_colors(&path, theme)
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        scheme
+    }
+
+    fn rgb(hex: &str) -> Rgb<u8> {
+        hex_to_rgb(hex).unwrap()
+    }
+
+    fn hsl(hex: &str) -> Hsl {
+        to_srgb(&rgb(hex)).into_color()
+    }
+
+    fn is_grey(hex: &str) -> bool {
+        let [r, g, b] = rgb(hex).0;
+        r == g && g == b
+    }
+
+    /// Red, green and blue bands
+    fn rgb_bands() -> RgbImage {
+        RgbImage::from_fn(90, 30, |x, _| match x / 30 {
+            0 => Rgb([200, 40, 40]),
+            1 => Rgb([40, 180, 60]),
+            _ => Rgb([50, 70, 200]),
+        })
+    }
+
+    #[test]
+    fn extractor_uses_the_config_it_is_given() {
+        let mut config = IroConfig::default();
+        config.palette.style = "nord".to_string();
+        let extractor = ColorExtractor::new_with_config(config.clone());
+        assert_eq!(extractor.style(), "nord");
+
+        let nord = extract(config, "nord", rgb_bands(), "dark");
+        let lofi = extract(IroConfig::default(), "lofi", rgb_bands(), "dark");
+        assert_eq!(nord.colors.len(), 16);
+        assert_ne!(nord.colors, lofi.colors);
+    }
+
+    #[test]
+    fn auto_theme_follows_the_wallpaper_brightness() {
+        // Mostly one tone, with a blue patch so there is some color to extract
+        let mostly = |value: u8| {
+            RgbImage::from_fn(60, 60, |x, y| {
+                if x < 12 && y < 12 {
+                    Rgb([60, 90, 200])
+                } else {
+                    Rgb([value; 3])
+                }
+            })
+        };
+
+        let light = extract(IroConfig::default(), "white", mostly(245), "auto");
+        let dark = extract(IroConfig::default(), "black", mostly(10), "auto");
+        assert!(relative_luminance(&rgb(&light.background)) > 0.5);
+        assert!(relative_luminance(&rgb(&dark.background)) < 0.1);
+        for scheme in [light, dark] {
+            assert!(contrast_ratio(&rgb(&scheme.foreground), &rgb(&scheme.background)) > 7.0);
+        }
+    }
+
+    #[test]
+    fn notes_are_kept_for_the_caller() {
+        let mut config = IroConfig::default();
+        config.palette.style = "auto".to_string();
+        config
+            .palette
+            .pins
+            .insert("color99".to_string(), Pin::Hex(Rgb([255, 0, 0])));
+        let extractor = ColorExtractor::new_with_config(config).without_cache();
+        assert_eq!(
+            extractor.take_notes(),
+            [ExtractionNote::UnknownPin("color99".to_string())]
+        );
+
+        let path = wallpaper("notes", rgb_bands());
+        extractor.extract_colors(&path, "auto").unwrap();
+        std::fs::remove_file(path).unwrap();
+        let notes = extractor.take_notes();
+        assert!(matches!(notes[0], ExtractionNote::AutoTheme(_)));
+        assert!(matches!(notes[1], ExtractionNote::AutoStyle(_)));
+        assert_eq!(notes.len(), 2);
+        assert!(extractor.take_notes().is_empty());
+    }
+
+    #[test]
+    fn semantic_ansi_puts_colors_in_their_slots() {
+        let mut config = IroConfig::default();
+        config.palette.semantic_ansi = true;
+        let scheme = extract(config, "semantic", rgb_bands(), "dark");
+
+        for (slot, hue) in [(1, 0.0), (2, 120.0), (4, 240.0)] {
+            let color = hsl(&scheme.colors[slot]);
+            let actual = color.hue.into_positive_degrees();
+            assert!(
+                hue_distance(actual, hue) < 30.0,
+                "color{} is {} (hue {}), expected around {}",
+                slot,
+                scheme.colors[slot],
+                actual,
+                hue
+            );
+        }
+    }
+
+    #[test]
+    fn black_and_white_checkerboard_gets_a_grayscale_ramp() {
+        let checkerboard = || {
+            RgbImage::from_fn(64, 64, |x, y| {
+                if (x / 8 + y / 8) % 2 == 0 {
+                    Rgb([0, 0, 0])
+                } else {
+                    Rgb([255, 255, 255])
+                }
+            })
+        };
+
+        for (theme, anchor) in [("dark", "#ffffff"), ("light", "#000000")] {
+            let scheme = extract(IroConfig::default(), theme, checkerboard(), theme);
+            assert!(
+                scheme.colors.iter().all(|color| is_grey(color)),
+                "{} colors aren't grey: {:?}",
+                theme,
+                scheme.colors
+            );
+            assert_eq!(scheme.colors[15], anchor);
+        }
+    }
+
+    #[test]
+    fn muted_wallpaper_still_gets_a_saturated_accent() {
+        // Beige and taupe tones, all well below the accent saturation floor
+        let muted = || {
+            RgbImage::from_fn(64, 64, |x, y| {
+                let shade = ((x + y) / 2) as u8;
+                Rgb([120 + shade, 110 + shade, 100 + shade])
+            })
+        };
+        let floor = IroConfig::default().palette.min_accent_saturation;
+
+        for theme in ["dark", "light"] {
+            let scheme = extract(IroConfig::default(), theme, muted(), theme);
+            for color in [&scheme.accent, &scheme.secondary] {
+                let saturation = hsl(color).saturation;
+                assert!(
+                    saturation >= floor - 0.02,
+                    "{} {} has saturation {}",
+                    theme,
+                    color,
+                    saturation
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+use palette::convert::IntoColorUnclamped;
+use palette::{LinSrgb, Oklch, Srgb};
+
+/// WCAG relative luminance of an sRGB color (0.0 = black, 1.0 = white)
+pub fn relative_luminance(color: &Rgb<u8>) -> f32 {
+    let linearize = |channel: u8| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * linearize(color[0]) + 0.7152 * linearize(color[1]) + 0.0722 * linearize(color[2])
+}
+
+/// WCAG contrast ratio between two colors, from 1.0 (identical) to 21.0 (black on white)
+pub fn contrast_ratio(a: &Rgb<u8>, b: &Rgb<u8>) -> f32 {
+    let la = relative_luminance(a);
+    let lb = relative_luminance(b);
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Parse `#rgb`, `#rrggbb` or `#rrggbbaa` (CSS order, the alpha is dropped), with
+/// or without the `#`
+pub fn hex_to_rgb(hex: &str) -> Result<Rgb<u8>> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid hex color: {}", hex);
+    }
+
+    let channel = |start: usize, len: usize| u8::from_str_radix(&digits[start..start + len], 16);
+    match digits.len() {
+        // Each shorthand nibble doubles, #fa0 is #ffaa00
+        3 => Ok(Rgb([
+            channel(0, 1)? * 17,
+            channel(1, 1)? * 17,
+            channel(2, 1)? * 17,
+        ])),
+        6 | 8 => Ok(Rgb([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?])),
+        len => anyhow::bail!(
+            "Invalid hex color: {} (expected 3, 6 or 8 hex digits, found {})",
+            hex,
+            len
+        ),
+    }
+}
+
+/// `#rrggbb` for an RGB color
+pub fn rgb_to_hex(color: &Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Convert 8-bit RGB into the f32 space the palette math works in
+pub fn to_srgb(color: &Rgb<u8>) -> Srgb {
+    Srgb::new(color[0], color[1], color[2]).into_format()
+}
+
+/// Quantize back to 8-bit RGB, rounding rather than truncating so that an
+/// unchanged color comes back exactly as it went in
+pub fn quantize(color: Srgb) -> Rgb<u8> {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb([
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue),
+    ])
+}
+
+/// Convert to RGB, lowering chroma until the color fits in sRGB, so it keeps its
+/// OKLab lightness and hue instead of shifting when channels are clipped
+pub fn oklch_to_rgb(mut lch: Oklch) -> Rgb<u8> {
+    // Overshooting by less than half an 8-bit step quantizes to the same value as the
+    // edge of the gamut, and float error alone puts in-gamut colors a hair outside
+    let in_gamut = |rgb: &Srgb| {
+        let tolerance = 0.5 / 255.0;
+        [rgb.red, rgb.green, rgb.blue]
+            .iter()
+            .all(|c| (-tolerance..=1.0 + tolerance).contains(c))
+    };
+
+    // The unclamped conversion, as the clamping one would hide what's out of gamut
+    let mut rgb: Srgb = lch.into_color_unclamped();
+    while !in_gamut(&rgb) && lch.chroma > 0.001 {
+        lch.chroma *= 0.95;
+        rgb = lch.into_color_unclamped();
+    }
+    quantize(rgb)
+}
+
+/// Scale `img` down so its longest side is at most `max_side`, averaging each block
+/// of source pixels in linear light. Averaging the gamma-encoded values instead
+/// would darken every edge between light and dark areas. Smaller images are
+/// returned as they are.
+pub fn downsample(img: &RgbImage, max_side: u32) -> RgbImage {
+    let (width, height) = img.dimensions();
+    let longest = width.max(height);
+    if longest <= max_side || max_side == 0 {
+        return img.clone();
+    }
+
+    let out_width = (width as u64 * max_side as u64 / longest as u64).max(1) as u32;
+    let out_height = (height as u64 * max_side as u64 / longest as u64).max(1) as u32;
+
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|channel| to_srgb(&Rgb([channel; 3])).into_linear().red)
+        .collect();
+
+    // Every source pixel falls into exactly one output cell
+    let mut sums = vec![[0.0_f32; 3]; (out_width * out_height) as usize];
+    let mut counts = vec![0_u32; sums.len()];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let cell_x = (x as u64 * out_width as u64 / width as u64) as u32;
+        let cell_y = (y as u64 * out_height as u64 / height as u64) as u32;
+        let cell = (cell_y * out_width + cell_x) as usize;
+        for channel in 0..3 {
+            sums[cell][channel] += to_linear[pixel[channel] as usize];
+        }
+        counts[cell] += 1;
+    }
+
+    RgbImage::from_fn(out_width, out_height, |x, y| {
+        let cell = (y * out_width + x) as usize;
+        let count = counts[cell].max(1) as f32;
+        let [r, g, b] = sums[cell].map(|sum| sum / count);
+        quantize(LinSrgb::new(r, g, b).into_encoding())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+    fn assert_ratio(a: Rgb<u8>, b: Rgb<u8>, expected: f32) {
+        let ratio = contrast_ratio(&a, &b);
+        assert!(
+            (ratio - expected).abs() < 0.01,
+            "{} on {}: {} instead of {}",
+            rgb_to_hex(&a),
+            rgb_to_hex(&b),
+            ratio,
+            expected
+        );
+    }
+
+    #[test]
+    fn contrast_ratio_matches_wcag_pairs() {
+        assert_ratio(BLACK, WHITE, 21.0);
+        assert_ratio(WHITE, WHITE, 1.0);
+        // The greys the WCAG AA and AAA thresholds are usually quoted with
+        assert_ratio(Rgb([0x76, 0x76, 0x76]), WHITE, 4.54);
+        assert_ratio(Rgb([0x77, 0x77, 0x77]), WHITE, 4.48);
+        assert_ratio(Rgb([0x59, 0x59, 0x59]), WHITE, 7.0);
+        assert_ratio(Rgb([0xff, 0x00, 0x00]), WHITE, 4.0);
+        assert_ratio(Rgb([0x00, 0x00, 0xff]), WHITE, 8.59);
+    }
+
+    #[test]
+    fn contrast_ratio_is_symmetric() {
+        let a = Rgb([0x7a, 0xa2, 0xf7]);
+        let b = Rgb([0x1a, 0x1b, 0x26]);
+        assert_eq!(contrast_ratio(&a, &b), contrast_ratio(&b, &a));
+    }
+
+    #[test]
+    fn hex_accepts_every_length() {
+        for hex in ["#fff", "#ffffff", "#ffffffff", "fff", "FFFFFF"] {
+            assert_eq!(hex_to_rgb(hex).unwrap(), WHITE, "{}", hex);
+        }
+        assert_eq!(hex_to_rgb("#fa0").unwrap(), Rgb([0xff, 0xaa, 0x00]));
+        assert_eq!(hex_to_rgb("#7aa2f780").unwrap(), Rgb([0x7a, 0xa2, 0xf7]));
+    }
+
+    #[test]
+    fn hex_rejects_malformed_input() {
+        for hex in [
+            "",
+            "#",
+            "##fff",
+            "#ff",
+            "#ffff",
+            "#fffffff",
+            "#gggggg",
+            "#ff ff ff",
+            "#ffé",
+        ] {
+            assert!(hex_to_rgb(hex).is_err(), "{:?} parsed", hex);
+        }
+    }
+}
@@ -1,28 +1,346 @@
+use crate::color_math::hex_to_rgb;
 use anyhow::{Context, Result};
+use image::Rgb;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Config layout written by this build. Bump it when a field is renamed or changes
+/// meaning, and convert old values in `IroConfig::migrate`.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// `palette.diversity_threshold` every config before version 2 was written with
+const LEGACY_DIVERSITY_THRESHOLD: f32 = 50.0;
+
+/// Set for commands that must not write to ~/.config/iro
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Keep iro's config dir untouched for the rest of the run: a missing config isn't
+/// created, an old one is upgraded in memory only, and missing default templates
+/// are rendered without being written
+pub fn read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+/// Whether `read_only` was called
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IroConfig {
+    /// Layout version the file was written with, 0 for files from before versioning.
+    /// Missing means 0 here, not the `CONFIG_VERSION` the struct default has.
+    #[serde(default)]
+    pub version: u32,
     pub theme: ThemeConfig,
     pub palette: PaletteConfig,
     /// Directory containing wallpaper images
-    #[serde(default = "default_wallpaper_dir")]
     pub wallpaper_dir: String,
+    /// How wallpapers are set
+    pub wallpaper: WallpaperConfig,
+    /// How apps are told to pick up the new colors
+    pub reload: ReloadConfig,
+    /// How app configs are backed up before iro edits them
+    pub backup: BackupConfig,
+    /// Per-app overrides, keyed by output name ("hyprland", "kitty", "waybar", ...)
+    pub outputs: BTreeMap<String, OutputConfig>,
+    /// Outputs to write, keyed like `outputs`; `false` leaves that app alone.
+    /// Outputs not listed stay on.
+    pub targets: BTreeMap<String, bool>,
 }
 
-fn default_wallpaper_dir() -> String {
-    dirs::home_dir()
-        .map(|h| {
-            h.join("Pictures")
-                .join("Wallpaper")
-                .to_string_lossy()
-                .to_string()
-        })
-        .unwrap_or_else(|| "~/Pictures/Wallpaper".to_string())
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WallpaperConfig {
+    /// Program that draws the wallpaper
+    pub backend: WallpaperBackend,
+    /// swww `--transition-type`, e.g. "simple", "fade", "wipe", "grow", "none"
+    pub transition_type: String,
+    /// swww `--transition-duration` in seconds
+    pub transition_duration: f32,
+    /// How the wallpaper is fit to each monitor
+    pub mode: WallpaperMode,
+    /// Also pick up wallpapers in subdirectories of `wallpaper_dir`
+    pub recursive: bool,
+    /// hyprpaper only: unload the wallpapers no monitor shows after each change
+    pub unload_unused: bool,
+}
+
+impl Default for WallpaperConfig {
+    fn default() -> Self {
+        Self {
+            backend: WallpaperBackend::default(),
+            transition_type: "simple".to_string(),
+            transition_duration: 1.0,
+            mode: WallpaperMode::default(),
+            recursive: false,
+            unload_unused: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReloadConfig {
+    /// Shell commands run in order after the configs are written. Unset keeps the
+    /// built-in reload of Hyprland, mako and dunst.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commands: Option<Vec<String>>,
+    /// Waybar bars to start when they aren't running, each with its own config and
+    /// style. Empty starts a single default bar when none is running.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub waybar: Vec<WaybarInstance>,
+}
+
+/// One waybar bar, started as `waybar -c <config> -s <style>`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaybarInstance {
+    /// Bar config passed with `-c`, `~` expanded; unset uses waybar's default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+    /// Stylesheet passed with `-s`, `~` expanded; unset uses waybar's default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// When an app config is copied aside before iro writes to it
+    pub strategy: BackupStrategy,
+    /// Timestamped backups kept per config; older ones are deleted
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            strategy: BackupStrategy::default(),
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStrategy {
+    /// `<name>.conf.iro.bak`, made the first time only, so it keeps the original
+    #[default]
+    Once,
+    /// `<name>.conf.iro.<unix time>.bak` on every write, the newest `keep` kept
+    Timestamped,
+    /// No backups
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallpaperBackend {
+    /// hyprpaper, driven over hyprctl IPC
+    #[default]
+    Hyprpaper,
+    /// swww, with animated transitions
+    Swww,
+    /// swaybg, restarted with the new wallpapers
+    Swaybg,
+}
+
+impl WallpaperBackend {
+    /// Executable the backend needs on PATH
+    pub fn binary(self) -> &'static str {
+        match self {
+            WallpaperBackend::Hyprpaper => "hyprpaper",
+            WallpaperBackend::Swww => "swww",
+            WallpaperBackend::Swaybg => "swaybg",
+        }
+    }
+}
+
+/// How a wallpaper that doesn't match the monitor's aspect ratio is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallpaperMode {
+    /// Fill the monitor, cropping what sticks out
+    #[default]
+    Cover,
+    /// Show the whole image, filling the bars with the scheme's background
+    Contain,
+    /// Repeat the image at its own size
+    Tile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// How iro manages this app's config file, unset keeps the app's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<OutputMode>,
+    /// Where the file is written, for outputs that are iro's own files (css, scss)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// Splice a marked color section into the existing config
+    Inline,
+    /// Render the whole file from the matching template
+    Template,
+    /// Write colors to a separate file and include it from the config
+    Include,
+}
+
+impl fmt::Display for OutputMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            OutputMode::Inline => "inline",
+            OutputMode::Template => "template",
+            OutputMode::Include => "include",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How `PaletteGenerator` picks the candidate colors from an image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteAlgorithm {
+    /// Most frequent colors after quantizing to 16 steps per channel
+    #[default]
+    Histogram,
+    /// Lloyd's k-means in RGB space with `color_count` centroids
+    Kmeans,
+    /// Octree quantization reduced to `color_count` leaves
+    Octree,
+}
+
+impl std::str::FromStr for PaletteAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "histogram" => Ok(PaletteAlgorithm::Histogram),
+            "kmeans" => Ok(PaletteAlgorithm::Kmeans),
+            "octree" => Ok(PaletteAlgorithm::Octree),
+            _ => anyhow::bail!("Unknown palette algorithm: {}", s),
+        }
+    }
+}
+
+/// How `PaletteGenerator` decides whether two candidate colors are too similar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Perceptual CIEDE2000 difference in CIELAB
+    #[default]
+    Lab,
+    /// Perception-weighted RGB difference
+    Rgb,
+}
+
+/// Color space brightness adjustments (surface, bright colors) scale lightness in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessSpace {
+    /// HSL lightness, which shifts saturated colors unevenly
+    #[default]
+    Hsl,
+    /// OKLab lightness, keeping hue and chroma
+    Oklab,
+}
+
+/// How the accent and secondary colors relate on the hue wheel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Harmony {
+    /// The two most vibrant extracted colors
+    #[default]
+    Natural,
+    /// Secondary opposite the accent, 180° away
+    Complementary,
+    /// Secondary a third of the way around the wheel, 120° away
+    Triadic,
+    /// Secondary next to the accent, 30° away
+    Analogous,
+}
+
+/// Hue families a terminal color can be pinned to, in the order of the style hue ranges
+pub const PIN_FAMILIES: [&str; 6] = ["red", "yellow", "green", "cyan", "blue", "magenta"];
+
+/// A `[palette.pins]` value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Pin {
+    /// Always this color
+    Hex(Rgb<u8>),
+    /// The extracted color nearest this `PIN_FAMILIES` hue, kept inside the family
+    Family(usize),
+}
+
+impl Pin {
+    /// Terminal color index of a pin key, "color0" to "color15"
+    pub fn slot(key: &str) -> Option<usize> {
+        key.strip_prefix("color")?
+            .parse()
+            .ok()
+            .filter(|slot| *slot < 16)
+    }
+}
+
+impl TryFrom<String> for Pin {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        if value.starts_with('#') {
+            return Ok(Pin::Hex(hex_to_rgb(&value)?));
+        }
+        let name = value.strip_suffix("-family").unwrap_or(&value);
+        PIN_FAMILIES
+            .iter()
+            .position(|family| *family == name)
+            .map(Pin::Family)
+            .with_context(|| {
+                format!(
+                    "Invalid pin '{}': use a hex color like \"#ff5555\" or one of {}",
+                    value,
+                    PIN_FAMILIES.map(|f| format!("{}-family", f)).join(", ")
+                )
+            })
+    }
+}
+
+impl From<Pin> for String {
+    fn from(pin: Pin) -> Self {
+        match pin {
+            Pin::Hex(rgb) => format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]),
+            Pin::Family(family) => format!("{}-family", PIN_FAMILIES[family]),
+        }
+    }
+}
+
+/// Copy every key of `defaults` that `value` lacks into it, recursing into tables
+fn fill_missing(value: &mut toml::Value, defaults: toml::Value) {
+    let (toml::Value::Table(table), toml::Value::Table(defaults)) = (value, defaults) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match table.get_mut(&key) {
+            Some(existing) => fill_missing(existing, default),
+            None => {
+                table.insert(key, default);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ThemeConfig {
     /// Theme mode: "light" or "dark" or "auto"
     pub mode: String,
@@ -44,30 +362,112 @@ pub struct ThemeConfig {
 
     /// Custom background color (used when light_background_style is "custom")
     pub light_background_custom: Option<String>,
+
+    /// Use an accent → secondary gradient for waybar module backgrounds
+    pub waybar_gradient: bool,
+
+    /// Hyprland window shadow color
+    /// "background" - darkened background, a regular drop shadow
+    /// "accent" - translucent accent, a colored glow
+    /// "none" - leave the shadow color alone
+    pub shadow_color: String,
+
+    /// Also theme the Linux virtual console (writes ~/.config/iro/vtrgb, and the
+    /// console itself when iro runs on one)
+    pub tty_colors: bool,
+
+    /// Opacity of Hyprland's `$surface`/`$surface0` and `$base` variables, from 0.0
+    /// to 1.0. Below 1.0 they are written as `rgba()`, for blurred translucent bars
+    pub surface_alpha: f32,
+    pub background_alpha: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PaletteConfig {
-    /// Palette style preset
-    /// Options: "vibrant", "pastel", "neon", "muted", "catppuccin", "nord", "dracula", "gruvbox", "tokyo-night", "rose-pine"
+    /// Palette style preset, see `PaletteStyle::all_styles` (`iro --list-styles`)
+    /// "auto" picks "warm" or "nord" from the wallpaper's dominant hue
     pub style: String,
 
-    /// Color diversity threshold (higher = more diverse colors required)
-    pub diversity_threshold: f32,
+    /// Color diversity threshold (higher = more diverse colors required). Its scale
+    /// depends on `distance_metric`; unset picks the metric's default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diversity_threshold: Option<f32>,
 
-    /// Saturation boost for dark mode (1.0 = no boost, >1.0 = more saturated)
-    pub dark_saturation: f32,
+    /// Color difference used for the diversity check: "lab" or "rgb"
+    pub distance_metric: DistanceMetric,
 
-    /// Saturation adjustment for light mode
-    pub light_saturation: f32,
+    /// Space the surface, bright colors and bright foreground are lightened or
+    /// darkened in: "hsl" or "oklab"
+    pub brightness_space: BrightnessSpace,
 
-    /// Brightness adjustment for light mode
-    pub light_brightness: f32,
+    /// Replace the style's saturation and brightness for dark and light mode, e.g.
+    /// `dark_saturation = 1.2`. Unset keeps the style's own values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark_saturation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_saturation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark_brightness: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_brightness: Option<f32>,
 
     /// Number of colors to extract from image
     pub color_count: usize,
+
+    /// How the accent is picked: 0.0 = the color covering the most area,
+    /// 1.0 = the most vibrant color, anything between mixes the two
+    pub vibrance_area_balance: f32,
+
+    /// HSL saturation the accent and secondary are raised to when the wallpaper's
+    /// most vibrant colors fall short, keeping their hue (0.0 = off)
+    pub min_accent_saturation: f32,
+
+    /// Synthesize extra hues when the wallpaper has fewer than this many (0 = off)
+    pub min_distinct_hues: usize,
+
+    /// Color extraction algorithm: "histogram", "kmeans" or "octree"
+    pub algorithm: PaletteAlgorithm,
+
+    /// Longest side, in pixels, the wallpaper is averaged down to before extraction.
+    /// Capped at `MAX_SAMPLE_SIZE` to keep extraction fast.
+    pub sample_size: u32,
+
+    /// Pixels with an average channel value outside `[min, max]` are left out of
+    /// extraction, so near-black shadows and blown highlights don't crowd the palette
+    pub brightness_range: [u8; 2],
+
+    /// Derive the secondary color from the accent's hue: "natural", "complementary",
+    /// "triadic" or "analogous"
+    pub harmony: Harmony,
+
+    /// Multipliers on top of the style's contrast, saturation and brightness, for
+    /// nudging a style without defining a new one (`--contrast` etc. override them)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<f32>,
+
+    /// Put each extracted color in the ANSI slot its hue is closest to, so color2 is
+    /// green and color4 blue, instead of filling the slots in hue order
+    pub semantic_ansi: bool,
+
+    /// Fill color1 to color6 with the six most common colors as extracted, sorted by
+    /// hue and then lightness, instead of the most vibrant color in each slot's hue
+    /// range. Ignored with `semantic_ansi`.
+    pub stable_ordering: bool,
+
+    /// Terminal color slots kept to a fixed color or hue family whatever the
+    /// wallpaper, e.g. `color1 = "red-family"` or `color1 = "#ff5555"`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub pins: BTreeMap<String, Pin>,
 }
 
+/// Largest `palette.sample_size` honored
+pub const MAX_SAMPLE_SIZE: u32 = 512;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ColorHarmony {
     Extracted,
@@ -90,11 +490,23 @@ pub struct PaletteStyle {
     pub target_hues: Option<&'static [f32]>,
     pub bg_tint_strength: f32,
     pub color_harmony: ColorHarmony,
+    /// Minimum saturation after scaling, for colors that have a hue at all
+    pub saturation_floor: Option<f32>,
+    /// Maximum saturation after scaling
+    pub saturation_ceiling: Option<f32>,
+    /// Band the lightness is remapped into, keeping light colors lighter than dark ones
+    pub lightness_range: Option<(f32, f32)>,
 }
 
 impl PaletteStyle {
-    pub fn from_name(name: &str) -> Self {
-        match name {
+    /// Preset called `name`, None for an unknown name
+    pub fn from_name(name: &str) -> Option<Self> {
+        let style = match name {
+            "lofi" => Self::default(),
+            "auto" => Self {
+                description: "Warm or cool, picked from the wallpaper",
+                ..Self::default()
+            },
             "kawaii" => Self {
                 description: "Cute pink aesthetic",
                 dark_saturation: 0.55,
@@ -107,6 +519,9 @@ impl PaletteStyle {
                 target_hues: Some(&[330.0, 280.0, 200.0]),
                 bg_tint_strength: 0.14,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "pastel" => Self {
                 description: "Soft dreamy pastels",
@@ -120,8 +535,11 @@ impl PaletteStyle {
                 target_hues: None,
                 bg_tint_strength: 0.12,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: Some(0.45),
+                lightness_range: Some((0.72, 0.88)),
             },
-            "vivid" => Self {
+            "vivid" | "vibrant" => Self {
                 description: "Bold vibrant colors",
                 dark_saturation: 0.65,
                 light_saturation: 0.55,
@@ -133,6 +551,25 @@ impl PaletteStyle {
                 target_hues: None,
                 bg_tint_strength: 0.08,
                 color_harmony: ColorHarmony::Triadic,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
+            },
+            "neon" => Self {
+                description: "Electric glow, full saturation",
+                dark_saturation: 1.0,
+                light_saturation: 0.90,
+                dark_brightness: 1.0,
+                light_brightness: 0.95,
+                contrast: 0.90,
+                warmth_shift: 0.0,
+                hue_boosts: &[],
+                target_hues: None,
+                bg_tint_strength: 0.10,
+                color_harmony: ColorHarmony::Complementary,
+                saturation_floor: Some(0.85),
+                saturation_ceiling: None,
+                lightness_range: Some((0.50, 0.70)),
             },
             "nord" => Self {
                 description: "Cool nordic minimal",
@@ -146,6 +583,25 @@ impl PaletteStyle {
                 target_hues: Some(&[200.0, 180.0, 220.0]),
                 bg_tint_strength: 0.10,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
+            },
+            "nord-dark" => Self {
+                description: "Deep polar night, frosty accents",
+                dark_saturation: 0.30,
+                light_saturation: 0.28,
+                dark_brightness: 0.76,
+                light_brightness: 0.84,
+                contrast: 0.78,
+                warmth_shift: -0.20,
+                hue_boosts: &[(210.0, 40.0, 0.14), (190.0, 30.0, 0.10)],
+                target_hues: Some(&[210.0, 190.0, 230.0, 160.0]),
+                bg_tint_strength: 0.16,
+                color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "warm" => Self {
                 description: "Cozy warm tones",
@@ -159,6 +615,9 @@ impl PaletteStyle {
                 target_hues: Some(&[30.0, 45.0, 15.0]),
                 bg_tint_strength: 0.15,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "muted" => Self {
                 description: "Soft neutral palette",
@@ -172,6 +631,9 @@ impl PaletteStyle {
                 target_hues: None,
                 bg_tint_strength: 0.10,
                 color_harmony: ColorHarmony::Extracted,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "catppuccin" => Self {
                 description: "Creamy pastels, mocha vibes",
@@ -185,6 +647,9 @@ impl PaletteStyle {
                 target_hues: Some(&[350.0, 220.0, 170.0, 45.0]),
                 bg_tint_strength: 0.15,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "dracula" => Self {
                 description: "Purple/pink gothic aesthetic",
@@ -198,6 +663,9 @@ impl PaletteStyle {
                 target_hues: Some(&[300.0, 280.0, 330.0, 180.0]),
                 bg_tint_strength: 0.12,
                 color_harmony: ColorHarmony::SplitComp,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "gruvbox" => Self {
                 description: "Retro warm oranges, earthy",
@@ -211,6 +679,9 @@ impl PaletteStyle {
                 target_hues: Some(&[40.0, 100.0, 180.0, 0.0]),
                 bg_tint_strength: 0.18,
                 color_harmony: ColorHarmony::Complementary,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "tokyo-night" => Self {
                 description: "Cool neon blues, city night",
@@ -224,6 +695,9 @@ impl PaletteStyle {
                 target_hues: Some(&[185.0, 230.0, 280.0, 340.0]),
                 bg_tint_strength: 0.10,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "rose-pine" => Self {
                 description: "Romantic muted rose tints",
@@ -237,6 +711,9 @@ impl PaletteStyle {
                 target_hues: Some(&[340.0, 275.0, 45.0, 190.0]),
                 bg_tint_strength: 0.14,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "everforest" => Self {
                 description: "Nature greens, forest calm",
@@ -250,6 +727,9 @@ impl PaletteStyle {
                 target_hues: Some(&[120.0, 90.0, 55.0, 180.0]),
                 bg_tint_strength: 0.12,
                 color_harmony: ColorHarmony::Analogous,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
             "synthwave" => Self {
                 description: "Neon retro 80s vibes",
@@ -263,30 +743,25 @@ impl PaletteStyle {
                 target_hues: Some(&[325.0, 185.0, 280.0, 45.0]),
                 bg_tint_strength: 0.08,
                 color_harmony: ColorHarmony::Complementary,
+                saturation_floor: None,
+                saturation_ceiling: None,
+                lightness_range: None,
             },
-            _ => Self {
-                description: "Calm balanced aesthetic",
-                dark_saturation: 0.48,
-                light_saturation: 0.42,
-                dark_brightness: 0.86,
-                light_brightness: 0.90,
-                contrast: 0.72,
-                warmth_shift: 0.08,
-                hue_boosts: &[],
-                target_hues: None,
-                bg_tint_strength: 0.12,
-                color_harmony: ColorHarmony::Extracted,
-            },
-        }
+            _ => return None,
+        };
+        Some(style)
     }
 
     pub fn all_styles() -> Vec<&'static str> {
         vec![
             "lofi",
+            "auto",
             "kawaii",
             "pastel",
             "vivid",
+            "neon",
             "nord",
+            "nord-dark",
             "warm",
             "muted",
             "catppuccin",
@@ -300,48 +775,181 @@ impl PaletteStyle {
     }
 }
 
+/// "lofi", the style unknown names fall back to
+impl Default for PaletteStyle {
+    fn default() -> Self {
+        Self {
+            description: "Calm balanced aesthetic",
+            dark_saturation: 0.48,
+            light_saturation: 0.42,
+            dark_brightness: 0.86,
+            light_brightness: 0.90,
+            contrast: 0.72,
+            warmth_shift: 0.08,
+            hue_boosts: &[],
+            target_hues: None,
+            bg_tint_strength: 0.12,
+            color_harmony: ColorHarmony::Extracted,
+            saturation_floor: None,
+            saturation_ceiling: None,
+            lightness_range: None,
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            mode: "dark".to_string(),
+            dark_background_style: "extracted".to_string(),
+            dark_background_custom: None,
+            light_background_style: "extracted".to_string(),
+            light_background_custom: None,
+            waybar_gradient: false,
+            shadow_color: "background".to_string(),
+            tty_colors: false,
+            surface_alpha: 1.0,
+            background_alpha: 1.0,
+        }
+    }
+}
+
+impl Default for PaletteConfig {
+    fn default() -> Self {
+        Self {
+            style: "tokyo-night".to_string(),
+            diversity_threshold: None,
+            distance_metric: DistanceMetric::default(),
+            brightness_space: BrightnessSpace::default(),
+            dark_saturation: None,
+            light_saturation: None,
+            dark_brightness: None,
+            light_brightness: None,
+            color_count: 16,
+            vibrance_area_balance: 1.0,
+            min_accent_saturation: 0.4,
+            min_distinct_hues: 0,
+            algorithm: PaletteAlgorithm::default(),
+            sample_size: 200,
+            brightness_range: [20, 240],
+            harmony: Harmony::default(),
+            contrast: None,
+            saturation: None,
+            brightness: None,
+            semantic_ansi: false,
+            stable_ordering: false,
+            pins: BTreeMap::new(),
+        }
+    }
+}
+
 impl Default for IroConfig {
     fn default() -> Self {
         Self {
-            theme: ThemeConfig {
-                mode: "dark".to_string(),
-                dark_background_style: "extracted".to_string(),
-                dark_background_custom: None,
-                light_background_style: "extracted".to_string(),
-                light_background_custom: None,
-            },
-            palette: PaletteConfig {
-                style: "tokyo-night".to_string(),
-                diversity_threshold: 50.0,
-                dark_saturation: 0.42,
-                light_saturation: 0.37,
-                light_brightness: 0.88,
-                color_count: 16,
-            },
-            wallpaper_dir: default_wallpaper_dir(),
+            version: CONFIG_VERSION,
+            theme: ThemeConfig::default(),
+            palette: PaletteConfig::default(),
+            wallpaper_dir: dirs::home_dir()
+                .map(|h| {
+                    h.join("Pictures")
+                        .join("Wallpaper")
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .unwrap_or_else(|| "~/Pictures/Wallpaper".to_string()),
+            wallpaper: WallpaperConfig::default(),
+            reload: ReloadConfig::default(),
+            backup: BackupConfig::default(),
+            outputs: BTreeMap::new(),
+            targets: BTreeMap::new(),
         }
     }
 }
 
 impl IroConfig {
     pub fn load() -> Result<Self> {
+        Ok(Self::load_upgrading()?.0)
+    }
+
+    /// `load`, along with the version config.toml was upgraded from and where the
+    /// original was kept when it was written by an older iro
+    pub fn load_upgrading() -> Result<(Self, Option<(u32, PathBuf)>)> {
         let config_path = Self::config_path()?;
+        let read_only = is_read_only();
 
         if !config_path.exists() {
             // Create default config
             let config = Self::default();
-            config.save()?;
-            return Ok(config);
+            if !read_only {
+                config.save()?;
+            }
+            return Ok((config, None));
         }
 
         let content =
             std::fs::read_to_string(&config_path).context("Failed to read config file")?;
 
-        let config: IroConfig = toml::from_str(&content).context("Failed to parse config file")?;
+        let mut config = Self::from_toml(&content)?;
+        if config.version < CONFIG_VERSION {
+            let old_version = config.version;
+            config.migrate();
+            if read_only {
+                return Ok((config, None));
+            }
 
+            // Keep the original, rewriting drops the user's comments
+            let backup_path = config_path.with_extension("toml.bak");
+            std::fs::copy(&config_path, &backup_path).context("Failed to back up config file")?;
+            config.save()?;
+            return Ok((config, Some((old_version, backup_path))));
+        }
+
+        Ok((config, None))
+    }
+
+    /// Parse a config file, taking any field it doesn't have yet from the defaults so
+    /// files written by older versions keep loading
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut value: toml::Value =
+            toml::from_str(content).context("Failed to parse config file")?;
+        let version = value
+            .get("version")
+            .and_then(toml::Value::as_integer)
+            .unwrap_or(0);
+
+        let defaults =
+            toml::Value::try_from(Self::default()).context("Failed to serialize default config")?;
+        fill_missing(&mut value, defaults);
+
+        let mut config: IroConfig = value.try_into().context("Failed to parse config file")?;
+        // The filled in default would claim the current version
+        config.version = u32::try_from(version).unwrap_or(0);
         Ok(config)
     }
 
+    /// Bring a config from `self.version` up to `CONFIG_VERSION`. Missing fields are
+    /// already filled in by `from_toml`; renamed or reinterpreted ones get converted
+    /// here, one version step at a time.
+    fn migrate(&mut self) {
+        if self.version < 2 {
+            // Version 1 wrote these out but never read them, the style's values were
+            // always used. Drop them so they don't start overriding the style now.
+            self.palette.dark_saturation = None;
+            self.palette.light_saturation = None;
+            self.palette.light_brightness = None;
+
+            // Older versions wrote out 50.0, which was tuned for RGB distance and is far
+            // too strict for Lab, the default metric now. Fall back to the metric's own
+            // default instead.
+            if self.palette.distance_metric == DistanceMetric::Lab
+                && self.palette.diversity_threshold == Some(LEGACY_DIVERSITY_THRESHOLD)
+            {
+                self.palette.diversity_threshold = None;
+            }
+        }
+        self.version = CONFIG_VERSION;
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
 
@@ -357,13 +965,227 @@ impl IroConfig {
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf> {
+    pub fn config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir().context("Failed to get config directory")?;
         Ok(config_dir.join("iro").join("config.toml"))
     }
 
+    /// Whether `[targets]` leaves an output on
+    pub fn target_enabled(&self, output: &str) -> bool {
+        self.targets.get(output).copied().unwrap_or(true)
+    }
+
+    /// Mode configured for an output, or the app's own default
+    pub fn output_mode(&self, output: &str, default: OutputMode) -> OutputMode {
+        self.outputs
+            .get(output)
+            .and_then(|o| o.mode)
+            .unwrap_or(default)
+    }
+
+    /// Path configured for an output, with `~` expanded
+    pub fn output_path(&self, output: &str) -> Option<PathBuf> {
+        let path = self.outputs.get(output)?.path.as_ref()?;
+        Some(PathBuf::from(shellexpand::tilde(path).as_ref()))
+    }
+
     pub fn wallpaper_path(&self) -> PathBuf {
-        let expanded = shellexpand::tilde(&self.wallpaper_dir);
-        PathBuf::from(expanded.as_ref())
+        expand_path(&self.wallpaper_dir)
+    }
+}
+
+/// Expand `~` and environment variables in a configured path. An unset variable is
+/// left in place, so the path reads as missing rather than pointing somewhere else.
+pub fn expand_path(path: &str) -> PathBuf {
+    let expanded = shellexpand::full(path).unwrap_or_else(|_| shellexpand::tilde(path));
+    PathBuf::from(expanded.as_ref())
+}
+
+impl PaletteConfig {
+    /// Configured diversity threshold, or a default on the scale of `distance_metric`
+    pub fn diversity_threshold(&self) -> f32 {
+        self.diversity_threshold
+            .unwrap_or(match self.distance_metric {
+                DistanceMetric::Lab => 20.0,
+                DistanceMetric::Rgb => 50.0,
+            })
+    }
+
+    /// Style `name` with the saturation and brightness overrides, then the contrast,
+    /// saturation and brightness multipliers applied. Results are clamped so extreme
+    /// multipliers can't flatten every color to black or white.
+    pub fn resolve_style(&self, name: &str) -> PaletteStyle {
+        let mut style = PaletteStyle::from_name(name).unwrap_or_default();
+        if let Some(saturation) = self.dark_saturation {
+            style.dark_saturation = saturation.clamp(0.0, 2.0);
+        }
+        if let Some(saturation) = self.light_saturation {
+            style.light_saturation = saturation.clamp(0.0, 2.0);
+        }
+        if let Some(brightness) = self.dark_brightness {
+            style.dark_brightness = brightness.clamp(0.4, 1.3);
+        }
+        if let Some(brightness) = self.light_brightness {
+            style.light_brightness = brightness.clamp(0.4, 1.3);
+        }
+        if let Some(contrast) = self.contrast {
+            style.contrast = (style.contrast * contrast).clamp(0.3, 1.5);
+        }
+        if let Some(saturation) = self.saturation {
+            style.dark_saturation = (style.dark_saturation * saturation).clamp(0.0, 2.0);
+            style.light_saturation = (style.light_saturation * saturation).clamp(0.0, 2.0);
+        }
+        if let Some(brightness) = self.brightness {
+            style.dark_brightness = (style.dark_brightness * brightness).clamp(0.4, 1.3);
+            style.light_brightness = (style.light_brightness * brightness).clamp(0.4, 1.3);
+        }
+        style
+    }
+
+    /// Configured sample size, kept between 16 and `MAX_SAMPLE_SIZE` pixels
+    pub fn sample_size(&self) -> u32 {
+        self.sample_size.clamp(16, MAX_SAMPLE_SIZE)
+    }
+
+    /// Configured brightness range, with the bounds swapped if given backwards
+    pub fn brightness_range(&self) -> RangeInclusive<u8> {
+        let [min, max] = self.brightness_range;
+        min.min(max)..=min.max(max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated(content: &str) -> IroConfig {
+        let mut config = IroConfig::from_toml(content).unwrap();
+        config.migrate();
+        config
+    }
+
+    #[test]
+    fn unversioned_config_upgrades_keeping_its_values() {
+        let config = migrated(
+            "wallpaper_dir = \"~/walls\"\n\
+             [theme]\nmode = \"light\"\n\
+             [palette]\ncolor_count = 12\nalgorithm = \"kmeans\"\n",
+        );
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.wallpaper_dir, "~/walls");
+        assert_eq!(config.theme.mode, "light");
+        assert_eq!(config.palette.color_count, 12);
+        assert_eq!(config.palette.algorithm, PaletteAlgorithm::Kmeans);
+
+        // Everything the old file didn't have comes from the defaults
+        let defaults = IroConfig::default();
+        assert_eq!(config.palette.sample_size, defaults.palette.sample_size);
+        assert_eq!(config.backup.keep, defaults.backup.keep);
+        assert!(config.outputs.is_empty());
+    }
+
+    #[test]
+    fn version_1_overrides_are_dropped() {
+        let config = migrated(
+            "version = 1\n[palette]\ndark_saturation = 0.9\nlight_saturation = 0.4\nlight_brightness = 0.8\n",
+        );
+        assert_eq!(config.palette.dark_saturation, None);
+        assert_eq!(config.palette.light_saturation, None);
+        assert_eq!(config.palette.light_brightness, None);
+    }
+
+    #[test]
+    fn bad_values_still_fail() {
+        assert!(IroConfig::from_toml("[palette]\ncolor_count = \"many\"\n").is_err());
+        assert!(IroConfig::from_toml("not toml").is_err());
+    }
+
+    #[test]
+    fn legacy_diversity_threshold_is_dropped_under_lab() {
+        let config = migrated("[palette]\ndiversity_threshold = 50.0\n");
+        assert_eq!(config.palette.diversity_threshold, None);
+        assert_eq!(config.version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn legacy_diversity_threshold_is_kept_under_rgb() {
+        let config = migrated("[palette]\ndiversity_threshold = 50.0\ndistance_metric = \"rgb\"\n");
+        assert_eq!(config.palette.diversity_threshold, Some(50.0));
+    }
+
+    #[test]
+    fn custom_diversity_threshold_is_kept() {
+        let config = migrated("[palette]\ndiversity_threshold = 25.0\n");
+        assert_eq!(config.palette.diversity_threshold, Some(25.0));
+    }
+
+    #[test]
+    fn current_configs_keep_their_threshold() {
+        let config = migrated(&format!(
+            "version = {}\n[palette]\ndiversity_threshold = 50.0\n",
+            CONFIG_VERSION
+        ));
+        assert_eq!(config.palette.diversity_threshold, Some(50.0));
+    }
+
+    #[test]
+    fn every_style_resolves_to_distinct_settings() {
+        // What a style changes about the output, leaving out its description
+        let settings = |name: &str| {
+            let style = PaletteStyle::from_name(name)
+                .unwrap_or_else(|| panic!("style {} doesn't resolve", name));
+            format!(
+                "{:?}",
+                (
+                    [
+                        style.dark_saturation,
+                        style.light_saturation,
+                        style.dark_brightness,
+                        style.light_brightness,
+                        style.contrast,
+                        style.warmth_shift,
+                        style.bg_tint_strength,
+                    ],
+                    style.hue_boosts,
+                    style.target_hues,
+                    style.color_harmony,
+                    style.saturation_floor,
+                    style.saturation_ceiling,
+                    style.lightness_range,
+                )
+            )
+        };
+
+        // "auto" picks warm or nord at runtime, it has no settings of its own
+        let styles: Vec<&str> = PaletteStyle::all_styles()
+            .into_iter()
+            .filter(|name| *name != "auto")
+            .collect();
+        for (i, a) in styles.iter().enumerate() {
+            for b in &styles[i + 1..] {
+                assert_ne!(settings(a), settings(b), "{} and {} are the same", a, b);
+            }
+        }
+        assert!(PaletteStyle::from_name("no-such-style").is_none());
+    }
+
+    #[test]
+    fn configs_missing_fields_round_trip() {
+        let serialized = toml::to_string_pretty(&IroConfig::default()).unwrap();
+        let mut value: toml::Value = toml::from_str(&serialized).unwrap();
+        let palette = value.get_mut("palette").unwrap().as_table_mut().unwrap();
+        palette.remove("color_count").unwrap();
+        palette.remove("style").unwrap();
+        value.as_table_mut().unwrap().remove("theme").unwrap();
+
+        let config = IroConfig::from_toml(&toml::to_string(&value).unwrap()).unwrap();
+        let defaults = IroConfig::default();
+        assert_eq!(config.palette.color_count, defaults.palette.color_count);
+        assert_eq!(config.palette.style, defaults.palette.style);
+        assert_eq!(config.theme.mode, defaults.theme.mode);
+        assert_eq!(config.version, CONFIG_VERSION);
+
+        // Saving what was loaded gives the defaults back
+        assert_eq!(toml::to_string_pretty(&config).unwrap(), serialized);
     }
 }
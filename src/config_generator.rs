@@ -1,69 +1,642 @@
-use crate::{template_engine::TemplateEngine, ColorScheme};
+use crate::{
+    color_math::hex_to_rgb,
+    config::{BackupStrategy, IroConfig, OutputMode},
+    diff,
+    template_engine::TemplateEngine,
+    ColorScheme,
+};
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+type Generator = fn(&ConfigGenerator, &ColorScheme, OutputMode) -> Result<()>;
+
+/// Markers around the color blocks iro keeps in configs it edits in place
+const COLORS_START: &str = "# DYNAMIC COLOR SCHEME - Generated by iro";
+const URGENCY_COLORS_START: &str = "# DYNAMIC URGENCY COLORS - Generated by iro";
+const COLORS_END: &str = "# END DYNAMIC COLOR SCHEME";
+
+thread_local! {
+    /// What the generator on this thread printed, while `generate_configs` collects it
+    static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// `print!` for generators. `generate_configs` runs them in parallel and prints what
+/// each one wrote once they are all done, in table order, so every run reads the same.
+pub(crate) fn emit(text: &str) {
+    CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(buffer) => buffer.push_str(text),
+        None => print!("{}", text),
+    });
+}
+
+/// `println!` through `emit`
+macro_rules! report {
+    ($($arg:tt)*) => {
+        $crate::config_generator::emit(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+pub(crate) use report;
+
+/// Run `generate` with its output collected instead of printed
+pub(crate) fn captured<T>(generate: impl FnOnce() -> T) -> (T, String) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(String::new()));
+    let result = generate();
+    let output = CAPTURED.with(|captured| captured.borrow_mut().take());
+    (result, output.unwrap_or_default())
+}
+
+/// A generated file as reported by `iro paths`
+pub struct OutputTarget {
+    pub name: &'static str,
+    pub mode: OutputMode,
+    /// App config iro manages, None when the app isn't installed
+    pub path: Option<PathBuf>,
+    /// Separate colors file written in include mode
+    pub include: Option<PathBuf>,
+    /// Template rendered in template mode
+    pub template: Option<PathBuf>,
+}
 
 pub struct ConfigGenerator {
     template_engine: TemplateEngine,
     config_dir: PathBuf,
+    config: IroConfig,
+    /// Sandbox directory that receives all writes instead of the live config dir
+    output_dir: Option<PathBuf>,
+    /// Named palette: colors go to `iro-<profile>-colors.*` and app configs are left alone
+    profile: Option<String>,
+    /// pywal-compatible colors.json, only written when set
+    pywal_path: Option<PathBuf>,
+    /// Print a diff of every file instead of writing it
+    dry_run: bool,
+    /// Output keys from `--only`, every other output is skipped
+    only: Option<Vec<String>>,
 }
 
 impl ConfigGenerator {
     pub fn new() -> Result<Self> {
-        let config_dir = dirs::config_dir().context("Failed to get config directory")?;
-
-        let template_engine = TemplateEngine::new()?;
+        let generator = Self::from_config(IroConfig::load()?)?;
 
         // Create default templates if they don't exist
-        template_engine.create_default_templates()?;
+        generator.template_engine.create_default_templates()?;
+
+        Ok(generator)
+    }
+
+    /// Generator for an already loaded config that leaves missing templates alone,
+    /// for read-only checks like `iro doctor`
+    pub fn from_config(config: IroConfig) -> Result<Self> {
+        let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+        let template_engine =
+            TemplateEngine::new()?.with_semantic_ansi(config.palette.semantic_ansi);
 
         Ok(Self {
             template_engine,
             config_dir,
+            config,
+            output_dir: None,
+            profile: None,
+            pywal_path: None,
+            dry_run: false,
+            only: None,
         })
     }
 
-    pub fn generate_configs(&self, color_scheme: &ColorScheme) -> Result<()> {
-        println!("📝 Generating configuration files...");
+    /// Write every generated file under `output_dir` (mirroring the config dir layout)
+    /// instead of touching the live configs. Existing configs are still read from the
+    /// live config dir so the output shows exactly what iro would write.
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = Some(output_dir);
+        self
+    }
+
+    /// Print what each file would become as a unified diff against the current one,
+    /// without writing or backing up anything
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Also write a pywal-compatible colors.json to `path`, so dotfiles built around
+    /// pywal keep working. `None` picks pywal's own `~/.cache/wal/colors.json`.
+    pub fn with_pywal(mut self, path: Option<PathBuf>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => dirs::cache_dir()
+                .context("Failed to get cache directory")?
+                .join("wal")
+                .join("colors.json"),
+        };
+        self.pywal_path = Some(path);
+        Ok(self)
+    }
+
+    /// Write only the outputs in `keys` (e.g. "waybar", "kitty") for this run
+    pub fn with_only(mut self, keys: &[String]) -> Result<Self> {
+        let known: Vec<&str> = Self::generators().iter().map(|(_, key, ..)| *key).collect();
+        if let Some(unknown) = keys.iter().find(|key| !known.contains(&key.as_str())) {
+            anyhow::bail!(
+                "Unknown output '{}' (outputs: {})",
+                unknown,
+                known.join(", ")
+            );
+        }
+        self.only = Some(keys.to_vec());
+        Ok(self)
+    }
+
+    /// (name, output key, generator, required, default mode) - optional generators are
+    /// skipped when the app is missing, and `[outputs.<key>] mode` overrides the default
+    fn generators() -> [(&'static str, &'static str, Generator, bool, OutputMode); 21] {
+        [
+            (
+                "Hyprland config",
+                "hyprland",
+                Self::generate_hyprland_config,
+                true,
+                OutputMode::Inline,
+            ),
+            (
+                "hyprlock",
+                "hyprlock",
+                Self::generate_hyprlock_config,
+                false,
+                OutputMode::Inline,
+            ),
+            (
+                "Waybar",
+                "waybar",
+                Self::generate_waybar_config,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "Waybar colors JSON",
+                "waybar-json",
+                Self::generate_waybar_json,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "Kitty",
+                "kitty",
+                Self::generate_kitty_config,
+                false,
+                OutputMode::Inline,
+            ),
+            (
+                "Alacritty",
+                "alacritty",
+                Self::generate_alacritty_config,
+                false,
+                OutputMode::Include,
+            ),
+            (
+                "Rofi",
+                "rofi",
+                Self::generate_rofi_config,
+                false,
+                OutputMode::Include,
+            ),
+            (
+                "mako",
+                "mako",
+                Self::generate_mako_config,
+                false,
+                OutputMode::Inline,
+            ),
+            (
+                "dunst",
+                "dunst",
+                Self::generate_dunst_config,
+                false,
+                OutputMode::Inline,
+            ),
+            (
+                "Neovim colors",
+                "nvim",
+                Self::generate_nvim_lua,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "GTK colors",
+                "gtk",
+                Self::generate_gtk_css,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "shell colors",
+                "shell",
+                Self::generate_shell_colors,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "fish colors",
+                "fish",
+                Self::generate_fish_colors,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "nushell colors",
+                "nushell",
+                Self::generate_nushell_colors,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "tmux",
+                "tmux",
+                Self::generate_tmux_config,
+                false,
+                OutputMode::Include,
+            ),
+            (
+                "Starship",
+                "starship",
+                Self::generate_starship_palette,
+                false,
+                OutputMode::Inline,
+            ),
+            (
+                "CSS variables",
+                "css",
+                Self::generate_css_vars,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "SCSS variables",
+                "scss",
+                Self::generate_scss_vars,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "QuickShell",
+                "quickshell",
+                Self::generate_quickshell_config,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "TTY",
+                "tty",
+                Self::generate_tty_config,
+                false,
+                OutputMode::Template,
+            ),
+            (
+                "pywal colors",
+                "pywal",
+                Self::generate_pywal_json,
+                false,
+                OutputMode::Template,
+            ),
+        ]
+    }
 
-        // Generate Hyprland config
-        self.generate_hyprland_config(color_scheme)
-            .context("Failed to generate Hyprland config")?;
+    /// Write colors to `iro-<profile>-colors.*` include files only, so several named
+    /// palettes can live side by side and be included from different app configs
+    pub fn with_profile(mut self, profile: &str) -> Result<Self> {
+        if profile.is_empty()
+            || !profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!(
+                "Invalid profile name '{}': use letters, digits, '-' and '_'",
+                profile
+            );
+        }
+        self.profile = Some(profile.to_string());
+        Ok(self)
+    }
+
+    pub fn templates_dir(&self) -> &Path {
+        self.template_engine.templates_dir()
+    }
+
+    /// Whether an output runs at all; opt-in outputs are off unless configured, and
+    /// `[targets]` or `--only` can turn any output off
+    fn output_enabled(&self, key: &str) -> bool {
+        if !self.config.target_enabled(key) {
+            return false;
+        }
+        if let Some(only) = &self.only {
+            if !only.iter().any(|k| k == key) {
+                return false;
+            }
+        }
+
+        match key {
+            "tty" => self.config.theme.tty_colors,
+            "pywal" => self.pywal_path.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Where each output writes, for `iro paths`
+    pub fn output_targets(&self) -> Vec<OutputTarget> {
+        Self::generators()
+            .iter()
+            .filter(|(_, key, ..)| self.output_enabled(key))
+            .map(|&(name, key, _, _, default_mode)| {
+                let mode = self.config.output_mode(key, default_mode);
+                let path = self.app_config_path(key);
+                let include = match (mode, &path, self.include_file_name(key)) {
+                    (OutputMode::Include, Some(path), Some(file)) => {
+                        Some(path.with_file_name(file))
+                    }
+                    _ => None,
+                };
+                let template = match (mode, Self::template_name(key)) {
+                    (OutputMode::Template, Some(file)) => Some(self.templates_dir().join(file)),
+                    _ => None,
+                };
+                OutputTarget {
+                    name,
+                    mode,
+                    path,
+                    include,
+                    template,
+                }
+            })
+            .collect()
+    }
+
+    /// Every live config with a backup to restore, as `(config, backup)`: the one from
+    /// before iro first edited it, or else the newest timestamped one. Disabled outputs
+    /// are included, their backups are still there.
+    pub fn backups(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.backed_up_configs()
+            .into_iter()
+            .filter_map(|config| {
+                let original = Self::backup_path(&config);
+                let backup = if original.exists() {
+                    original
+                } else {
+                    Self::timestamped_backups(&config).pop()?
+                };
+                Some((config, backup))
+            })
+            .collect()
+    }
+
+    /// Every backup file of every config, timestamped ones included
+    pub fn backup_files(&self) -> Vec<PathBuf> {
+        self.backed_up_configs()
+            .into_iter()
+            .flat_map(|config| {
+                let original = Self::backup_path(&config);
+                original
+                    .exists()
+                    .then_some(original)
+                    .into_iter()
+                    .chain(Self::timestamped_backups(&config))
+            })
+            .collect()
+    }
+
+    /// Configs iro may have backed up, whether or not there is a backup
+    fn backed_up_configs(&self) -> Vec<PathBuf> {
+        let mut configs: Vec<PathBuf> = Vec::new();
+        let candidates = Self::generators()
+            .into_iter()
+            .filter_map(|(_, key, ..)| self.app_config_path(key))
+            // GTK 4 gets its own copy of the GTK 3 colors
+            .chain(std::iter::once(
+                self.config_dir.join("gtk-4.0").join("colors.css"),
+            ));
+        for config in candidates {
+            if !configs.contains(&config) {
+                configs.push(config);
+            }
+        }
+        configs
+    }
+
+    /// Config file an output manages, or None when the app isn't installed
+    fn app_config_path(&self, key: &str) -> Option<PathBuf> {
+        let installed = |dir: &str, file: &str| {
+            let dir = self.config_dir.join(dir);
+            dir.exists().then(|| dir.join(file))
+        };
+
+        match key {
+            "hyprland" => Some(self.config_dir.join("hypr").join("hyprland.conf")),
+            "hyprlock" => {
+                let path = self.config_dir.join("hypr").join("hyprlock.conf");
+                path.exists().then_some(path)
+            }
+            "waybar" => installed("waybar", "style.css"),
+            "waybar-json" => installed("waybar", "iro-colors.json"),
+            "kitty" => installed("kitty", "kitty.conf"),
+            "alacritty" => installed("alacritty", "alacritty.toml"),
+            "rofi" => installed("rofi", "config.rasi"),
+            "mako" => installed("mako", "config"),
+            "dunst" => installed("dunst", "dunstrc"),
+            "nvim" => installed("nvim", "lua/iro_colors.lua"),
+            "gtk" => {
+                // GTK 4 only setups count too, both copies are written either way
+                let installed = ["gtk-3.0", "gtk-4.0"]
+                    .iter()
+                    .any(|dir| self.config_dir.join(dir).exists());
+                installed.then(|| self.config_dir.join("gtk-3.0").join("colors.css"))
+            }
+            "shell" => Some(self.config_dir.join("iro").join("colors.sh")),
+            "fish" => Some(self.config_dir.join("iro").join("colors.fish")),
+            "nushell" => Some(self.config_dir.join("iro").join("colors.nu")),
+            "tmux" => tmux_config_path(&self.config_dir),
+            "starship" => {
+                let path = self.config_dir.join("starship.toml");
+                path.exists().then_some(path)
+            }
+            "css" => Some(
+                self.config
+                    .output_path("css")
+                    .unwrap_or_else(|| self.config_dir.join("iro").join("colors.css")),
+            ),
+            "scss" => Some(
+                self.config
+                    .output_path("scss")
+                    .unwrap_or_else(|| self.config_dir.join("iro").join("_iro.scss")),
+            ),
+            "tty" => Some(self.config_dir.join("iro").join("vtrgb")),
+            "pywal" => self.pywal_path.clone(),
+            "quickshell" => {
+                // Check multiple possible quickshell locations
+                let home = dirs::home_dir()?;
+                let possible_paths = [
+                    self.config_dir.join("quickshell"),
+                    home.join("Git/quick"),
+                    home.join(".config/quickshell"),
+                ];
+                possible_paths
+                    .into_iter()
+                    .find(|p| p.exists())
+                    .map(|dir| dir.join("Theme.qml"))
+            }
+            _ => None,
+        }
+    }
 
-        // Generate Waybar config (optional)
-        if let Err(e) = self.generate_waybar_config(color_scheme) {
-            println!("  ⊘ Skipped Waybar ({})", e.root_cause());
+    /// Template an output renders in template mode
+    fn template_name(key: &str) -> Option<&'static str> {
+        match key {
+            "hyprland" => Some("hyprland.conf"),
+            "hyprlock" => Some("hyprlock.conf"),
+            "waybar" => Some("waybar.css"),
+            "kitty" => Some("kitty.conf"),
+            "alacritty" => Some("alacritty.toml"),
+            "rofi" => Some("rofi.rasi"),
+            "mako" => Some("mako.conf"),
+            "dunst" => Some("dunstrc"),
+            "nvim" => Some("nvim.lua"),
+            "gtk" => Some("gtk.css"),
+            "shell" => Some("shell_colors.sh"),
+            "fish" => Some("shell_colors.fish"),
+            "nushell" => Some("shell_colors.nu"),
+            "tmux" => Some("tmux.conf"),
+            "css" => Some("styles.css"),
+            "scss" => Some("_iro.scss"),
+            "quickshell" => Some("quickshell-theme.qml"),
+            _ => None,
         }
+    }
 
-        // Generate Kitty config (optional)
-        if let Err(e) = self.generate_kitty_config(color_scheme) {
-            println!("  ⊘ Skipped Kitty ({})", e.root_cause());
+    /// File the colors go to in include mode, next to the app config
+    fn include_file_name(&self, key: &str) -> Option<String> {
+        let extension = match key {
+            // Next to hyprland's iro-colors.conf in the hypr dir
+            "hyprlock" => {
+                return Some(
+                    self.colors_file_name("conf")
+                        .replace("-colors.", "-hyprlock-colors."),
+                )
+            }
+            "hyprland" | "kitty" => "conf",
+            "waybar" => "css",
+            "rofi" => "rasi",
+            "alacritty" => "toml",
+            _ => return None,
+        };
+        Some(self.colors_file_name(extension))
+    }
+
+    fn colors_file_name(&self, extension: &str) -> String {
+        match &self.profile {
+            Some(profile) => format!("iro-{}-colors.{}", profile, extension),
+            None => format!("iro-colors.{}", extension),
         }
+    }
 
-        // Generate Rofi config (optional)
-        if let Err(e) = self.generate_rofi_config(color_scheme) {
-            println!("  ⊘ Skipped Rofi ({})", e.root_cause());
+    pub fn generate_configs(&self, color_scheme: &ColorScheme) -> Result<()> {
+        if self.dry_run {
+            println!("📝 Generating configuration files (dry run)...");
+        } else {
+            println!("📝 Generating configuration files...");
         }
 
-        // Generate shell colors
-        self.generate_shell_colors(color_scheme)
-            .context("Failed to generate shell colors")?;
+        let generators: Vec<(&str, Generator, bool, OutputMode)> = Self::generators()
+            .into_iter()
+            .filter(|(_, key, ..)| self.output_enabled(key))
+            // Profiles only ever produce include files
+            .filter(|(_, key, ..)| self.profile.is_none() || self.include_file_name(key).is_some())
+            .map(|(name, key, generate, required, default_mode)| {
+                let mode = match self.profile {
+                    Some(_) => OutputMode::Include,
+                    None => self.config.output_mode(key, default_mode),
+                };
+                (name, generate, required, mode)
+            })
+            .collect();
+
+        // Required outputs go first, so when one fails nothing else gets written
+        for &(name, generate, _, mode) in generators.iter().filter(|(_, _, required, _)| *required)
+        {
+            generate(self, color_scheme, mode)
+                .with_context(|| format!("Failed to generate {}", name))?;
+        }
 
-        // Generate QuickShell theme (optional)
-        if let Err(e) = self.generate_quickshell_config(color_scheme) {
-            println!("  ⊘ Skipped QuickShell ({})", e.root_cause());
+        // Every other generator writes its own file, so they can all run at once
+        let results: Vec<(&str, Result<()>, String)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = generators
+                .iter()
+                .filter(|(_, _, required, _)| !required)
+                .map(|&(name, generate, _, mode)| {
+                    let handle =
+                        scope.spawn(move || captured(|| generate(self, color_scheme, mode)));
+                    (name, handle)
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|(name, handle)| match handle.join() {
+                    Ok((result, output)) => (name, result, output),
+                    Err(_) => (
+                        name,
+                        Err(anyhow::anyhow!("generator panicked")),
+                        String::new(),
+                    ),
+                })
+                .collect()
+        });
+
+        let mut skipped = 0;
+        for (name, result, output) in &results {
+            print!("{}", output);
+            if let Err(e) = result {
+                println!("  ⊘ Skipped {} ({})", name, e.root_cause());
+                skipped += 1;
+            }
         }
 
-        println!("  ✓ Generated all configuration files");
+        if skipped == 0 {
+            println!("  ✓ Generated all configuration files");
+        } else {
+            println!(
+                "  ✓ Generated {} of {} configuration files",
+                generators.len() - skipped,
+                generators.len()
+            );
+        }
         Ok(())
     }
 
-    fn generate_hyprland_config(&self, color_scheme: &ColorScheme) -> Result<()> {
-        let hyprland_dir = self.config_dir.join("hypr");
-        let config_path = hyprland_dir.join("hyprland.conf");
+    fn generate_hyprland_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self
+            .app_config_path("hyprland")
+            .context("Failed to resolve Hyprland config")?;
 
         // Backup original config if it exists and no backup exists
         self.backup_config(&config_path)?;
 
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "hyprland.conf", color_scheme, &[])
+                    .context("Failed to write Hyprland config")?;
+                report!("  ✓ Updated Hyprland colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                let include_path = config_path.with_file_name(self.colors_file_name("conf"));
+                let colors = self.generate_hyprland_colors(color_scheme)?;
+                // Variables must exist before anything in hyprland.conf uses them
+                let source = format!("source = {}", include_path.display());
+                self.write_include(&config_path, &include_path, &colors, &source, true)
+                    .context("Failed to write Hyprland colors")?;
+                report!("  ✓ Updated Hyprland colors");
+                return Ok(());
+            }
+        }
+
         // Read the current config to preserve non-color settings
         let current_config = if config_path.exists() {
             std::fs::read_to_string(&config_path).unwrap_or_default()
@@ -74,67 +647,286 @@ impl ConfigGenerator {
         // Generate the color section
         let color_section = self.generate_hyprland_colors(color_scheme)?;
 
-        // Replace or add color section in the config
-        let updated_config = if current_config.contains("# Dynamic Color scheme") {
-            // Replace existing dynamic colors
-            self.replace_section(
-                &current_config,
-                "# Dynamic Color scheme",
-                "# General settings",
-                &color_section,
-            )
-        } else if current_config.contains("# Color scheme") {
-            // Replace old static color scheme
-            self.replace_section(
-                &current_config,
-                "# Color scheme",
-                "# General settings",
-                &color_section,
-            )
-        } else {
-            // Add color section after input section
-            self.insert_after_section(&current_config, "}", &color_section)
-        };
+        let updated_config = Self::merge_hyprland_colors(&current_config, &color_section);
 
-        std::fs::write(&config_path, updated_config).context("Failed to write Hyprland config")?;
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write Hyprland config")?;
 
-        println!("  ✓ Updated Hyprland colors");
+        report!("  ✓ Updated Hyprland colors");
         Ok(())
     }
 
-    fn generate_waybar_config(&self, color_scheme: &ColorScheme) -> Result<()> {
-        let waybar_dir = self.config_dir.join("waybar");
-        if !waybar_dir.exists() {
-            anyhow::bail!("not installed");
+    /// `current_config` with the dynamic colors replaced, or an old static color scheme
+    /// replaced, or the colors added after the first top-level block
+    fn merge_hyprland_colors(current_config: &str, color_section: &str) -> String {
+        let colors = color_section.trim_start_matches('\n');
+        let existing = ["# Dynamic Color scheme", "# Color scheme"]
+            .into_iter()
+            .find_map(|marker| Some((marker, find_line(current_config, marker, 0)?)));
+        match existing {
+            // Closed by COLORS_END, so whatever follows the section is kept
+            Some((marker, start)) if find_line(current_config, COLORS_END, start).is_some() => {
+                Self::replace_section(current_config, marker, COLORS_END, colors)
+            }
+            // Sections written before COLORS_END end where the next settings start, or
+            // else at the first blank line; the colors themselves have none
+            Some((marker, start)) => {
+                let (end, gap) = if find_line(current_config, "# General settings", start).is_some()
+                {
+                    ("# General settings", "\n\n")
+                } else {
+                    ("\n", "\n")
+                };
+                let replacement = format!("{}{}{}", colors, COLORS_END, gap);
+                Self::replace_section(current_config, marker, end, &replacement)
+            }
+            None => Self::insert_after_section(
+                current_config,
+                "}",
+                &format!("\n{}{}\n", colors, COLORS_END),
+            ),
+        }
+    }
+
+    fn generate_hyprlock_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("hyprlock").context("not installed")?;
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "hyprlock.conf", color_scheme, &[])
+                    .context("Failed to write hyprlock config")?;
+                report!("  ✓ Updated hyprlock colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                let include_name = self
+                    .include_file_name("hyprlock")
+                    .context("No include file for hyprlock")?;
+                let include_path = config_path.with_file_name(include_name);
+                let colors = format!(
+                    "{}\n{}\n{}\n",
+                    COLORS_START,
+                    Self::hyprlock_variables(color_scheme),
+                    COLORS_END
+                );
+                // Variables must exist before the widgets use them
+                let source = format!("source = {}", include_path.display());
+                self.write_include(&config_path, &include_path, &colors, &source, true)
+                    .context("Failed to write hyprlock colors")?;
+                report!("  ✓ Updated hyprlock colors");
+                return Ok(());
+            }
+        }
+
+        let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+
+        let block = format!(
+            "{}\n{}\n{}",
+            COLORS_START,
+            Self::hyprlock_variables(color_scheme),
+            COLORS_END
+        );
+        let updated_config = Self::upsert_block(&current_config, COLORS_START, &block, true);
+        let updated_config = Self::use_hyprlock_variables(&updated_config);
+
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write hyprlock config")?;
+
+        report!("  ✓ Updated hyprlock colors");
+        Ok(())
+    }
+
+    /// The scheme as hyprlang variables, named like the ones in hyprland.conf
+    fn hyprlock_variables(color_scheme: &ColorScheme) -> String {
+        [
+            ("base", &color_scheme.background),
+            ("text", &color_scheme.foreground),
+            ("surface", &color_scheme.surface),
+            ("accent", &color_scheme.accent),
+            ("secondary", &color_scheme.secondary),
+            ("error", &color_scheme.error),
+        ]
+        .iter()
+        .map(|(name, hex)| format!("${} = rgb({})", name, hex.trim_start_matches('#')))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    /// Point the color keys of hyprlock's widgets at the iro variables: the input
+    /// field's border, fill, text, failure and check colors, label text and the
+    /// background fallback color. Other keys and blocks are left as they are.
+    fn use_hyprlock_variables(content: &str) -> String {
+        const KEYS: [(&str, &str, &str); 7] = [
+            ("input-field", "outer_color", "$accent"),
+            ("input-field", "inner_color", "$surface"),
+            ("input-field", "font_color", "$text"),
+            ("input-field", "fail_color", "$error"),
+            ("input-field", "check_color", "$secondary"),
+            ("label", "color", "$text"),
+            ("background", "color", "$base"),
+        ];
+
+        let mut block: Option<String> = None;
+        let mut output = String::with_capacity(content.len());
+        for line in content.lines() {
+            let trimmed = line.trim();
+            let mut replaced = None;
+
+            if let Some(name) = trimmed.strip_suffix('{') {
+                block = Some(name.trim().to_string());
+            } else if trimmed == "}" {
+                block = None;
+            } else if let (Some(name), Some((key, _))) = (&block, trimmed.split_once('=')) {
+                let key = key.trim();
+                if let Some((_, _, variable)) = KEYS
+                    .iter()
+                    .find(|(widget, k, _)| widget == name && *k == key)
+                {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    replaced = Some(format!("{}{} = {}", indent, key, variable));
+                }
+            }
+
+            output.push_str(replaced.as_deref().unwrap_or(line));
+            output.push('\n');
         }
+        output
+    }
 
-        let style_path = waybar_dir.join("style.css");
+    fn generate_waybar_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let style_path = self.app_config_path("waybar").context("not installed")?;
 
         // Backup original style
         self.backup_config(&style_path)?;
 
-        // Generate new CSS with dynamic colors
-        let rendered_css = self
-            .template_engine
-            .render_template("waybar.css", color_scheme)?;
+        // Modules get either a flat background or the accent → secondary gradient
+        let module_background = if self.config.theme.waybar_gradient {
+            format!(
+                "linear-gradient(90deg, alpha({}, 0.9), alpha({}, 0.9))",
+                color_scheme.accent, color_scheme.secondary
+            )
+        } else {
+            "alpha(@base, 0.9)".to_string()
+        };
+
+        let vars = [("module_background", module_background.as_str())];
 
-        std::fs::write(&style_path, rendered_css).context("Failed to write Waybar style")?;
+        match mode {
+            OutputMode::Template => {
+                // Generate new CSS with dynamic colors
+                self.write_template(&style_path, "waybar.css", color_scheme, &vars)
+            }
+            OutputMode::Include => {
+                let colors = self.template_engine.render_template_with_vars(
+                    "gtk-colors.css",
+                    color_scheme,
+                    &vars,
+                )?;
+                // CSS only honours @import at the top of the file
+                let file = self.colors_file_name("css");
+                self.write_include(
+                    &style_path,
+                    &style_path.with_file_name(&file),
+                    &colors,
+                    &format!("@import \"{}\";", file),
+                    true,
+                )
+            }
+            OutputMode::Inline => {
+                let colors = self.template_engine.render_template_with_vars(
+                    "gtk-colors.css",
+                    color_scheme,
+                    &vars,
+                )?;
+                let current_style = std::fs::read_to_string(&style_path).unwrap_or_default();
+                let mut section =
+                    format!("/* DYNAMIC COLOR SCHEME - Generated by iro */\n{}", colors);
+                // The end marker has to start its own line to be found again
+                if !section.ends_with('\n') {
+                    section.push('\n');
+                }
+
+                let updated_style = if current_style.contains("/* DYNAMIC COLOR SCHEME") {
+                    Self::replace_section(
+                        &current_style,
+                        "/* DYNAMIC COLOR SCHEME",
+                        "/* END DYNAMIC COLOR SCHEME */",
+                        &section,
+                    )
+                } else {
+                    format!(
+                        "{}/* END DYNAMIC COLOR SCHEME */\n\n{}",
+                        section, current_style
+                    )
+                };
+                self.write_output(&style_path, &updated_style).map(|_| ())
+            }
+        }
+        .context("Failed to write Waybar style")?;
 
-        println!("  ✓ Updated Waybar colors");
+        report!("  ✓ Updated Waybar colors");
         Ok(())
     }
 
-    fn generate_kitty_config(&self, color_scheme: &ColorScheme) -> Result<()> {
-        let kitty_dir = self.config_dir.join("kitty");
-        if !kitty_dir.exists() {
-            anyhow::bail!("not installed");
+    /// The whole scheme as JSON, for Waybar setups that drive their modules from a file
+    fn generate_waybar_json(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        if mode != OutputMode::Template {
+            anyhow::bail!(
+                "Waybar colors JSON only supports the template mode, not {}",
+                mode
+            );
         }
 
-        let config_path = kitty_dir.join("kitty.conf");
+        let json_path = self
+            .app_config_path("waybar-json")
+            .context("not installed")?;
+        self.backup_config(&json_path)?;
+
+        let json = serde_json::to_string_pretty(color_scheme)
+            .context("Failed to serialize color scheme")?;
+        let json_path = self
+            .write_output(&json_path, &format!("{}\n", json))
+            .context("Failed to write Waybar colors JSON")?;
+
+        report!("  ✓ Generated Waybar colors JSON ({})", json_path.display());
+        Ok(())
+    }
+
+    fn generate_kitty_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("kitty").context("not installed")?;
 
         // Backup original config
         self.backup_config(&config_path)?;
 
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "kitty.conf", color_scheme, &[])
+                    .context("Failed to write Kitty config")?;
+                report!("  ✓ Updated Kitty colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                let colors = self.generate_kitty_colors(color_scheme)?;
+                // Appended so the colors win over anything set earlier in kitty.conf
+                let file = self.colors_file_name("conf");
+                self.write_include(
+                    &config_path,
+                    &config_path.with_file_name(&file),
+                    &colors,
+                    &format!("include {}", file),
+                    false,
+                )
+                .context("Failed to write Kitty colors")?;
+                report!("  ✓ Updated Kitty colors");
+                return Ok(());
+            }
+        }
+
         // Read current config to preserve non-color settings
         let current_config = if config_path.exists() {
             std::fs::read_to_string(&config_path).unwrap_or_default()
@@ -148,7 +940,7 @@ impl ConfigGenerator {
         // Replace or add color section
         let updated_config = if current_config.contains("# DYNAMIC COLOR SCHEME") {
             // Replace existing dynamic colors
-            self.replace_section(
+            Self::replace_section(
                 &current_config,
                 "# ═══════════════════════════════════════════════════════════════════\n# DYNAMIC COLOR SCHEME",
                 "# ═══════════════════════════════════════════════════════════════════\n# TAB BAR",
@@ -156,36 +948,219 @@ impl ConfigGenerator {
             )
         } else if current_config.contains("# ROSE PINE DAWN x TOKYO NIGHT COLOR SCHEME") {
             // Replace old static color scheme
-            self.replace_section(&current_config,
+            Self::replace_section(&current_config,
                 "# ═══════════════════════════════════════════════════════════════════\n# ROSE PINE DAWN x TOKYO NIGHT COLOR SCHEME",
                 "# ═══════════════════════════════════════════════════════════════════\n# TAB BAR",
                 &color_section)
         } else {
             // Add color section before tab bar or at end
             if current_config.contains("# TAB BAR") {
-                self.replace_section(&current_config, "# ═══════════════════════════════════════════════════════════════════\n# TAB BAR", "", &format!("{}\n\n# ═══════════════════════════════════════════════════════════════════\n# TAB BAR", color_section))
+                // The section ends in a blank line already, as it does when replaced later
+                Self::insert_before_section(
+                    &current_config,
+                    "# ═══════════════════════════════════════════════════════════════════\n# TAB BAR",
+                    &color_section,
+                )
             } else {
                 format!("{}\n\n{}", current_config, color_section)
             }
         };
 
-        std::fs::write(&config_path, updated_config).context("Failed to write Kitty config")?;
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write Kitty config")?;
+
+        report!("  ✓ Updated Kitty colors");
+        Ok(())
+    }
+
+    fn generate_alacritty_config(
+        &self,
+        color_scheme: &ColorScheme,
+        mode: OutputMode,
+    ) -> Result<()> {
+        let config_path = self.app_config_path("alacritty").context("not installed")?;
+
+        // Alacritty ignores alacritty.yml once an alacritty.toml exists, so creating one
+        // next to a legacy config would drop all of the user's settings
+        if !config_path.exists() && config_path.with_extension("yml").exists() {
+            anyhow::bail!("only a legacy alacritty.yml found, convert it with `alacritty migrate`");
+        }
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+        let updated_config = match mode {
+            OutputMode::Template => {
+                self.write_template(&config_path, "alacritty.toml", color_scheme, &[])
+                    .context("Failed to write Alacritty config")?;
+                report!("  ✓ Updated Alacritty colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                let file = self.colors_file_name("toml");
+                self.write_output(
+                    &config_path.with_file_name(&file),
+                    &Self::generate_alacritty_colors(color_scheme),
+                )
+                .context("Failed to write Alacritty colors")?;
+
+                // Profiles are wired up by hand, like the other include files
+                let updated = match self.profile {
+                    Some(_) => None,
+                    None => Self::add_alacritty_import(&current_config, &file)?,
+                };
+                let Some(updated) = updated else {
+                    report!("  ✓ Updated Alacritty colors");
+                    return Ok(());
+                };
+                updated
+            }
+            OutputMode::Inline => Self::merge_alacritty_colors(&current_config, color_scheme)?,
+        };
+
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write Alacritty config")?;
 
-        println!("  ✓ Updated Kitty colors");
+        report!("  ✓ Updated Alacritty colors");
         Ok(())
     }
 
-    fn generate_rofi_config(&self, color_scheme: &ColorScheme) -> Result<()> {
-        let rofi_dir = self.config_dir.join("rofi");
-        if !rofi_dir.exists() {
-            anyhow::bail!("not installed");
+    /// Add `file` to the `[general] import` list of an alacritty.toml, or `None` when
+    /// it's imported already, under any path. Imports are loaded before the config
+    /// itself, so the user's own colors still win.
+    fn add_alacritty_import(content: &str, file: &str) -> Result<Option<String>> {
+        let mut document: toml_edit::DocumentMut =
+            content.parse().context("Failed to parse alacritty.toml")?;
+
+        // Alacritty before 0.14 read the list at the top level
+        let imports = if document.contains_key("import") {
+            &mut document["import"]
+        } else {
+            document
+                .entry("general")
+                .or_insert_with(toml_edit::table)
+                .as_table_mut()
+                .context("general in alacritty.toml is not a table")?
+                .entry("import")
+                .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+        };
+        let imports = imports
+            .as_array_mut()
+            .context("import in alacritty.toml is not a list")?;
+
+        let imported = imports.iter().any(|import| {
+            import
+                .as_str()
+                .is_some_and(|import| Path::new(import).file_name() == Some(file.as_ref()))
+        });
+        if imported {
+            return Ok(None);
+        }
+
+        // Keep iro last so it overrides other themes, following the list's layout:
+        // one entry per line or all on one line
+        let mut entry = toml_edit::Value::from(file);
+        if let Some(last) = imports.iter().last() {
+            let prefix = last.decor().prefix().and_then(|prefix| prefix.as_str());
+            match prefix {
+                Some(prefix) if prefix.contains('\n') => entry.decor_mut().set_prefix(prefix),
+                _ => entry.decor_mut().set_prefix(" "),
+            }
+        }
+        imports.push_formatted(entry);
+
+        Ok(Some(document.to_string()))
+    }
+
+    /// Set the scheme's `[colors.*]` tables in an alacritty.toml. The file is edited
+    /// as TOML, so a `[colors]` table the user already has is filled in rather than
+    /// repeated, and everything else stays as it was.
+    fn merge_alacritty_colors(content: &str, color_scheme: &ColorScheme) -> Result<String> {
+        let mut document: toml_edit::DocumentMut =
+            content.parse().context("Failed to parse alacritty.toml")?;
+        let generated: toml_edit::DocumentMut =
+            Self::generate_alacritty_colors(color_scheme).parse()?;
+
+        let colors = document
+            .entry("colors")
+            .or_insert_with(|| {
+                // Only the subtables get a header, not an empty [colors]
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                toml_edit::Item::Table(table)
+            })
+            .as_table_mut()
+            .context("colors in alacritty.toml is not a table")?;
+        let generated = generated["colors"]
+            .as_table()
+            .context("generated alacritty colors have no [colors] table")?;
+        for (name, values) in generated.iter() {
+            let table = colors
+                .entry(name)
+                .or_insert_with(toml_edit::table)
+                .as_table_mut()
+                .with_context(|| format!("colors.{} in alacritty.toml is not a table", name))?;
+            for (key, value) in values
+                .as_table()
+                .into_iter()
+                .flat_map(|values| values.iter())
+            {
+                table[key] = value.clone();
+            }
         }
 
-        let config_path = rofi_dir.join("config.rasi");
+        Ok(document.to_string())
+    }
+
+    fn generate_rofi_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("rofi").context("not installed")?;
 
         // Backup original config
         self.backup_config(&config_path)?;
 
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "rofi.rasi", color_scheme, &[])
+                    .context("Failed to write Rofi config")?;
+                report!("  ✓ Updated Rofi colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                let colors = self
+                    .template_engine
+                    .render_template("rofi.rasi", color_scheme)?;
+                let file = self.colors_file_name("rasi");
+                let include_path = config_path.with_file_name(&file);
+                let import = format!("@import \"{}\"", file);
+
+                // A block left over from inline mode comes after the import and would
+                // override it, so take it out while adding the import
+                let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+                if self.profile.is_none() && current_config.contains("/* DYNAMIC COLOR SCHEME") {
+                    let cleaned = format!(
+                        "{}\n",
+                        self.strip_dynamic_sections(&current_config).trim_end()
+                    );
+                    let updated_config = if cleaned.lines().any(|line| line.trim() == import) {
+                        cleaned
+                    } else {
+                        format!("{}\n{}", import, cleaned)
+                    };
+                    self.write_output(&include_path, &colors)
+                        .context("Failed to write Rofi colors")?;
+                    self.write_output(&config_path, &updated_config)
+                        .context("Failed to write Rofi config")?;
+                } else {
+                    self.write_include(&config_path, &include_path, &colors, &import, true)
+                        .context("Failed to write Rofi colors")?;
+                }
+                report!("  ✓ Updated Rofi colors");
+                return Ok(());
+            }
+        }
+
         // Read current config to preserve non-color settings
         let current_config = if config_path.exists() {
             std::fs::read_to_string(&config_path).unwrap_or_default()
@@ -204,7 +1179,7 @@ impl ConfigGenerator {
             self.remove_all_dynamic_sections(&current_config, &color_section)
         } else if current_config.contains("/* COLOR PALETTE") {
             // Replace old static color palette section
-            self.replace_section(
+            Self::replace_section(
                 &current_config,
                 "/* ═══════════════════════════════════════════════════════════════════ */\n/* COLOR PALETTE",
                 "/* ═══════════════════════════════════════════════════════════════════ */\n/* MAIN WINDOW",
@@ -213,77 +1188,508 @@ impl ConfigGenerator {
         } else {
             // No existing color section - add before window section or at the end
             if current_config.contains("/* MAIN WINDOW") {
-                self.replace_section(
+                Self::insert_before_section(
                     &current_config,
                     "/* ═══════════════════════════════════════════════════════════════════ */\n/* MAIN WINDOW",
-                    "",
-                    &format!("{}\n\n/* ═══════════════════════════════════════════════════════════════════ */\n/* MAIN WINDOW", color_section)
+                    &format!("{}\n\n", color_section),
                 )
             } else {
                 format!("{}\n\n{}", current_config, color_section)
             }
         };
 
-        std::fs::write(&config_path, updated_config).context("Failed to write Rofi config")?;
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write Rofi config")?;
 
-        println!("  ✓ Updated Rofi colors");
+        report!("  ✓ Updated Rofi colors");
         Ok(())
     }
 
-    fn generate_shell_colors(&self, color_scheme: &ColorScheme) -> Result<()> {
-        let shell_colors = self
-            .template_engine
-            .render_template("shell_colors.sh", color_scheme)?;
+    fn generate_mako_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("mako").context("not installed")?;
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "mako.conf", color_scheme, &[])
+                    .context("Failed to write mako config")?;
+                report!("  ✓ Updated mako colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                anyhow::bail!("mako only supports the inline and template modes");
+            }
+        }
+
+        let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+
+        // Global options only count before the first [criteria] section, so the base
+        // colors go on top and the urgency override goes last
+        let colors = format!(
+            "{}\nbackground-color={}\ntext-color={}\nborder-color={}\nprogress-color=over {}\n{}",
+            COLORS_START,
+            color_scheme.background,
+            color_scheme.foreground,
+            color_scheme.accent,
+            color_scheme.surface,
+            COLORS_END
+        );
+        let urgency = format!(
+            "{}\n[urgency=high]\nborder-color={}\n{}",
+            URGENCY_COLORS_START, color_scheme.error, COLORS_END
+        );
+
+        let updated_config = Self::upsert_block(&current_config, COLORS_START, &colors, true);
+        let updated_config =
+            Self::upsert_block(&updated_config, URGENCY_COLORS_START, &urgency, false);
+
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write mako config")?;
+
+        report!("  ✓ Updated mako colors");
+        Ok(())
+    }
+
+    fn generate_dunst_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("dunst").context("not installed")?;
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        match mode {
+            OutputMode::Inline => {}
+            OutputMode::Template => {
+                self.write_template(&config_path, "dunstrc", color_scheme, &[])
+                    .context("Failed to write dunst config")?;
+                report!("  ✓ Updated dunst colors");
+                return Ok(());
+            }
+            OutputMode::Include => {
+                anyhow::bail!("dunst only supports the inline and template modes");
+            }
+        }
+
+        let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+
+        // Appended so these values win over the user's own urgency sections
+        let urgency = |name: &str, frame: &str| {
+            format!(
+                "[{}]\n    background = \"{}\"\n    foreground = \"{}\"\n    frame_color = \"{}\"\n",
+                name, color_scheme.background, color_scheme.foreground, frame
+            )
+        };
+        let colors = format!(
+            "{}\n[global]\n    frame_color = \"{}\"\n    separator_color = frame\n\n{}\n{}\n{}{}",
+            COLORS_START,
+            color_scheme.accent,
+            urgency("urgency_low", &color_scheme.surface),
+            urgency("urgency_normal", &color_scheme.accent),
+            urgency("urgency_critical", &color_scheme.error),
+            COLORS_END
+        );
+
+        let updated_config = Self::upsert_block(&current_config, COLORS_START, &colors, false);
+
+        self.write_output(&config_path, &updated_config)
+            .context("Failed to write dunst config")?;
+
+        report!("  ✓ Updated dunst colors");
+        Ok(())
+    }
 
-        // Write to iro config directory
-        let iro_dir = dirs::config_dir()
-            .context("Failed to get config directory")?
-            .join("iro");
-        std::fs::create_dir_all(&iro_dir)?;
+    fn generate_nvim_lua(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        // iro_colors.lua is a module of iro's own, required from the user's config
+        if mode != OutputMode::Template {
+            anyhow::bail!("Neovim colors only support the template mode, not {}", mode);
+        }
+
+        let lua_path = self.app_config_path("nvim").context("not installed")?;
+        self.write_template(&lua_path, "nvim.lua", color_scheme, &[])
+            .context("Failed to write Neovim colors")?;
+
+        report!("  ✓ Updated Neovim colors (require('iro_colors'))");
+        Ok(())
+    }
+
+    fn generate_gtk_css(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        // colors.css is only @define-color lines, there are no other settings to keep
+        if mode != OutputMode::Template {
+            anyhow::bail!("GTK colors only support the template mode, not {}", mode);
+        }
 
-        let shell_colors_path = iro_dir.join("colors.sh");
-        std::fs::write(&shell_colors_path, shell_colors).context("Failed to write shell colors")?;
+        // GTK 3 apps and GTK 4/libadwaita apps read their own copy
+        for version in ["gtk-3.0", "gtk-4.0"] {
+            let css_path = self.config_dir.join(version).join("colors.css");
+            self.backup_config(&css_path)?;
+            self.write_template(&css_path, "gtk.css", color_scheme, &[])
+                .with_context(|| format!("Failed to write {} colors", version))?;
+        }
+
+        report!("  ✓ Updated GTK colors");
+        Ok(())
+    }
+
+    fn generate_shell_colors(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let shell_colors_path = self.write_shell_colors("shell", color_scheme, mode)?;
 
         // Make it executable
         #[cfg(unix)]
-        {
+        if !self.dry_run {
             use std::os::unix::fs::PermissionsExt;
             let mut perms = std::fs::metadata(&shell_colors_path)?.permissions();
             perms.set_mode(0o755);
             std::fs::set_permissions(&shell_colors_path, perms)?;
         }
 
-        println!("  ✓ Generated shell colors (source ~/.config/iro/colors.sh)");
+        report!("  ✓ Generated shell colors (source ~/.config/iro/colors.sh)");
         Ok(())
     }
 
-    fn generate_quickshell_config(&self, color_scheme: &ColorScheme) -> Result<()> {
-        // Check multiple possible quickshell locations
-        let home = dirs::home_dir().context("Failed to get home directory")?;
-        let possible_paths = [
-            self.config_dir.join("quickshell"),
-            home.join("Git/quick"),
-            home.join(".config/quickshell"),
-        ];
+    fn generate_fish_colors(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        self.write_shell_colors("fish", color_scheme, mode)?;
+        report!("  ✓ Generated fish colors (source ~/.config/iro/colors.fish)");
+        Ok(())
+    }
 
-        let quickshell_dir = possible_paths.iter().find(|p| p.exists());
-        let quickshell_dir = match quickshell_dir {
-            Some(dir) => dir,
-            None => anyhow::bail!("not installed"),
-        };
+    fn generate_nushell_colors(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        self.write_shell_colors("nushell", color_scheme, mode)?;
+        report!("  ✓ Generated nushell colors (source ~/.config/iro/colors.nu)");
+        Ok(())
+    }
 
-        let theme_path = quickshell_dir.join("Theme.qml");
+    /// Render the colors file for a shell output into the iro config directory.
+    /// These are iro's own files, so they are always rendered whole.
+    fn write_shell_colors(
+        &self,
+        key: &str,
+        color_scheme: &ColorScheme,
+        mode: OutputMode,
+    ) -> Result<PathBuf> {
+        if mode != OutputMode::Template {
+            anyhow::bail!(
+                "{} colors only support the template mode, not {}",
+                key,
+                mode
+            );
+        }
 
-        let rendered = self
+        let template = Self::template_name(key).context("No template for shell colors")?;
+        let shell_colors = self
             .template_engine
-            .render_template("quickshell-theme.qml", color_scheme)?;
+            .render_template(template, color_scheme)?;
+
+        let path = self
+            .app_config_path(key)
+            .with_context(|| format!("Failed to resolve {} colors path", key))?;
+        self.write_output(&path, &shell_colors)
+            .with_context(|| format!("Failed to write {} colors", key))
+    }
+
+    /// Color tmux's status line, pane borders and messages. In include mode the
+    /// settings go to `~/.config/iro/tmux-colors.conf`, sourced at the end of tmux.conf.
+    fn generate_tmux_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        let config_path = self.app_config_path("tmux").context("not installed")?;
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        let colors = format!(
+            "{}\n{}{}\n",
+            COLORS_START,
+            Self::generate_tmux_colors(color_scheme),
+            COLORS_END
+        );
+        match mode {
+            OutputMode::Template => {
+                self.write_template(&config_path, "tmux.conf", color_scheme, &[])
+                    .context("Failed to write tmux config")?;
+            }
+            OutputMode::Include => {
+                let include_path = tmux_colors_path(&self.config_dir);
+                // Last, so the colors win over styles set earlier in tmux.conf
+                let source = format!("source-file {}", include_path.display());
+                self.write_include(&config_path, &include_path, &colors, &source, false)
+                    .context("Failed to write tmux colors")?;
+            }
+            OutputMode::Inline => {
+                let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+                let updated_config =
+                    Self::upsert_block(&current_config, COLORS_START, colors.trim_end(), false);
+                self.write_output(&config_path, &updated_config)
+                    .context("Failed to write tmux config")?;
+            }
+        }
+
+        report!("  ✓ Updated tmux colors");
+        Ok(())
+    }
+
+    fn generate_tmux_colors(color_scheme: &ColorScheme) -> String {
+        let background = &color_scheme.background;
+        let foreground = &color_scheme.foreground;
+        let accent = &color_scheme.accent;
+        let surface = &color_scheme.surface;
+        format!(
+            "set -g status-style \"bg={background},fg={foreground}\"\n\
+             set -g window-status-current-style \"fg={accent},bold\"\n\
+             set -g pane-border-style \"fg={surface}\"\n\
+             set -g pane-active-border-style \"fg={accent}\"\n\
+             set -g message-style \"bg={accent},fg={background}\"\n\
+             set -g message-command-style \"bg={accent},fg={background}\"\n\
+             set -g mode-style \"bg={accent},fg={background}\"\n"
+        )
+    }
+
+    /// Keep a `[palettes.iro]` table in starship.toml and select it with
+    /// `palette = "iro"`. The file is edited as TOML, so the rest of it, comments
+    /// included, stays as it was.
+    fn generate_starship_palette(
+        &self,
+        color_scheme: &ColorScheme,
+        mode: OutputMode,
+    ) -> Result<()> {
+        let config_path = self.app_config_path("starship").context("not installed")?;
+
+        if mode != OutputMode::Inline {
+            anyhow::bail!("starship only supports the inline mode");
+        }
+
+        // Backup original config
+        self.backup_config(&config_path)?;
+
+        let current_config = std::fs::read_to_string(&config_path).unwrap_or_default();
+        let mut document: toml_edit::DocumentMut = current_config
+            .parse()
+            .context("Failed to parse starship.toml")?;
+
+        document["palette"] = toml_edit::value("iro");
+        let palettes = document
+            .entry("palettes")
+            .or_insert_with(|| {
+                // Only [palettes.iro] gets a header, not an empty [palettes]
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                toml_edit::Item::Table(table)
+            })
+            .as_table_mut()
+            .context("palettes in starship.toml is not a table")?;
+        let palette = palettes
+            .entry("iro")
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .context("palettes.iro in starship.toml is not a table")?;
+        for (name, color) in Self::starship_colors(color_scheme) {
+            palette[name.as_str()] = toml_edit::value(color);
+        }
+
+        self.write_output(&config_path, &document.to_string())
+            .context("Failed to write starship.toml")?;
+
+        report!("  ✓ Updated Starship palette");
+        Ok(())
+    }
+
+    /// Names the iro palette offers in starship styles, e.g. `style = "bold accent"`:
+    /// the scheme's roles, the ANSI color names and color0 to color15
+    fn starship_colors(color_scheme: &ColorScheme) -> Vec<(String, String)> {
+        let mut colors: Vec<(String, String)> = [
+            ("background", &color_scheme.background),
+            ("foreground", &color_scheme.foreground),
+            ("accent", &color_scheme.accent),
+            ("secondary", &color_scheme.secondary),
+            ("surface", &color_scheme.surface),
+            ("error", &color_scheme.error),
+        ]
+        .into_iter()
+        .map(|(name, color)| (name.to_string(), color.clone()))
+        .collect();
+
+        let ansi_names = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+        colors.extend(
+            ansi_names
+                .iter()
+                .zip(&color_scheme.colors)
+                .map(|(name, color)| (name.to_string(), color.clone())),
+        );
+        colors.extend(
+            color_scheme
+                .colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| (format!("color{}", i), color.clone())),
+        );
+        colors
+    }
+
+    fn generate_css_vars(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        self.write_style_vars("css", "CSS", color_scheme, mode)
+    }
 
-        std::fs::write(&theme_path, rendered).context("Failed to write QuickShell theme")?;
+    fn generate_scss_vars(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        self.write_style_vars("scss", "SCSS", color_scheme, mode)
+    }
+
+    /// Render the `css` or `scss` variables file for web projects to import
+    fn write_style_vars(
+        &self,
+        key: &str,
+        label: &str,
+        color_scheme: &ColorScheme,
+        mode: OutputMode,
+    ) -> Result<()> {
+        // A variables file has nothing else in it worth keeping
+        if mode != OutputMode::Template {
+            anyhow::bail!(
+                "{} variables only support the template mode, not {}",
+                label,
+                mode
+            );
+        }
 
-        println!("  ✓ Updated QuickShell theme");
+        let path = self
+            .app_config_path(key)
+            .with_context(|| format!("Failed to resolve {} variables path", label))?;
+        let template = Self::template_name(key).context("no template")?;
+        self.write_template(&path, template, color_scheme, &[])
+            .with_context(|| format!("Failed to write {} variables", label))?;
+
+        report!(
+            "  ✓ Generated {} variables ({})",
+            label,
+            self.output_path(&path).display()
+        );
         Ok(())
     }
 
+    fn generate_quickshell_config(
+        &self,
+        color_scheme: &ColorScheme,
+        mode: OutputMode,
+    ) -> Result<()> {
+        // Theme.qml is a standalone QML singleton, so there is nothing to splice into
+        if mode != OutputMode::Template {
+            anyhow::bail!("QuickShell only supports the template mode, not {}", mode);
+        }
+
+        let theme_path = self
+            .app_config_path("quickshell")
+            .context("not installed")?;
+
+        self.write_template(&theme_path, "quickshell-theme.qml", color_scheme, &[])
+            .context("Failed to write QuickShell theme")?;
+
+        report!("  ✓ Updated QuickShell theme");
+        Ok(())
+    }
+
+    fn generate_tty_config(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        // vtrgb is iro's own file, so it is always rendered whole
+        if mode != OutputMode::Template {
+            anyhow::bail!("TTY colors only support the template mode, not {}", mode);
+        }
+
+        // setvtrgb format: one line each of 16 red, green and blue values
+        let colors = color_scheme
+            .colors
+            .iter()
+            .take(16)
+            .map(|hex| hex_to_rgb(hex))
+            .collect::<Result<Vec<_>>>()?;
+        let vtrgb = (0..3)
+            .map(|channel| {
+                colors
+                    .iter()
+                    .map(|rgb| rgb[channel].to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let vtrgb_path = self
+            .app_config_path("tty")
+            .context("Failed to resolve vtrgb path")?;
+        self.write_output(&vtrgb_path, &format!("{}\n", vtrgb))
+            .context("Failed to write vtrgb")?;
+
+        // Recolor the console right away when iro itself runs on one
+        let on_console = std::env::var("TERM").is_ok_and(|term| term == "linux");
+        if self.output_dir.is_none() && !self.dry_run && on_console {
+            let sequences = self.generate_tty_colors(color_scheme)?;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/tty")
+                .and_then(|mut tty| std::io::Write::write_all(&mut tty, sequences.as_bytes()))
+                .context("Failed to write to /dev/tty")?;
+            report!("  ✓ Updated TTY colors");
+        } else {
+            report!(
+                "  ✓ Generated TTY colors (apply with: sudo setvtrgb {})",
+                vtrgb_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    fn generate_pywal_json(&self, color_scheme: &ColorScheme, mode: OutputMode) -> Result<()> {
+        // colors.json is pywal's own file, so it is always written whole
+        if mode != OutputMode::Template {
+            anyhow::bail!("pywal colors only support the template mode, not {}", mode);
+        }
+
+        // Written by hand to keep pywal's color0..color15 order
+        let colors = color_scheme
+            .colors
+            .iter()
+            .take(16)
+            .enumerate()
+            .map(|(i, color)| format!("    \"color{}\": \"{}\"", i, color))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let pywal = format!(
+            r#"{{
+  "special": {{
+    "background": "{}",
+    "foreground": "{}",
+    "cursor": "{}"
+  }},
+  "colors": {{
+{}
+  }}
+}}
+"#,
+            color_scheme.background, color_scheme.foreground, color_scheme.accent, colors
+        );
+
+        let pywal_path = self
+            .app_config_path("pywal")
+            .context("Failed to resolve pywal colors path")?;
+        let pywal_path = self
+            .write_output(&pywal_path, &pywal)
+            .context("Failed to write pywal colors")?;
+
+        report!("  ✓ Generated pywal colors ({})", pywal_path.display());
+        Ok(())
+    }
+
+    /// Linux console palette escapes (`ESC ] P n rrggbb`) for the 16 terminal colors
+    fn generate_tty_colors(&self, color_scheme: &ColorScheme) -> Result<String> {
+        let mut output = String::with_capacity(16 * 10 + 3);
+
+        for (i, color) in color_scheme.colors.iter().take(16).enumerate() {
+            output.push_str(&format!("\x1b]P{:X}{}", i, color.trim_start_matches('#')));
+        }
+        // Clear so the new background fills the screen
+        output.push_str("\x1b[H\x1b[2J");
+
+        Ok(output)
+    }
+
     fn generate_hyprland_colors(&self, color_scheme: &ColorScheme) -> Result<String> {
         Ok(format!(
             r#"
@@ -295,13 +1701,13 @@ $magenta = rgb({})
 $accent = rgb({})
 $secondary = rgb({})
 $text = rgb({})
-$surface = rgb({})
-$surface0 = rgb({})
-$base = rgb({})
+$surface = {}
+$surface0 = {}
+$base = {}
 $mantle = rgb(292c3c)
 $crust = rgb(232634)
 $error = rgb({})
-"#,
+{}"#,
             color_scheme
                 .colors
                 .get(1)
@@ -325,13 +1731,73 @@ $error = rgb({})
             color_scheme.accent.trim_start_matches('#'),
             color_scheme.secondary.trim_start_matches('#'),
             color_scheme.foreground.trim_start_matches('#'),
-            color_scheme.surface.trim_start_matches('#'),
-            color_scheme.surface.trim_start_matches('#'), // surface0 - same as surface
-            color_scheme.background.trim_start_matches('#'),
+            Self::hyprland_color(&color_scheme.surface, self.config.theme.surface_alpha),
+            // surface0 - same as surface
+            Self::hyprland_color(&color_scheme.surface, self.config.theme.surface_alpha),
+            Self::hyprland_color(&color_scheme.background, self.config.theme.background_alpha),
             color_scheme.error.trim_start_matches('#'),
+            self.generate_hyprland_shadow(color_scheme)?,
         ))
     }
 
+    /// `$shadow` plus the decoration setting that uses it, per `theme.shadow_color`
+    fn generate_hyprland_shadow(&self, color_scheme: &ColorScheme) -> Result<String> {
+        let shadow = match self.config.theme.shadow_color.as_str() {
+            "none" => return Ok(String::new()),
+            "accent" => format!("{}99", color_scheme.accent.trim_start_matches('#')),
+            _ => {
+                let bg = hex_to_rgb(&color_scheme.background)?;
+                format!("{:02x}{:02x}{:02x}ee", bg[0] / 2, bg[1] / 2, bg[2] / 2)
+            }
+        };
+
+        Ok(format!(
+            "$shadow = rgba({})\ndecoration:shadow:color = $shadow\n",
+            shadow
+        ))
+    }
+
+    /// `rgb(rrggbb)`, or `rgba(rrggbbaa)` when `alpha` is below 1.0
+    fn hyprland_color(hex: &str, alpha: f32) -> String {
+        let hex = hex.trim_start_matches('#');
+        if alpha < 1.0 {
+            let alpha = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+            format!("rgba({}{:02x})", hex, alpha)
+        } else {
+            format!("rgb({})", hex)
+        }
+    }
+
+    fn generate_alacritty_colors(color_scheme: &ColorScheme) -> String {
+        const NAMES: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+        let table = |name: &str, colors: &[String]| {
+            let mut table = format!("[colors.{}]\n", name);
+            for (name, color) in NAMES.iter().zip(colors) {
+                table.push_str(&format!("{} = \"{}\"\n", name, color));
+            }
+            table
+        };
+
+        format!(
+            "[colors.primary]\nbackground = \"{}\"\nforeground = \"{}\"\n\n\
+             [colors.cursor]\ntext = \"{}\"\ncursor = \"{}\"\n\n\
+             [colors.selection]\ntext = \"{}\"\nbackground = \"{}\"\n\n{}\n{}",
+            color_scheme.background,
+            color_scheme.foreground,
+            color_scheme.background,
+            color_scheme.accent,
+            color_scheme.background,
+            color_scheme.accent,
+            table(
+                "normal",
+                &color_scheme.colors[..8.min(color_scheme.colors.len())]
+            ),
+            table("bright", color_scheme.colors.get(8..).unwrap_or_default()),
+        )
+    }
+
     fn generate_kitty_colors(&self, color_scheme: &ColorScheme) -> Result<String> {
         let mut output = String::with_capacity(1024);
 
@@ -393,56 +1859,276 @@ $error = rgb({})
         Ok(output)
     }
 
+    /// Map a live config path to where it should be written
+    fn output_path(&self, path: &Path) -> PathBuf {
+        let Some(output_dir) = &self.output_dir else {
+            return path.to_path_buf();
+        };
+
+        let home = dirs::home_dir().unwrap_or_default();
+        if let Ok(relative) = path.strip_prefix(&self.config_dir) {
+            output_dir.join(relative)
+        } else if let Ok(relative) = path.strip_prefix(&home) {
+            output_dir.join(relative)
+        } else {
+            output_dir.join(path.file_name().unwrap_or_default())
+        }
+    }
+
+    /// Write a generated file, returning the path it actually ended up at
+    fn write_output(&self, path: &Path, contents: &str) -> Result<PathBuf> {
+        let target = self.output_path(path);
+
+        if self.dry_run {
+            let current = std::fs::read_to_string(&target).unwrap_or_default();
+            let label = target.display();
+            emit(&diff::unified_diff(
+                &current,
+                contents,
+                &format!("{} (current)", label),
+                &format!("{} (iro)", label),
+            ));
+            return Ok(target);
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&target, contents)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        Ok(target)
+    }
+
+    /// Render `template_name` and replace `path` with it
+    fn write_template(
+        &self,
+        path: &Path,
+        template_name: &str,
+        color_scheme: &ColorScheme,
+        extra_vars: &[(&str, &str)],
+    ) -> Result<()> {
+        let rendered = self.template_engine.render_template_with_vars(
+            template_name,
+            color_scheme,
+            extra_vars,
+        )?;
+        self.write_output(path, &rendered)?;
+        Ok(())
+    }
+
+    /// Write `colors` to `include_path` and make sure `config_path` pulls it in with
+    /// `include_line`, placed at the top (`prepend`) or bottom of the config
+    fn write_include(
+        &self,
+        config_path: &Path,
+        include_path: &Path,
+        colors: &str,
+        include_line: &str,
+        prepend: bool,
+    ) -> Result<()> {
+        self.write_output(include_path, colors)?;
+
+        // Profiles are wired up by hand, e.g. from a separate kitty session config
+        if self.profile.is_some() {
+            return Ok(());
+        }
+
+        let current_config = std::fs::read_to_string(config_path).unwrap_or_default();
+        if current_config
+            .lines()
+            .any(|line| line.trim() == include_line)
+        {
+            return Ok(());
+        }
+
+        let updated_config = if prepend {
+            format!("{}\n{}", include_line, current_config)
+        } else {
+            format!("{}\n{}\n", current_config.trim_end(), include_line)
+        };
+        self.write_output(config_path, &updated_config)?;
+        Ok(())
+    }
+
+    /// Where the original of `config_path` is kept before iro first edits it
+    pub fn backup_path(config_path: &Path) -> PathBuf {
+        config_path.with_extension("conf.iro.bak")
+    }
+
+    /// Where `backup.strategy = "timestamped"` keeps the copy made at `timestamp`
+    fn timestamped_backup_path(config_path: &Path, timestamp: u64) -> PathBuf {
+        config_path.with_extension(format!("conf.iro.{}.bak", timestamp))
+    }
+
+    /// Timestamped backups of `config_path`, oldest first
+    pub fn timestamped_backups(config_path: &Path) -> Vec<PathBuf> {
+        let (Some(dir), Some(stem)) = (config_path.parent(), config_path.file_stem()) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}.conf.iro.", stem.to_string_lossy());
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut backups: Vec<(u64, PathBuf)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let timestamp = name
+                    .to_str()?
+                    .strip_prefix(&prefix)?
+                    .strip_suffix(".bak")?
+                    .parse()
+                    .ok()?;
+                Some((timestamp, entry.path()))
+            })
+            .collect();
+        backups.sort();
+        backups.into_iter().map(|(_, path)| path).collect()
+    }
+
     fn backup_config(&self, config_path: &PathBuf) -> Result<()> {
-        if config_path.exists() {
-            let backup_path = config_path.with_extension("conf.iro.bak");
-            if !backup_path.exists() {
-                std::fs::copy(config_path, &backup_path)
-                    .with_context(|| format!("Failed to backup {}", config_path.display()))?;
-                println!(
-                    "  💾 Backed up original config to {}",
-                    backup_path.display()
-                );
+        // Sandboxed, profile and dry runs never touch live app configs, so there is nothing
+        // to back up
+        if self.output_dir.is_some() || self.profile.is_some() || self.dry_run {
+            return Ok(());
+        }
+        if !config_path.exists() {
+            return Ok(());
+        }
+
+        match self.config.backup.strategy {
+            BackupStrategy::None => {}
+            BackupStrategy::Once => {
+                let backup_path = Self::backup_path(config_path);
+                if !backup_path.exists() {
+                    std::fs::copy(config_path, &backup_path)
+                        .with_context(|| format!("Failed to backup {}", config_path.display()))?;
+                    report!(
+                        "  💾 Backed up original config to {}",
+                        backup_path.display()
+                    );
+                }
+            }
+            BackupStrategy::Timestamped => {
+                let backups = Self::timestamped_backups(config_path);
+                // Nothing changed since the last backup, another copy would only push
+                // an older state out
+                let unchanged = backups.last().is_some_and(|newest| {
+                    std::fs::read(newest).ok() == std::fs::read(config_path).ok()
+                });
+                if !unchanged {
+                    let timestamp = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|elapsed| elapsed.as_secs())
+                        .unwrap_or_default();
+                    let backup_path = Self::timestamped_backup_path(config_path, timestamp);
+                    std::fs::copy(config_path, &backup_path)
+                        .with_context(|| format!("Failed to backup {}", config_path.display()))?;
+                    report!("  💾 Backed up config to {}", backup_path.display());
+                }
+                self.prune_backups(config_path);
             }
         }
         Ok(())
     }
 
+    /// Delete the oldest timestamped backups of `config_path` beyond `backup.keep`
+    fn prune_backups(&self, config_path: &Path) {
+        let backups = Self::timestamped_backups(config_path);
+        let excess = backups.len().saturating_sub(self.config.backup.keep.max(1));
+        for backup in &backups[..excess] {
+            if let Err(e) = std::fs::remove_file(backup) {
+                report!("  ⚠ Failed to delete {}: {}", backup.display(), e);
+            }
+        }
+    }
+
+    /// Replace the block from `start_marker` through the next `COLORS_END` with `block`,
+    /// or add `block` at the top (`prepend`) or bottom when there is none yet. Both
+    /// markers only count at the start of a line.
+    fn upsert_block(content: &str, start_marker: &str, block: &str, prepend: bool) -> String {
+        if let Some(start) = find_line(content, start_marker, 0) {
+            if let Some(end) = find_line(content, COLORS_END, start + start_marker.len()) {
+                let end = end + COLORS_END.len();
+                return format!("{}{}{}", &content[..start], block, &content[end..]);
+            }
+        }
+
+        if content.trim().is_empty() {
+            format!("{}\n", block)
+        } else if prepend {
+            format!("{}\n\n{}", block, content)
+        } else {
+            format!("{}\n\n{}\n", content.trim_end(), block)
+        }
+    }
+
+    /// Replace everything from the line starting with `start_marker` up to the next line
+    /// starting with `end_marker`, which is kept. The section runs to the end of the
+    /// content when `end_marker` is empty or missing. Content without `start_marker`
+    /// is returned as is.
     fn replace_section(
-        &self,
         content: &str,
         start_marker: &str,
         end_marker: &str,
         replacement: &str,
     ) -> String {
-        if let Some(start_pos) = content.find(start_marker) {
-            let before = &content[..start_pos];
-
-            if end_marker.is_empty() {
-                format!("{}{}", before, replacement)
-            } else if let Some(end_pos) = content[start_pos..].find(end_marker) {
-                let after = &content[start_pos + end_pos..];
-                format!("{}{}{}", before, replacement, after)
-            } else {
-                format!("{}{}", before, replacement)
-            }
-        } else {
-            content.to_string()
+        let Some(start) = find_line(content, start_marker, 0) else {
+            return content.to_string();
+        };
+        let before = &content[..start];
+
+        let end = (!end_marker.is_empty())
+            .then(|| find_line(content, end_marker, start + start_marker.len()))
+            .flatten();
+        match end {
+            Some(end) => format!("{}{}{}", before, replacement, &content[end..]),
+            None => format!("{}{}", before, replacement),
         }
     }
 
-    fn insert_after_section(&self, content: &str, marker: &str, insertion: &str) -> String {
-        if let Some(pos) = content.find(marker) {
-            let end_pos = pos + marker.len();
-            let before = &content[..end_pos];
-            let after = &content[end_pos..];
-            format!("{}\n{}{}", before, insertion, after)
-        } else {
-            format!("{}\n{}", content, insertion)
+    /// Insert `insertion` after the first line starting with `marker`, or append it
+    /// when there is no such line. A `}` marker thus only matches a closing brace at
+    /// the start of a line, not one nested deeper or inside a value.
+    fn insert_after_section(content: &str, marker: &str, insertion: &str) -> String {
+        let Some(start) = find_line(content, marker, 0) else {
+            return format!("{}\n{}", content, insertion);
+        };
+
+        let line_end = content[start..]
+            .find('\n')
+            .map_or(content.len(), |len| start + len + 1);
+        let (before, after) = content.split_at(line_end);
+        let separator = if before.ends_with('\n') { "" } else { "\n" };
+        format!("{}{}{}{}", before, separator, insertion, after)
+    }
+
+    /// Insert `insertion` right before the first line starting with `marker`, keeping
+    /// the marker and everything after it, or append it when there is no such line
+    fn insert_before_section(content: &str, marker: &str, insertion: &str) -> String {
+        match find_line(content, marker, 0) {
+            Some(start) => format!("{}{}{}", &content[..start], insertion, &content[start..]),
+            None => format!("{}\n{}", content, insertion),
         }
     }
 
     fn remove_all_dynamic_sections(&self, content: &str, new_section: &str) -> String {
+        let result = self.strip_dynamic_sections(content);
+
+        // Now insert the new section before /* MAIN WINDOW if it exists
+        if let Some(main_window_pos) = result.find("/* ═══════════════════════════════════════════════════════════════════ */\n/* MAIN WINDOW") {
+            // Insert new_section before the MAIN WINDOW marker, preserving everything after
+            format!("{}{}\n\n{}", &result[..main_window_pos], new_section, &result[main_window_pos..])
+        } else {
+            // No MAIN WINDOW section found, just append at the end
+            format!("{}\n\n{}", result, new_section)
+        }
+    }
+
+    /// Remove every inline `/* DYNAMIC COLOR SCHEME */` block iro added to a rasi file
+    fn strip_dynamic_sections(&self, content: &str) -> String {
         let mut result = content.to_string();
 
         // Pattern to match: /* ═══...═══ */\n/* DYNAMIC COLOR SCHEME...*/\n\n* { ... }\n\n
@@ -478,13 +2164,384 @@ $error = rgb({})
             break;
         }
 
-        // Now insert the new section before /* MAIN WINDOW if it exists
-        if let Some(main_window_pos) = result.find("/* ═══════════════════════════════════════════════════════════════════ */\n/* MAIN WINDOW") {
-            // Insert new_section before the MAIN WINDOW marker, preserving everything after
-            format!("{}{}\n\n{}", &result[..main_window_pos], new_section, &result[main_window_pos..])
-        } else {
-            // No MAIN WINDOW section found, just append at the end
-            format!("{}\n\n{}", result, new_section)
+        result
+    }
+}
+
+/// Byte offset of the first `marker` at or after `from` that starts a line, so a
+/// marker mentioned mid-line (in a comment or a value) isn't taken for a section
+/// boundary. A `"\n"` marker finds the first blank line.
+fn find_line(content: &str, marker: &str, from: usize) -> Option<usize> {
+    if marker.is_empty() {
+        return None;
+    }
+
+    let mut from = from;
+    while let Some(offset) = content.get(from..)?.find(marker) {
+        let pos = from + offset;
+        if pos == 0 || content[..pos].ends_with('\n') {
+            return Some(pos);
+        }
+        from = pos + marker.len();
+    }
+    None
+}
+
+/// tmux's own config: `~/.tmux.conf`, or `tmux/tmux.conf` in the config dir where
+/// tmux 3.1 and later also look. None when neither exists.
+pub fn tmux_config_path(config_dir: &Path) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    [
+        home.join(".tmux.conf"),
+        config_dir.join("tmux").join("tmux.conf"),
+    ]
+    .into_iter()
+    .find(|path| path.exists())
+}
+
+/// The colors file tmux.conf sources in include mode
+pub fn tmux_colors_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("iro").join("tmux-colors.conf")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HYPRLAND_CONF: &str = "\
+monitor = ,preferred,auto,1
+$terminal = kitty
+exec-once = waybar & hyprpaper
+
+input {
+    kb_layout = us
+    touchpad {
+        natural_scroll = true
+    }
+}
+
+general {
+    gaps_in = 5
+    col.active_border = $accent
+}
+
+bind = SUPER, Return, exec, $terminal
+";
+
+    const COLORS: &str =
+        "\n# Dynamic Color scheme - Generated by iro\n$accent = rgb(aabbcc)\n$base = rgb(111111)\n";
+    const NEW_COLORS: &str =
+        "\n# Dynamic Color scheme - Generated by iro\n$accent = rgb(ddeeff)\n$base = rgb(222222)\n";
+
+    #[test]
+    fn find_line_skips_markers_mid_line() {
+        let content = "a = b # Color scheme\n# Color scheme\n";
+        assert_eq!(find_line(content, "# Color scheme", 0), Some(21));
+        assert_eq!(find_line(content, "# Color scheme", 22), None);
+        assert_eq!(find_line(content, "missing", 0), None);
+        assert_eq!(find_line(content, "", 0), None);
+    }
+
+    #[test]
+    fn find_line_finds_blank_lines() {
+        assert_eq!(find_line("a\nb\n\nc\n", "\n", 0), Some(4));
+        assert_eq!(find_line("a\nb\n", "\n", 0), None);
+    }
+
+    #[test]
+    fn replace_section_without_start_marker_is_unchanged() {
+        let content = "foo\nbar\n";
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "# END", "new\n"),
+            content
+        );
+    }
+
+    #[test]
+    fn replace_section_keeps_end_marker_and_rest() {
+        let content = "top\n# START\nold\n# END\nbottom\n";
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "# END", "# START\nnew\n"),
+            "top\n# START\nnew\n# END\nbottom\n"
+        );
+    }
+
+    #[test]
+    fn replace_section_uses_first_marker_at_line_start() {
+        let content = "x = 1 # START\n# START\nold\n# END\nmid\n# START\nsecond\n# END\n";
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "# END", "# START\nnew\n"),
+            "x = 1 # START\n# START\nnew\n# END\nmid\n# START\nsecond\n# END\n"
+        );
+    }
+
+    #[test]
+    fn replace_section_ignores_end_marker_mid_line() {
+        let content = "# START\nold # END\n# END\nrest\n";
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "# END", "new\n"),
+            "new\n# END\nrest\n"
+        );
+    }
+
+    #[test]
+    fn replace_section_with_empty_or_missing_end_runs_to_the_end() {
+        let content = "top\n# START\nold\nmore\n";
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "", "new\n"),
+            "top\nnew\n"
+        );
+        assert_eq!(
+            ConfigGenerator::replace_section(content, "# START", "# END", "new\n"),
+            "top\nnew\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_section_appends_without_marker() {
+        assert_eq!(
+            ConfigGenerator::insert_after_section("a = 1\n", "}", "b = 2\n"),
+            "a = 1\n\nb = 2\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_section_skips_nested_and_inline_braces() {
+        let content = "x = ${HOME}\nblock {\n    inner {\n    }\n} # block\nafter\n";
+        assert_eq!(
+            ConfigGenerator::insert_after_section(content, "}", "new\n"),
+            "x = ${HOME}\nblock {\n    inner {\n    }\n} # block\nnew\nafter\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_section_at_last_line() {
+        assert_eq!(
+            ConfigGenerator::insert_after_section("block {\n}", "}", "new\n"),
+            "block {\n}\nnew\n"
+        );
+    }
+
+    #[test]
+    fn insert_before_section_keeps_marker_and_rest() {
+        let content = "a # TAB BAR\n# TAB BAR\nb\n";
+        assert_eq!(
+            ConfigGenerator::insert_before_section(content, "# TAB BAR", "new\n"),
+            "a # TAB BAR\nnew\n# TAB BAR\nb\n"
+        );
+        assert_eq!(
+            ConfigGenerator::insert_before_section("a\n", "# TAB BAR", "new\n"),
+            "a\n\nnew\n"
+        );
+    }
+
+    #[test]
+    fn upsert_block_replaces_existing_block() {
+        let block = format!("{}\nnew\n{}", COLORS_START, COLORS_END);
+        let content = format!("top\n{}\nold\n{}\nbottom\n", COLORS_START, COLORS_END);
+        assert_eq!(
+            ConfigGenerator::upsert_block(&content, COLORS_START, &block, false),
+            format!("top\n{}\nbottom\n", block)
+        );
+    }
+
+    #[test]
+    fn upsert_block_adds_missing_block() {
+        let block = format!("{}\nnew\n{}", COLORS_START, COLORS_END);
+        assert_eq!(
+            ConfigGenerator::upsert_block("", COLORS_START, &block, false),
+            format!("{}\n", block)
+        );
+        assert_eq!(
+            ConfigGenerator::upsert_block("a\n", COLORS_START, &block, true),
+            format!("{}\n\na\n", block)
+        );
+        assert_eq!(
+            ConfigGenerator::upsert_block("a\n\n", COLORS_START, &block, false),
+            format!("a\n\n{}\n", block)
+        );
+    }
+
+    #[test]
+    fn upsert_block_ignores_markers_mid_line() {
+        let block = format!("{}\nnew\n{}", COLORS_START, COLORS_END);
+        let content = format!("note = \"{}\"\nkeep\n", COLORS_START);
+        assert_eq!(
+            ConfigGenerator::upsert_block(&content, COLORS_START, &block, false),
+            format!("{}\n\n{}\n", content.trim_end(), block)
+        );
+    }
+
+    #[test]
+    fn hyprland_colors_go_after_the_first_top_level_block() {
+        let merged = ConfigGenerator::merge_hyprland_colors(HYPRLAND_CONF, COLORS);
+        let input_end = merged.find("\n}\n").unwrap() + 3;
+        assert!(merged[input_end..].starts_with("\n# Dynamic Color scheme"));
+        assert!(merged.contains("    touchpad {\n        natural_scroll = true\n    }\n}\n"));
+        assert!(merged.ends_with(&format!(
+            "$base = rgb(111111)\n{}\n\ngeneral {{\n    gaps_in = 5\n    col.active_border = $accent\n}}\n\nbind = SUPER, Return, exec, $terminal\n",
+            COLORS_END
+        )));
+    }
+
+    #[test]
+    fn hyprland_colors_are_replaced_in_place() {
+        let first = ConfigGenerator::merge_hyprland_colors(HYPRLAND_CONF, COLORS);
+        let second = ConfigGenerator::merge_hyprland_colors(&first, NEW_COLORS);
+        assert_eq!(second, first.replace(COLORS, NEW_COLORS));
+        assert_eq!(
+            ConfigGenerator::merge_hyprland_colors(&second, NEW_COLORS),
+            second
+        );
+    }
+
+    #[test]
+    fn hyprland_sections_without_end_marker_keep_what_follows() {
+        // Written by versions that didn't close the section
+        let old = HYPRLAND_CONF.replacen("}\n\n", &format!("}}\n{}\n", COLORS), 1);
+        let merged = ConfigGenerator::merge_hyprland_colors(&old, NEW_COLORS);
+        assert_eq!(
+            merged,
+            ConfigGenerator::merge_hyprland_colors(HYPRLAND_CONF, NEW_COLORS)
+        );
+
+        let old = "# Color scheme\n$accent = rgb(000000)\n# General settings\ngeneral {\n}\n";
+        assert_eq!(
+            ConfigGenerator::merge_hyprland_colors(old, NEW_COLORS),
+            format!(
+                "{}{}\n\n# General settings\ngeneral {{\n}}\n",
+                NEW_COLORS.trim_start(),
+                COLORS_END
+            )
+        );
+    }
+
+    #[test]
+    fn generator_output_is_collected_per_thread() {
+        let (value, output) = captured(|| {
+            report!("  ✓ Updated {}", "Kitty colors");
+            emit("diff\n");
+            7
+        });
+        assert_eq!(value, 7);
+        assert_eq!(output, "  ✓ Updated Kitty colors\ndiff\n");
+        // Back to printing once the generator is done
+        assert!(CAPTURED.with(|captured| captured.borrow().is_none()));
+    }
+
+    fn scheme() -> ColorScheme {
+        ColorScheme {
+            background: "#1a1b26".to_string(),
+            foreground: "#c0caf5".to_string(),
+            colors: (0..16)
+                .map(|i| format!("#{:02x}{:02x}{:02x}", i * 16, 0x40, 0x80))
+                .collect(),
+            accent: "#7aa2f7".to_string(),
+            secondary: "#bb9af7".to_string(),
+            surface: "#24283b".to_string(),
+            error: "#f7768e".to_string(),
         }
     }
+
+    #[test]
+    fn alacritty_colors_parse_back_as_toml() {
+        let scheme = scheme();
+
+        let toml: toml::Value =
+            toml::from_str(&ConfigGenerator::generate_alacritty_colors(&scheme)).unwrap();
+        let colors = &toml["colors"];
+        assert_eq!(colors["primary"]["background"].as_str(), Some("#1a1b26"));
+        assert_eq!(colors["primary"]["foreground"].as_str(), Some("#c0caf5"));
+
+        for (table, offset) in [("normal", 0), ("bright", 8)] {
+            let table = colors[table].as_table().unwrap();
+            assert_eq!(table.len(), 8);
+            for (i, name) in [
+                "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+            ]
+            .iter()
+            .enumerate()
+            {
+                assert_eq!(
+                    table[*name].as_str(),
+                    Some(scheme.colors[offset + i].as_str())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn alacritty_import_is_added_once() {
+        assert_eq!(
+            ConfigGenerator::add_alacritty_import("", "iro-colors.toml").unwrap(),
+            Some("[general]\nimport = [\"iro-colors.toml\"]\n".to_string())
+        );
+        assert_eq!(
+            ConfigGenerator::add_alacritty_import(
+                "[general]\nimport = [\"~/themes/nord.toml\"]\n",
+                "iro-colors.toml"
+            )
+            .unwrap(),
+            Some("[general]\nimport = [\"~/themes/nord.toml\", \"iro-colors.toml\"]\n".to_string())
+        );
+        assert_eq!(
+            ConfigGenerator::add_alacritty_import(
+                "[general]\nimport = [\n  \"nord.toml\",\n]\n",
+                "iro-colors.toml"
+            )
+            .unwrap(),
+            Some(
+                "[general]\nimport = [\n  \"nord.toml\",\n  \"iro-colors.toml\",\n]\n".to_string()
+            )
+        );
+
+        // Already imported, in either quote style or by a full path
+        for config in [
+            "[general]\nimport = ['iro-colors.toml']\n",
+            "import = [\"~/.config/alacritty/iro-colors.toml\"]\n",
+        ] {
+            assert_eq!(
+                ConfigGenerator::add_alacritty_import(config, "iro-colors.toml").unwrap(),
+                None,
+                "{}",
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn alacritty_inline_colors_fill_in_the_users_tables() {
+        let config = "\
+[font]
+size = 11
+
+[colors]
+draw_bold_text_with_bright_colors = true
+
+[colors.primary]
+background = \"#000000\"
+dim_foreground = \"#808080\"
+";
+        let updated = ConfigGenerator::merge_alacritty_colors(config, &scheme()).unwrap();
+        let toml: toml::Value = toml::from_str(&updated).unwrap();
+        assert_eq!(toml["font"]["size"].as_integer(), Some(11));
+
+        let colors = &toml["colors"];
+        assert_eq!(
+            colors["draw_bold_text_with_bright_colors"].as_bool(),
+            Some(true)
+        );
+        assert_eq!(colors["primary"]["background"].as_str(), Some("#1a1b26"));
+        assert_eq!(
+            colors["primary"]["dim_foreground"].as_str(),
+            Some("#808080")
+        );
+        assert_eq!(colors["bright"]["white"].as_str(), Some("#f04080"));
+
+        // Running again changes nothing
+        assert_eq!(
+            ConfigGenerator::merge_alacritty_colors(&updated, &scheme()).unwrap(),
+            updated
+        );
+    }
 }
@@ -0,0 +1,255 @@
+use crate::{
+    config::{IroConfig, CONFIG_VERSION},
+    config_generator::ConfigGenerator,
+    template_engine::DEFAULT_TEMPLATES,
+    util::find_in_path,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    healthy: bool,
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn push(&mut self, name: impl Into<String>, status: Status, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Run every check and print the report, exiting with status 1 if any check failed.
+/// A missing config or template is only reported, never recreated.
+pub fn run(json: bool) -> Result<()> {
+    let mut report = Report {
+        healthy: true,
+        checks: Vec::new(),
+    };
+
+    let config = check_config(&mut report)?;
+    check_binaries(&mut report, config.as_ref());
+    check_wallpapers(&mut report, config.as_ref());
+    if let Some(config) = config {
+        check_outputs(&mut report, config)?;
+    }
+
+    report.healthy = report.checks.iter().all(|c| c.status != Status::Error);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if !report.healthy {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_report(report: &Report) {
+    println!("🩺 iro doctor");
+    for check in &report.checks {
+        let icon = match check.status {
+            Status::Ok => "✓",
+            Status::Warning => "⚠",
+            Status::Error => "✗",
+        };
+        println!("  {} {}: {}", icon, check.name, check.detail);
+    }
+
+    let errors = report
+        .checks
+        .iter()
+        .filter(|c| c.status == Status::Error)
+        .count();
+    if errors == 0 {
+        println!("✅ No critical problems found");
+    } else {
+        println!("❌ {} critical problem(s) found", errors);
+    }
+}
+
+fn check_binaries(report: &mut Report, config: Option<&IroConfig>) {
+    let backend = config
+        .map(|config| config.wallpaper.backend)
+        .unwrap_or_default();
+
+    // Without these iro can't list monitors or set wallpapers
+    for binary in ["hyprctl", backend.binary()] {
+        match find_in_path(binary) {
+            Some(path) => report.push(binary, Status::Ok, path.display().to_string()),
+            None => report.push(binary, Status::Error, "not found in PATH"),
+        }
+    }
+
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_none() {
+        report.push(
+            "hyprland",
+            Status::Warning,
+            "not running inside a Hyprland session, reloads and wallpapers will fail",
+        );
+    }
+}
+
+/// Parse config.toml without writing the default one, None when it is invalid
+fn check_config(report: &mut Report) -> Result<Option<IroConfig>> {
+    let config_path = IroConfig::config_path()?;
+
+    if !config_path.exists() {
+        report.push(
+            "config",
+            Status::Warning,
+            format!(
+                "{} not found, defaults will be written on the next run",
+                config_path.display()
+            ),
+        );
+        return Ok(Some(IroConfig::default()));
+    }
+
+    let parsed = std::fs::read_to_string(&config_path)
+        .context("Failed to read config file")
+        .and_then(|content| IroConfig::from_toml(&content));
+
+    match parsed {
+        Ok(config) if config.version < CONFIG_VERSION => {
+            report.push(
+                "config",
+                Status::Warning,
+                format!(
+                    "{} is from an older iro (version {}), it will be upgraded on the next run",
+                    config_path.display(),
+                    config.version
+                ),
+            );
+            Ok(Some(config))
+        }
+        Ok(config) => {
+            report.push("config", Status::Ok, config_path.display().to_string());
+            Ok(Some(config))
+        }
+        Err(e) => {
+            report.push(
+                "config",
+                Status::Error,
+                format!("{}: {:#}", config_path.display(), e),
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn check_wallpapers(report: &mut Report, config: Option<&IroConfig>) {
+    let default_config = IroConfig::default();
+    let config = config.unwrap_or(&default_config);
+    let wallpaper_dir = config.wallpaper_path();
+
+    // Only --random, --random-each and the GUI need the wallpaper dir
+    match crate::wallpaper::list_wallpapers(&wallpaper_dir, config.wallpaper.recursive) {
+        Ok(wallpapers) => report.push(
+            "wallpapers",
+            Status::Ok,
+            format!(
+                "{} image(s) in {}",
+                wallpapers.len(),
+                wallpaper_dir.display()
+            ),
+        ),
+        Err(e) => report.push("wallpapers", Status::Warning, e.to_string()),
+    }
+}
+
+fn check_outputs(report: &mut Report, config: IroConfig) -> Result<()> {
+    let config_gen = ConfigGenerator::from_config(config)?;
+
+    for target in config_gen.output_targets() {
+        let Some(path) = target.path else {
+            report.push(target.name, Status::Ok, "not installed, skipped");
+            continue;
+        };
+
+        if let Some(template) = &target.template {
+            if !template.exists() {
+                let is_default = template
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .is_some_and(|f| DEFAULT_TEMPLATES.contains(&f));
+                if is_default {
+                    report.push(
+                        target.name,
+                        Status::Warning,
+                        format!(
+                            "template {} missing, the default will be recreated on the next run",
+                            template.display()
+                        ),
+                    );
+                } else {
+                    report.push(
+                        target.name,
+                        Status::Error,
+                        format!("template mode needs {}", template.display()),
+                    );
+                    continue;
+                }
+            }
+        }
+
+        match writable(&path) {
+            Ok(()) => report.push(
+                target.name,
+                Status::Ok,
+                format!("[{}] {}", target.mode, path.display()),
+            ),
+            Err(e) => report.push(
+                target.name,
+                Status::Error,
+                format!("{}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that iro could write `path` without touching its contents
+fn writable(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .context("not writable")?;
+        return Ok(());
+    }
+
+    // Files iro creates itself only need a writable parent; missing dirs get created
+    let mut dir = path.parent().context("no parent directory")?;
+    while !dir.exists() {
+        dir = dir.parent().context("no existing parent directory")?;
+    }
+    let probe = dir.join(format!(".iro-doctor-{}", std::process::id()));
+    std::fs::write(&probe, "").with_context(|| format!("{} not writable", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
@@ -1,52 +1,122 @@
 use crate::{
+    color_extractor::is_wallpaper_file,
+    color_math::{hex_to_rgb, relative_luminance, to_srgb},
     config::{IroConfig, PaletteStyle},
-    ColorExtractor, ConfigGenerator,
+    state::{AppliedState, GuiState, History},
+    ColorExtractor, ColorScheme, ConfigGenerator,
 };
-use anyhow::{Context, Result};
+use anyhow::Result;
 use eframe::egui;
+use palette::{Hsl, IntoColor};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::mpsc;
 use std::thread;
 
+/// How many alternative palettes the shuffle button cycles through
+const PALETTE_VARIANTS: usize = 5;
+
+/// (wallpaper, theme, style) a set of palette variants was generated for
+type VariantKey = (PathBuf, String, String);
+type VariantResult = (VariantKey, Result<Vec<ColorScheme>, String>);
+type PreviewResult = (VariantKey, Result<ColorScheme, String>);
+/// (index, thumbnail and its dominant color), None when the image couldn't be decoded
+type ThumbnailResult = (usize, Option<(egui::ColorImage, DominantColor)>);
+
+/// Order of the wallpaper grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GridSort {
+    Name,
+    Hue,
+    Brightness,
+}
+
+impl GridSort {
+    const ALL: [GridSort; 3] = [GridSort::Name, GridSort::Hue, GridSort::Brightness];
+
+    fn label(self) -> &'static str {
+        match self {
+            GridSort::Name => "name",
+            GridSort::Hue => "dominant hue",
+            GridSort::Brightness => "brightness",
+        }
+    }
+}
+
+/// Cheap color summary of a thumbnail, for sorting the grid
+#[derive(Debug, Clone, Copy)]
+struct DominantColor {
+    /// Hue in degrees shared by most of the saturated pixels, None for greyscale
+    hue: Option<f32>,
+    /// Mean relative luminance
+    brightness: f32,
+}
+
+/// (path, theme, style, previewed scheme) sent to the apply thread
+type ApplyRequest = (PathBuf, String, String, Option<ColorScheme>);
+
 pub struct WallpaperPickerApp {
     wallpaper_dir: PathBuf,
+    /// Include wallpapers in subdirectories, `wallpaper.recursive`
+    recursive: bool,
     wallpapers: Vec<PathBuf>,
     selected_index: Option<usize>,
-    thumbnails: Arc<Mutex<Vec<Option<egui::ColorImage>>>>,
     texture_cache: Vec<Option<egui::TextureHandle>>,
     status_message: String,
     applying_theme: bool,
-    theme_sender: Option<mpsc::Sender<(PathBuf, String, String)>>, // (path, theme, style)
+    theme_sender: mpsc::Sender<ApplyRequest>,
     theme_receiver: mpsc::Receiver<String>,
-    thumbnail_receiver: mpsc::Receiver<(usize, egui::ColorImage)>,
+    thumbnail_receiver: mpsc::Receiver<ThumbnailResult>,
+    /// Thumbnails received so far, decoded or not, for the progress message
+    thumbnails_loaded: usize,
+    /// Dominant color of each wallpaper, filled in as its thumbnail loads
+    dominant_colors: Vec<Option<DominantColor>>,
+    grid_sort: GridSort,
     search_filter: String,
     grid_columns: usize,
-    loading_started: bool,
+    /// Scroll the selected cell into view on the next frame, after keyboard moves
+    scroll_to_selected: bool,
+    /// Applied wallpaper waiting for the user to confirm its deletion
+    pending_delete: Option<PathBuf>,
     theme_mode: String, // "dark" or "light"
+    gui_state: GuiState,
     palette_style: String,
-    show_style_menu: bool,
+    variant_sender: mpsc::Sender<VariantKey>,
+    variant_receiver: mpsc::Receiver<VariantResult>,
+    palette_variants: Option<(VariantKey, Vec<ColorScheme>)>,
+    variant_index: usize,
+    loading_variants: bool,
+    preview_sender: mpsc::Sender<VariantKey>,
+    preview_receiver: mpsc::Receiver<PreviewResult>,
+    /// Extracted palettes for the side panel, so reselecting a wallpaper is instant
+    previews: HashMap<VariantKey, Result<ColorScheme, String>>,
+    pending_previews: HashSet<VariantKey>,
 }
 
 impl WallpaperPickerApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        // Load config to get wallpaper directory
-        let config = IroConfig::load().unwrap_or_default();
-        let wallpaper_dir = config.wallpaper_path();
-
-        let (theme_sender, theme_receiver_internal) = mpsc::channel::<(PathBuf, String, String)>();
+    pub fn new(_cc: &eframe::CreationContext<'_>, wallpaper_dir: PathBuf) -> Self {
+        let (theme_sender, theme_receiver_internal) = mpsc::channel::<ApplyRequest>();
+        let (variant_sender, variant_requests) = mpsc::channel::<VariantKey>();
+        let (variant_results, variant_receiver) = mpsc::channel::<VariantResult>();
+        let (preview_sender, preview_requests) = mpsc::channel::<VariantKey>();
+        let (preview_results, preview_receiver) = mpsc::channel::<PreviewResult>();
         let (status_sender, theme_receiver) = mpsc::channel::<String>();
-        let (thumbnail_sender, thumbnail_receiver) = mpsc::channel::<(usize, egui::ColorImage)>();
-        let thumbnail_loader = thumbnail_sender.clone();
+        // Replaced with a live channel by reload_wallpapers
+        let (_, thumbnail_receiver) = mpsc::channel::<ThumbnailResult>();
 
         // Load config to get defaults
         let config = IroConfig::load().unwrap_or_default();
-        let default_theme = config.theme.mode.clone();
+        let gui_state = GuiState::load().unwrap_or_default();
+        let default_theme = gui_state
+            .theme
+            .clone()
+            .unwrap_or_else(|| config.theme.mode.clone());
         let default_style = config.palette.style.clone();
 
         // Spawn background thread for applying themes
         thread::spawn(move || {
-            while let Ok((wallpaper_path, theme, style)) = theme_receiver_internal.recv() {
-                let result = apply_theme_with_settings(&wallpaper_path, &theme, &style);
+            while let Ok((wallpaper_path, theme, style, scheme)) = theme_receiver_internal.recv() {
+                let result = apply_theme_with_settings(&wallpaper_path, &theme, &style, scheme);
                 let message = match result {
                     Ok(_) => "✅ Theme applied successfully!".to_string(),
                     Err(e) => format!("❌ Error: {}", e),
@@ -55,79 +125,142 @@ impl WallpaperPickerApp {
             }
         });
 
+        // Spawn background thread for generating palette variants
+        let variant_config = config.clone();
+        thread::spawn(move || {
+            while let Ok(key) = variant_requests.recv() {
+                let (wallpaper_path, theme, style) = &key;
+                let result = ColorExtractor::new_with_config(variant_config.clone())
+                    .with_style(style)
+                    .extract_color_variants(wallpaper_path, theme, PALETTE_VARIANTS)
+                    .map_err(|e| e.to_string());
+                let _ = variant_results.send((key, result));
+            }
+        });
+
+        // Spawn background thread for extracting the selected wallpaper's palette
+        let preview_config = config.clone();
+        thread::spawn(move || {
+            while let Ok(key) = preview_requests.recv() {
+                let (wallpaper_path, theme, style) = &key;
+                let result = ColorExtractor::new_with_config(preview_config.clone())
+                    .with_style(style)
+                    .extract_colors(wallpaper_path, theme)
+                    .map_err(|e| e.to_string());
+                let _ = preview_results.send((key, result));
+            }
+        });
+
         let mut app = Self {
             wallpaper_dir,
+            recursive: config.wallpaper.recursive,
             wallpapers: Vec::new(),
             selected_index: None,
-            thumbnails: Arc::new(Mutex::new(Vec::new())),
             texture_cache: Vec::new(),
             status_message: "Loading wallpapers...".to_string(),
             applying_theme: false,
-            theme_sender: Some(theme_sender),
+            theme_sender,
             theme_receiver,
             thumbnail_receiver,
+            thumbnails_loaded: 0,
+            dominant_colors: Vec::new(),
+            grid_sort: GridSort::Name,
             search_filter: String::new(),
             grid_columns: 4,
-            loading_started: false,
+            scroll_to_selected: false,
+            pending_delete: None,
             theme_mode: default_theme,
+            gui_state,
             palette_style: default_style,
-            show_style_menu: false,
+            variant_sender,
+            variant_receiver,
+            palette_variants: None,
+            variant_index: 0,
+            loading_variants: false,
+            preview_sender,
+            preview_receiver,
+            previews: HashMap::new(),
+            pending_previews: HashSet::new(),
         };
 
-        app.load_wallpapers();
-
-        // Start loading thumbnails immediately with the sender
-        if !app.wallpapers.is_empty() {
-            app.start_loading_thumbnails(thumbnail_loader);
-        }
+        app.reload_wallpapers();
 
         app
     }
 
-    fn load_wallpapers(&mut self) {
-        self.wallpapers.clear();
-        if let Ok(mut thumbnails) = self.thumbnails.lock() {
-            thumbnails.clear();
-        }
-        self.texture_cache.clear();
+    /// Rescan the wallpaper dir and load the thumbnails again. The fresh channel
+    /// drops thumbnails still coming from an earlier scan, whose indices are stale.
+    fn reload_wallpapers(&mut self) {
+        let (thumbnail_sender, thumbnail_receiver) = mpsc::channel::<ThumbnailResult>();
+        self.thumbnail_receiver = thumbnail_receiver;
+        self.load_wallpapers();
 
-        if self.wallpaper_dir.exists() {
-            let extensions = ["jpg", "jpeg", "png", "webp", "gif", "bmp", "tiff"];
+        if !self.wallpapers.is_empty() {
+            self.start_loading_thumbnails(thumbnail_sender);
+        }
+    }
 
-            if let Ok(entries) = std::fs::read_dir(&self.wallpaper_dir) {
-                for entry in entries.flatten() {
-                    if let Some(ext) = entry.path().extension() {
-                        if let Some(ext_str) = ext.to_str() {
-                            if extensions.contains(&ext_str.to_lowercase().as_str()) {
-                                self.wallpapers.push(entry.path());
-                            }
-                        }
+    /// Copy images dropped onto the window into the wallpaper dir, selecting the
+    /// last one added. Other files and names already taken are skipped.
+    fn add_dropped_files(&mut self, paths: Vec<PathBuf>) {
+        let mut messages = Vec::new();
+        let mut added = None;
+
+        for path in paths {
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let target = self.wallpaper_dir.join(name);
+            let name = name.to_string_lossy();
+
+            if !is_wallpaper_file(&path) {
+                messages.push(format!("⊘ Skipped {} (not an image)", name));
+            } else if target.exists() {
+                messages.push(format!("⊘ Skipped {} (already in wallpapers)", name));
+            } else {
+                match std::fs::copy(&path, &target) {
+                    Ok(_) => {
+                        messages.push(format!("✓ Added {}", name));
+                        added = Some(target);
                     }
+                    Err(e) => messages.push(format!("❌ Failed to copy {}: {}", name, e)),
                 }
             }
         }
 
-        self.wallpapers.sort();
-        if let Ok(mut thumbnails) = self.thumbnails.lock() {
-            *thumbnails = vec![None; self.wallpapers.len()];
+        if let Some(target) = added {
+            self.reload_wallpapers();
+            if let Some(index) = self.wallpapers.iter().position(|path| *path == target) {
+                self.selected_index = Some(index);
+                self.scroll_to_selected = true;
+            }
         }
+        self.status_message = messages.join("  ");
+    }
+
+    fn load_wallpapers(&mut self) {
+        self.wallpapers = crate::wallpaper::scan_wallpapers(&self.wallpaper_dir, self.recursive);
         self.texture_cache = vec![None; self.wallpapers.len()];
-        self.loading_started = false;
+        self.dominant_colors = vec![None; self.wallpapers.len()];
+        self.thumbnails_loaded = 0;
 
         if !self.wallpapers.is_empty() {
             self.selected_index = Some(0);
-            self.status_message = format!("{} wallpapers", self.wallpapers.len());
+            self.status_message = self.thumbnail_progress_message();
         } else {
             self.status_message = format!("no wallpapers in {}", self.wallpaper_dir.display());
         }
     }
 
-    fn start_loading_thumbnails(&mut self, sender: mpsc::Sender<(usize, egui::ColorImage)>) {
-        if self.loading_started {
-            return;
-        }
-        self.loading_started = true;
+    /// Path below the wallpaper dir, so wallpapers in subdirectories show where they are
+    fn wallpaper_label(&self, path: &Path) -> String {
+        path.strip_prefix(&self.wallpaper_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
 
+    fn start_loading_thumbnails(&self, sender: mpsc::Sender<ThumbnailResult>) {
         let wallpapers = self.wallpapers.clone();
 
         // Spawn worker threads for parallel loading
@@ -142,20 +275,96 @@ impl WallpaperPickerApp {
 
                 for idx in start..end {
                     if let Some(path) = wallpapers.get(idx) {
-                        if let Ok(img) = image::open(path) {
-                            // Fast thumbnail - use Triangle filter
+                        // Fast thumbnail - use Triangle filter
+                        let thumbnail = image::open(path).ok().map(|img| {
                             let thumb = img.resize(180, 120, image::imageops::FilterType::Triangle);
                             let rgba = thumb.to_rgba8();
                             let size = [rgba.width() as usize, rgba.height() as usize];
-                            let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &rgba);
-                            let _ = sender.send((idx, color_image));
-                        }
+                            (
+                                egui::ColorImage::from_rgba_unmultiplied(size, &rgba),
+                                dominant_color(&rgba),
+                            )
+                        });
+                        let _ = sender.send((idx, thumbnail));
                     }
                 }
             });
         }
     }
 
+    /// Open the folder holding `wallpaper` in the file manager
+    fn open_folder(&mut self, wallpaper: &Path) {
+        let Some(folder) = wallpaper.parent() else {
+            return;
+        };
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(folder);
+        if let Err(e) = crate::wallpaper::spawn_detached(command) {
+            self.status_message = format!("❌ Failed to run xdg-open: {}", e);
+        }
+    }
+
+    /// Put `hex` on the Wayland clipboard
+    fn copy_color(&mut self, hex: &str) {
+        // wl-copy forks to keep serving the clipboard, so this returns right away
+        let copied = std::process::Command::new("wl-copy")
+            .arg(hex)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+        self.status_message = match copied {
+            Ok(status) if status.success() => format!("✓ Copied {}", hex),
+            Ok(status) => format!("❌ wl-copy failed ({})", status),
+            Err(e) => format!("❌ Failed to run wl-copy: {}", e),
+        };
+    }
+
+    /// Delete `wallpaper`, asking first when it is the one currently applied
+    fn request_delete(&mut self, wallpaper: &Path) {
+        if is_applied_wallpaper(wallpaper) {
+            self.pending_delete = Some(wallpaper.to_path_buf());
+        } else {
+            self.delete_wallpaper(wallpaper);
+        }
+    }
+
+    /// Move `wallpaper` to the trash and refresh the grid, keeping the selection
+    /// at the same position
+    fn delete_wallpaper(&mut self, wallpaper: &Path) {
+        let name = wallpaper
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        match crate::wallpaper::move_to_trash(wallpaper) {
+            Ok(target) => {
+                let selected = self.selected_index;
+                self.reload_wallpapers();
+                if let Some(index) = selected {
+                    self.selected_index =
+                        (!self.wallpapers.is_empty()).then(|| index.min(self.wallpapers.len() - 1));
+                }
+                self.status_message = format!("✓ Moved {} to {}", name, target.display());
+            }
+            Err(e) => self.status_message = format!("❌ Error: {:#}", e),
+        }
+    }
+
+    /// "loaded N/M" while thumbnails are coming in, then the wallpaper count
+    fn thumbnail_progress_message(&self) -> String {
+        if self.thumbnails_loaded < self.wallpapers.len() {
+            format!(
+                "loaded {}/{}",
+                self.thumbnails_loaded,
+                self.wallpapers.len()
+            )
+        } else {
+            format!("{} wallpapers", self.wallpapers.len())
+        }
+    }
+
     fn apply_current_theme(&mut self) {
         if let Some(index) = self.selected_index {
             if self.applying_theme || index >= self.wallpapers.len() {
@@ -165,55 +374,237 @@ impl WallpaperPickerApp {
             let wallpaper_path = self.wallpapers[index].clone();
             let theme = self.theme_mode.clone();
             let style = self.palette_style.clone();
+            // Apply exactly the previewed variant, if one was picked
+            let scheme = self.previewed_scheme().cloned();
+
+            if self
+                .theme_sender
+                .send((wallpaper_path, theme, style, scheme))
+                .is_ok()
+            {
+                self.applying_theme = true;
+                self.status_message = "⏳ Applying theme...".to_string();
+            }
+        }
+    }
 
-            if let Some(sender) = &self.theme_sender {
-                if sender.send((wallpaper_path, theme, style)).is_ok() {
-                    self.applying_theme = true;
-                    self.status_message = "⏳ Applying theme...".to_string();
-                }
+    /// Switch between dark and light, remembering the choice for the next session
+    fn toggle_theme(&mut self) {
+        self.theme_mode = if self.theme_mode == "dark" {
+            "light"
+        } else {
+            "dark"
+        }
+        .to_string();
+
+        self.gui_state.theme = Some(self.theme_mode.clone());
+        if let Err(e) = self.gui_state.save() {
+            self.status_message = format!("⚠ Failed to remember theme: {}", e);
+        }
+    }
+
+    fn variant_key(&self) -> Option<VariantKey> {
+        let wallpaper = self.wallpapers.get(self.selected_index?)?;
+        Some((
+            wallpaper.clone(),
+            self.theme_mode.clone(),
+            self.palette_style.clone(),
+        ))
+    }
+
+    /// The palette variant shown for the current wallpaper, theme and style
+    fn previewed_scheme(&self) -> Option<&ColorScheme> {
+        let (key, variants) = self.palette_variants.as_ref()?;
+        if Some(key) != self.variant_key().as_ref() {
+            return None;
+        }
+        variants.get(self.variant_index)
+    }
+
+    /// Ask the preview thread for the selected wallpaper's palette, unless it is
+    /// cached or already on its way
+    fn request_preview(&mut self) {
+        let Some(key) = self.variant_key() else {
+            return;
+        };
+        if self.previews.contains_key(&key) || self.pending_previews.contains(&key) {
+            return;
+        }
+        if self.preview_sender.send(key.clone()).is_ok() {
+            self.pending_previews.insert(key);
+        }
+    }
+
+    /// Step to the next palette variant, generating the set first if needed
+    fn shuffle_palette(&mut self) {
+        let Some(key) = self.variant_key() else {
+            return;
+        };
+
+        match &self.palette_variants {
+            Some((current, variants)) if *current == key => {
+                self.variant_index = (self.variant_index + 1) % variants.len();
+                self.status_message =
+                    format!("palette {}/{}", self.variant_index + 1, variants.len());
+            }
+            _ if !self.loading_variants && self.variant_sender.send(key).is_ok() => {
+                self.loading_variants = true;
+                self.status_message = "⏳ Generating palettes...".to_string();
             }
+            _ => {}
         }
     }
 
+    /// Move the selection `step` cells through the shown wallpapers, wrapping around
+    /// at either end
+    fn move_selection(&mut self, step: isize) {
+        let shown: Vec<usize> = self
+            .filtered_wallpapers()
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        if shown.is_empty() {
+            return;
+        }
+
+        let target = match self
+            .selected_index
+            .and_then(|selected| shown.iter().position(|&index| index == selected))
+        {
+            Some(position) => (position as isize + step).rem_euclid(shown.len() as isize) as usize,
+            // Nothing shown is selected yet, so start at the first cell
+            None => 0,
+        };
+        self.selected_index = Some(shown[target]);
+        self.scroll_to_selected = true;
+    }
+
+    /// Select the first (`last` = false) or last shown wallpaper
+    fn select_edge(&mut self, last: bool) {
+        let shown = self.filtered_wallpapers();
+        let edge = if last { shown.last() } else { shown.first() };
+        if let Some(&(index, _)) = edge {
+            self.selected_index = Some(index);
+            self.scroll_to_selected = true;
+        }
+    }
+
+    /// Wallpapers matching the search, in grid order. Indices into `wallpapers`
+    /// stay put, so sorting never invalidates the texture cache or the selection.
     fn filtered_wallpapers(&self) -> Vec<(usize, &PathBuf)> {
-        self.wallpapers
+        let mut shown: Vec<(usize, &PathBuf)> = self
+            .wallpapers
             .iter()
             .enumerate()
             .filter(|(_, path)| {
                 if self.search_filter.is_empty() {
                     return true;
                 }
-                path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| {
-                        n.to_lowercase()
-                            .contains(&self.search_filter.to_lowercase())
-                    })
-                    .unwrap_or(false)
+                self.wallpaper_label(path)
+                    .to_lowercase()
+                    .contains(&self.search_filter.to_lowercase())
             })
-            .collect()
+            .collect();
+
+        // Wallpapers are scanned in name order; the others sort by the dominant
+        // color, with greyscale and still loading wallpapers last
+        let dominant = |index: usize| self.dominant_colors.get(index).copied().flatten();
+        match self.grid_sort {
+            GridSort::Name => {}
+            GridSort::Hue => shown.sort_by(|(a, _), (b, _)| {
+                let hue = |index| dominant(index).and_then(|d| d.hue).unwrap_or(f32::MAX);
+                hue(*a).total_cmp(&hue(*b))
+            }),
+            GridSort::Brightness => shown.sort_by(|(a, _), (b, _)| {
+                let brightness = |index| dominant(index).map(|d| d.brightness).unwrap_or(f32::MAX);
+                brightness(*a).total_cmp(&brightness(*b))
+            }),
+        }
+
+        shown
     }
 }
 
 impl eframe::App for WallpaperPickerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Add images dropped onto the window to the wallpaper dir
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        if !dropped.is_empty() {
+            self.add_dropped_files(dropped);
+        }
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop_hint"),
+            ));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(180));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "drop images to add them to the wallpapers",
+                egui::FontId::proportional(16.0),
+                egui::Color32::from_rgb(200, 200, 210),
+            );
+        }
+
         // Check for status updates from background thread
         if let Ok(message) = self.theme_receiver.try_recv() {
             self.status_message = message;
             self.applying_theme = false;
         }
 
+        // Receive palette variants, landing on the first alternative to the default
+        if let Ok((key, result)) = self.variant_receiver.try_recv() {
+            self.loading_variants = false;
+            match result {
+                Ok(variants) if !variants.is_empty() => {
+                    self.variant_index = 1.min(variants.len() - 1);
+                    self.status_message =
+                        format!("palette {}/{}", self.variant_index + 1, variants.len());
+                    self.palette_variants = Some((key, variants));
+                }
+                Ok(_) => {}
+                Err(e) => self.status_message = format!("❌ Error: {}", e),
+            }
+        }
+
+        // Receive extracted previews, then make sure the selection has one coming
+        while let Ok((key, result)) = self.preview_receiver.try_recv() {
+            self.pending_previews.remove(&key);
+            self.previews.insert(key, result);
+        }
+        self.request_preview();
+
         // Receive loaded thumbnails
+        let mut received = false;
         while let Ok((idx, color_image)) = self.thumbnail_receiver.try_recv() {
-            if idx < self.texture_cache.len() && self.texture_cache[idx].is_none() {
-                let texture = ctx.load_texture(
-                    format!("thumb_{}", idx),
-                    color_image,
-                    egui::TextureOptions::default(),
-                );
-                self.texture_cache[idx] = Some(texture);
-                ctx.request_repaint();
+            received = true;
+            self.thumbnails_loaded += 1;
+            if let Some((color_image, dominant)) = color_image {
+                if let Some(slot) = self.dominant_colors.get_mut(idx) {
+                    *slot = Some(dominant);
+                }
+                if idx < self.texture_cache.len() && self.texture_cache[idx].is_none() {
+                    let texture = ctx.load_texture(
+                        format!("thumb_{}", idx),
+                        color_image,
+                        egui::TextureOptions::default(),
+                    );
+                    self.texture_cache[idx] = Some(texture);
+                }
             }
+            ctx.request_repaint();
+        }
+        // Keep the progress current, unless another message has replaced it
+        if received && self.status_message.starts_with("loaded ") {
+            self.status_message = self.thumbnail_progress_message();
         }
 
         // Top panel with minimalist design
@@ -257,26 +648,39 @@ impl eframe::App for WallpaperPickerApp {
                         .on_hover_text("Toggle theme mode")
                         .clicked()
                     {
-                        self.theme_mode = if self.theme_mode == "dark" {
-                            "light"
-                        } else {
-                            "dark"
-                        }
-                        .to_string();
+                        self.toggle_theme();
                     }
 
                     ui.add_space(4.0);
 
-                    // Style selector button
-                    let style_display = self
-                        .palette_style
-                        .chars()
-                        .next()
-                        .unwrap()
-                        .to_uppercase()
-                        .to_string();
-                    let style_btn = egui::Button::new(
-                        egui::RichText::new(&style_display)
+                    // Style dropdown; previews and variants are keyed by style, so
+                    // picking another one re-extracts the selected wallpaper
+                    egui::ComboBox::from_id_source("palette_style")
+                        .selected_text(
+                            egui::RichText::new(&self.palette_style)
+                                .size(12.0)
+                                .color(egui::Color32::WHITE),
+                        )
+                        .width(96.0)
+                        .show_ui(ui, |ui| {
+                            for style_name in PaletteStyle::all_styles() {
+                                let style = PaletteStyle::from_name(style_name).unwrap_or_default();
+                                ui.selectable_value(
+                                    &mut self.palette_style,
+                                    style_name.to_string(),
+                                    style_name,
+                                )
+                                .on_hover_text(style.description);
+                            }
+                        })
+                        .response
+                        .on_hover_text("Palette style");
+
+                    ui.add_space(4.0);
+
+                    // Cycle through alternative palettes for the selected wallpaper
+                    let shuffle_btn = egui::Button::new(
+                        egui::RichText::new("🔀")
                             .size(12.0)
                             .color(egui::Color32::WHITE),
                     )
@@ -285,11 +689,14 @@ impl eframe::App for WallpaperPickerApp {
                     .min_size(egui::vec2(32.0, 24.0));
 
                     if ui
-                        .add(style_btn)
-                        .on_hover_text(format!("Style: {}", self.palette_style))
+                        .add_enabled(
+                            !self.loading_variants && self.selected_index.is_some(),
+                            shuffle_btn,
+                        )
+                        .on_hover_text("Shuffle palette")
                         .clicked()
                     {
-                        self.show_style_menu = !self.show_style_menu;
+                        self.shuffle_palette();
                     }
 
                     ui.add_space(8.0);
@@ -297,7 +704,7 @@ impl eframe::App for WallpaperPickerApp {
                     ui.add_space(8.0);
 
                     // Simple search
-                    let _search_response = ui.add(
+                    ui.add(
                         egui::TextEdit::singleline(&mut self.search_filter)
                             .hint_text("search...")
                             .desired_width(160.0)
@@ -315,6 +722,23 @@ impl eframe::App for WallpaperPickerApp {
                         .then(|| self.search_filter.clear());
                     }
 
+                    ui.add_space(8.0);
+
+                    egui::ComboBox::from_id_source("grid_sort")
+                        .selected_text(
+                            egui::RichText::new(self.grid_sort.label())
+                                .size(12.0)
+                                .color(egui::Color32::from_rgb(160, 160, 170)),
+                        )
+                        .width(110.0)
+                        .show_ui(ui, |ui| {
+                            for sort in GridSort::ALL {
+                                ui.selectable_value(&mut self.grid_sort, sort, sort.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Sort wallpapers");
+
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         // Minimalist apply button
                         let apply_text = if self.applying_theme {
@@ -370,58 +794,33 @@ impl eframe::App for WallpaperPickerApp {
                 });
             });
 
-        // Style menu popup
-        if self.show_style_menu {
-            egui::Window::new("palette style")
+        // Confirm deleting the applied wallpaper
+        if let Some(wallpaper) = self.pending_delete.clone() {
+            egui::Window::new("delete wallpaper")
                 .collapsible(false)
                 .resizable(false)
-                .title_bar(false)
-                .fixed_pos(egui::pos2(100.0, 50.0))
-                .frame(
-                    egui::Frame::window(&ctx.style())
-                        .fill(egui::Color32::from_rgb(25, 25, 35))
-                        .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(60, 60, 80)))
-                        .rounding(6.0)
-                        .inner_margin(egui::Margin::same(8.0)),
-                )
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
                 .show(ctx, |ui| {
-                    ui.set_min_width(200.0);
-
-                    for style_name in PaletteStyle::all_styles() {
-                        let style = PaletteStyle::from_name(style_name);
-                        let is_current = self.palette_style == style_name;
-
-                        let color = if is_current {
-                            egui::Color32::from_rgb(100, 120, 140)
-                        } else {
-                            egui::Color32::from_rgb(35, 35, 45)
-                        };
-
-                        let btn =
-                            egui::Button::new(egui::RichText::new(style_name).size(12.0).color(
-                                if is_current {
-                                    egui::Color32::WHITE
-                                } else {
-                                    egui::Color32::from_rgb(180, 180, 190)
-                                },
-                            ))
-                            .fill(color)
-                            .rounding(4.0)
-                            .min_size(egui::vec2(180.0, 24.0));
-
-                        if ui.add(btn).on_hover_text(style.description).clicked() {
-                            self.palette_style = style_name.to_string();
-                            self.show_style_menu = false;
+                    ui.label(format!(
+                        "{} is the applied wallpaper. Move it to the trash anyway?",
+                        self.wallpaper_label(&wallpaper)
+                    ));
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            self.pending_delete = None;
+                            self.delete_wallpaper(&wallpaper);
                         }
-                    }
-
-                    ui.add_space(4.0);
-                    if ui.small_button("✕ close").clicked() {
-                        self.show_style_menu = false;
-                    }
+                        if ui.button("Cancel").clicked() {
+                            self.pending_delete = None;
+                        }
+                    });
                 });
         }
 
+        // Swatch clicked in the status bar or the palette panel, copied once they're drawn
+        let mut copied = None;
+
         // Bottom status bar
         egui::TopBottomPanel::bottom("bottom_panel")
             .frame(
@@ -437,22 +836,83 @@ impl eframe::App for WallpaperPickerApp {
                             .color(egui::Color32::from_rgb(140, 140, 150)),
                     );
 
+                    // Swatches of the palette variant that apply will use
+                    if let Some(scheme) = self.previewed_scheme() {
+                        ui.add_space(12.0);
+                        let swatches = [&scheme.background, &scheme.accent, &scheme.secondary]
+                            .into_iter()
+                            .chain(scheme.colors.iter().skip(1).take(6));
+                        for hex in swatches {
+                            if paint_swatch(ui, hex, egui::vec2(14.0, 14.0)) {
+                                copied = Some(hex.clone());
+                            }
+                        }
+                    }
+
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if let Some(index) = self.selected_index {
                             if let Some(path) = self.wallpapers.get(index) {
-                                if let Some(filename) = path.file_name() {
-                                    ui.label(
-                                        egui::RichText::new(filename.to_string_lossy())
-                                            .size(11.0)
-                                            .color(egui::Color32::from_rgb(120, 120, 130)),
-                                    );
-                                }
+                                ui.label(
+                                    egui::RichText::new(self.wallpaper_label(path))
+                                        .size(11.0)
+                                        .color(egui::Color32::from_rgb(120, 120, 130)),
+                                );
                             }
                         }
                     });
                 });
             });
 
+        // Side panel with the palette apply would produce
+        egui::SidePanel::right("palette_panel")
+            .resizable(false)
+            .exact_width(220.0)
+            .frame(
+                egui::Frame::none()
+                    .fill(egui::Color32::from_rgb(15, 15, 20))
+                    .inner_margin(egui::Margin::same(16.0)),
+            )
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new("palette")
+                        .size(13.0)
+                        .color(egui::Color32::from_rgb(160, 160, 170)),
+                );
+                ui.add_space(10.0);
+
+                let key = self.variant_key();
+                // A shuffled variant is what apply uses, so it wins over the extraction
+                let preview = match self.previewed_scheme() {
+                    Some(scheme) => Some(Ok(scheme)),
+                    None => key
+                        .as_ref()
+                        .and_then(|key| self.previews.get(key))
+                        .map(|result| result.as_ref()),
+                };
+
+                let hint = |ui: &mut egui::Ui, text: &str| {
+                    ui.label(
+                        egui::RichText::new(text)
+                            .size(11.0)
+                            .color(egui::Color32::from_rgb(100, 100, 110)),
+                    );
+                };
+                match preview {
+                    Some(Ok(scheme)) => {
+                        if let Some(hex) = show_palette(ui, scheme) {
+                            copied = Some(hex);
+                        }
+                    }
+                    Some(Err(e)) => hint(ui, &format!("❌ {}", e)),
+                    None if key.is_some() => hint(ui, "extracting..."),
+                    None => hint(ui, "select a wallpaper"),
+                }
+            });
+
+        if let Some(hex) = copied {
+            self.copy_color(&hex);
+        }
+
         // Central panel with grid
         egui::CentralPanel::default()
             .frame(egui::Frame::none().fill(egui::Color32::from_rgb(18, 18, 24)))
@@ -502,7 +962,7 @@ impl eframe::App for WallpaperPickerApp {
                             .show(ui, |ui| {
                                 let mut col = 0;
 
-                                for (real_index, _path) in filtered.iter() {
+                                for (real_index, path) in filtered.iter() {
                                     let is_selected = self.selected_index == Some(*real_index);
 
                                     let border_color = if is_selected {
@@ -531,11 +991,27 @@ impl eframe::App for WallpaperPickerApp {
                                             self.selected_index = Some(*real_index);
                                         }
 
+                                        if is_selected && self.scroll_to_selected {
+                                            response.scroll_to_me(None);
+                                            self.scroll_to_selected = false;
+                                        }
+
                                         if response.double_clicked() {
                                             self.selected_index = Some(*real_index);
                                             self.apply_current_theme();
                                         }
 
+                                        response.context_menu(|ui| {
+                                            if ui.button("Open folder").clicked() {
+                                                self.open_folder(path);
+                                                ui.close_menu();
+                                            }
+                                            if ui.button("Delete").clicked() {
+                                                self.request_delete(path);
+                                                ui.close_menu();
+                                            }
+                                        });
+
                                         // Draw thumbnail
                                         if let Some(Some(texture)) =
                                             self.texture_cache.get(*real_index)
@@ -591,7 +1067,8 @@ impl eframe::App for WallpaperPickerApp {
                     });
             });
 
-        // Keyboard shortcuts
+        // Keyboard shortcuts; grid navigation only while not typing in the search box
+        let typing = ctx.wants_keyboard_input();
         ctx.input(|i| {
             if i.key_pressed(egui::Key::Enter) {
                 self.apply_current_theme();
@@ -599,130 +1076,222 @@ impl eframe::App for WallpaperPickerApp {
             if i.key_pressed(egui::Key::Escape) {
                 self.search_filter.clear();
             }
+            if typing {
+                return;
+            }
+
+            let columns = self.grid_columns as isize;
+            let pressed = |keys: [egui::Key; 2]| keys.iter().any(|&key| i.key_pressed(key));
+            if pressed([egui::Key::ArrowLeft, egui::Key::H]) {
+                self.move_selection(-1);
+            }
+            if pressed([egui::Key::ArrowRight, egui::Key::L]) {
+                self.move_selection(1);
+            }
+            if pressed([egui::Key::ArrowUp, egui::Key::K]) {
+                self.move_selection(-columns);
+            }
+            if pressed([egui::Key::ArrowDown, egui::Key::J]) {
+                self.move_selection(columns);
+            }
+            if i.key_pressed(egui::Key::Home) {
+                self.select_edge(false);
+            }
+            if i.key_pressed(egui::Key::End) {
+                self.select_edge(true);
+            }
         });
+        if self.scroll_to_selected {
+            ctx.request_repaint();
+        }
 
-        // Request repaint for animations and ongoing thumbnail loading
-        if self.applying_theme || self.texture_cache.iter().any(|t| t.is_none()) {
+        // Request repaint for animations, ongoing thumbnail loading and previews
+        if self.applying_theme
+            || !self.pending_previews.is_empty()
+            || self.texture_cache.iter().any(|t| t.is_none())
+        {
             ctx.request_repaint();
         }
     }
 }
 
-fn apply_theme_with_settings(wallpaper_path: &Path, theme: &str, style: &str) -> Result<()> {
-    // Load and update config with selected style
-    let mut config = IroConfig::load().unwrap_or_default();
-    config.palette.style = style.to_string();
-    config.save()?;
-
-    // Extract colors
-    let extractor = ColorExtractor::new()?;
-    let color_scheme = extractor.extract_colors(&wallpaper_path.to_path_buf(), theme)?;
-
-    // Generate configs
-    let config_gen = ConfigGenerator::new()?;
-    config_gen.generate_configs(&color_scheme)?;
-
-    // Reload applications
-    reload_applications()?;
-
-    // Set wallpaper
-    set_wallpaper_background(wallpaper_path)?;
-
-    Ok(())
+/// A rounded color square with its hex value on hover, true when it was clicked
+fn paint_swatch(ui: &mut egui::Ui, hex: &str, size: egui::Vec2) -> bool {
+    let Ok(rgb) = hex_to_rgb(hex) else {
+        return false;
+    };
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+    ui.painter()
+        .rect_filled(rect, 3.0, egui::Color32::from_rgb(rgb[0], rgb[1], rgb[2]));
+    response
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+        .on_hover_text(format!("{} (click to copy)", hex))
+        .clicked()
 }
 
-fn reload_applications() -> Result<()> {
-    std::process::Command::new("hyprctl")
-        .args(["reload"])
-        .output()
-        .context("Failed to reload hyprland")?;
+/// The named colors with their hex values, then the 16 terminal colors as
+/// normal and bright rows. Returns the hex of a swatch that was clicked.
+fn show_palette(ui: &mut egui::Ui, scheme: &ColorScheme) -> Option<String> {
+    let mut clicked = None;
+    let named = [
+        ("background", &scheme.background),
+        ("foreground", &scheme.foreground),
+        ("accent", &scheme.accent),
+        ("secondary", &scheme.secondary),
+        ("surface", &scheme.surface),
+        ("error", &scheme.error),
+    ];
+    for (name, hex) in named {
+        ui.horizontal(|ui| {
+            if paint_swatch(ui, hex, egui::vec2(28.0, 18.0)) {
+                clicked = Some(hex.clone());
+            }
+            ui.label(
+                egui::RichText::new(format!("{} {}", name, hex))
+                    .size(11.0)
+                    .color(egui::Color32::from_rgb(140, 140, 150)),
+            );
+        });
+    }
 
-    Ok(())
+    ui.add_space(10.0);
+    for row in scheme.colors.chunks(8) {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for hex in row {
+                if paint_swatch(ui, hex, egui::vec2(20.0, 20.0)) {
+                    clicked = Some(hex.clone());
+                }
+            }
+        });
+    }
+    clicked
 }
 
-fn set_wallpaper_background(wallpaper_path: &Path) -> Result<()> {
-    let wallpaper_str = wallpaper_path.to_str().context("Invalid wallpaper path")?;
+/// Whether `wallpaper` is shown by the last applied theme
+fn is_applied_wallpaper(wallpaper: &Path) -> bool {
+    let Ok(Some(state)) = AppliedState::load() else {
+        return false;
+    };
+    let wallpaper = std::fs::canonicalize(wallpaper).unwrap_or_else(|_| wallpaper.to_path_buf());
+    state
+        .wallpapers
+        .iter()
+        .chain(state.mapped.values())
+        .any(|applied| *applied == wallpaper)
+}
 
-    let output = std::process::Command::new("hyprctl")
-        .args(["monitors", "-j"])
-        .output()
-        .context("Failed to get monitors")?;
+/// Dominant hue from a saturation-weighted 10° hue histogram, ignoring pixels too
+/// grey to have a meaningful hue, plus the mean luminance
+fn dominant_color(thumb: &image::RgbaImage) -> DominantColor {
+    let mut buckets = [0.0f32; 36];
+    let mut luminance = 0.0;
 
-    let monitors_json: serde_json::Value =
-        serde_json::from_slice(&output.stdout).context("Failed to parse monitors JSON")?;
+    for pixel in thumb.pixels() {
+        let rgb = image::Rgb([pixel[0], pixel[1], pixel[2]]);
+        luminance += relative_luminance(&rgb);
 
-    let mut monitors = Vec::new();
-    if let Some(array) = monitors_json.as_array() {
-        for monitor in array {
-            if let Some(name) = monitor.get("name").and_then(|n| n.as_str()) {
-                monitors.push(name.to_string());
-            }
+        let hsl: Hsl = to_srgb(&rgb).into_color();
+        if hsl.saturation > 0.15 {
+            let bucket = (hsl.hue.into_positive_degrees() / 10.0) as usize % buckets.len();
+            buckets[bucket] += hsl.saturation;
         }
     }
 
-    if monitors.is_empty() {
-        return Err(anyhow::anyhow!("No monitors found"));
+    let hue = buckets
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .filter(|(_, &weight)| weight > 0.0)
+        .map(|(bucket, _)| bucket as f32 * 10.0 + 5.0);
+    let pixels = (thumb.width() * thumb.height()).max(1) as f32;
+
+    DominantColor {
+        hue,
+        brightness: luminance / pixels,
     }
+}
 
-    let config_dir = dirs::config_dir().context("Failed to get config directory")?;
-    let hyprpaper_conf = config_dir.join("hypr/hyprpaper.conf");
-
-    let mut config_content = format!(
-        "# Generated by iro\nsplash = false\nipc = on\n\npreload = {}\n\n",
-        wallpaper_str
-    );
+fn apply_theme_with_settings(
+    wallpaper_path: &Path,
+    theme: &str,
+    style: &str,
+    previewed: Option<ColorScheme>,
+) -> Result<()> {
+    // Load and update config with selected style
+    let mut config = IroConfig::load().unwrap_or_default();
+    config.palette.style = style.to_string();
+    config.save()?;
 
-    for monitor in &monitors {
-        config_content.push_str(&format!("wallpaper = {},{}\n", monitor, wallpaper_str));
-    }
+    // Extract colors, unless a shuffled palette was picked
+    let color_scheme = match previewed {
+        Some(scheme) => scheme,
+        None => {
+            let extractor = ColorExtractor::new_with_config(config);
+            let scheme = extractor.extract_colors(&wallpaper_path.to_path_buf(), theme)?;
+            for note in extractor.take_notes() {
+                eprintln!("{}", note);
+            }
+            scheme
+        }
+    };
 
-    std::fs::write(&hyprpaper_conf, &config_content)?;
+    // Generate configs
+    let config_gen = ConfigGenerator::new()?;
+    config_gen.generate_configs(&color_scheme)?;
 
-    let hyprpaper_running = std::process::Command::new("pgrep")
-        .args(["-x", "hyprpaper"])
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false);
+    // Reload applications
+    crate::reload::reload_applications(None)?;
 
-    if !hyprpaper_running {
-        std::process::Command::new("hyprpaper")
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to start hyprpaper")?;
-        std::thread::sleep(std::time::Duration::from_millis(400));
+    // Set wallpaper
+    set_wallpaper_background(wallpaper_path, &color_scheme.background)?;
+
+    // Remember what we applied for --apply-last. The theme is already applied, so a
+    // failure here is only worth a warning.
+    let applied = AppliedState::new(&[wallpaper_path.to_path_buf()], None, color_scheme)
+        .with_theme(theme, style);
+    if let Err(e) = applied.save() {
+        println!("  ⚠ Failed to save state: {}", e);
     }
-
-    let _ = std::process::Command::new("hyprctl")
-        .args(["hyprpaper", "preload", wallpaper_str])
-        .output();
-
-    for monitor in &monitors {
-        let _ = std::process::Command::new("hyprctl")
-            .args([
-                "hyprpaper",
-                "wallpaper",
-                &format!("{},{}", monitor, wallpaper_str),
-            ])
-            .output();
+    let recorded = History::load().and_then(|mut history| {
+        history.push(wallpaper_path);
+        history.save()
+    });
+    if let Err(e) = recorded {
+        println!("  ⚠ Failed to save history: {}", e);
     }
 
     Ok(())
 }
 
-pub fn launch_gui() -> Result<()> {
+fn set_wallpaper_background(wallpaper_path: &Path, background: &str) -> Result<()> {
+    let assignments: Vec<(String, PathBuf)> = crate::wallpaper::get_all_monitors()?
+        .into_iter()
+        .map(|monitor| (monitor, wallpaper_path.to_path_buf()))
+        .collect();
+
+    if assignments.is_empty() {
+        return Err(anyhow::anyhow!("No monitors found"));
+    }
+
+    let config = IroConfig::load().unwrap_or_default();
+    crate::wallpaper::set_wallpaper_with_backend(&config.wallpaper, &assignments, background)
+}
+
+/// Open the picker on the images in `wallpaper_dir`
+pub fn launch_gui(wallpaper_dir: PathBuf) -> Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1400.0, 900.0])
-            .with_title("iro - Wallpaper Theme Picker"),
+            .with_title("iro - Wallpaper Theme Picker")
+            .with_drag_and_drop(true),
         ..Default::default()
     };
 
     eframe::run_native(
         "iro",
         options,
-        Box::new(|cc| Ok(Box::new(WallpaperPickerApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(WallpaperPickerApp::new(cc, wallpaper_dir)))),
     )
     .map_err(|e| anyhow::anyhow!("GUI error: {}", e))?;
 
@@ -0,0 +1,103 @@
+//! Wallpaper based color schemes for Hyprland.
+//!
+//! Besides the `iro` binary, the palette extraction can be used as a library:
+//!
+//! ```no_run
+//! use iro::ColorExtractor;
+//! use std::path::PathBuf;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let extractor = ColorExtractor::new()?;
+//! let scheme = extractor.extract_colors(&PathBuf::from("wallpaper.jpg"), "dark")?;
+//! println!("{} on {}, accent {}", scheme.foreground, scheme.background, scheme.accent);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `ColorExtractor::new` reads `~/.config/iro/config.toml` like the binary does, so
+//! palette style, harmony and contrast settings apply to library callers too. Use
+//! `ColorExtractor::new_with_config` to pass settings of your own instead.
+//!
+//! Nothing is printed: warnings about the config and what an "auto" theme or style
+//! resolved to are kept for `ColorExtractor::take_notes`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+mod cache;
+pub mod color_extractor;
+pub mod color_math;
+pub mod config;
+pub mod config_generator;
+mod diff;
+pub mod palette;
+pub mod template_engine;
+mod util;
+
+// The rest backs the `iro` binary and isn't part of the library API
+#[doc(hidden)]
+pub mod doctor;
+#[cfg(feature = "gui")]
+#[doc(hidden)]
+pub mod gui;
+#[doc(hidden)]
+pub mod reload;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod term;
+#[doc(hidden)]
+pub mod themes;
+#[doc(hidden)]
+pub mod wallpaper;
+#[doc(hidden)]
+pub mod watch;
+
+pub use color_extractor::{ColorExtractor, ExtractionNote};
+pub use config::{IroConfig, PaletteStyle};
+pub use config_generator::ConfigGenerator;
+pub use palette::PaletteGenerator;
+pub use template_engine::TemplateEngine;
+
+/// A generated color scheme, in `#rrggbb` hex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorScheme {
+    pub background: String,
+    pub foreground: String,
+    pub colors: Vec<String>, // 16 terminal colors
+    pub accent: String,
+    pub secondary: String,
+    pub surface: String,
+    pub error: String,
+}
+
+impl ColorScheme {
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize color scheme")
+    }
+
+    /// Parse a scheme written by `to_toml`, checking that every color is valid hex
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut scheme: ColorScheme =
+            toml::from_str(content).context("Failed to parse color scheme")?;
+
+        if scheme.colors.len() != 16 {
+            anyhow::bail!("Expected 16 terminal colors, found {}", scheme.colors.len());
+        }
+        // Hand-edited themes may use #rgb or #rrggbbaa, templates expect #rrggbb
+        for color in [
+            &mut scheme.background,
+            &mut scheme.foreground,
+            &mut scheme.accent,
+            &mut scheme.secondary,
+            &mut scheme.surface,
+            &mut scheme.error,
+        ]
+        .into_iter()
+        .chain(&mut scheme.colors)
+        {
+            *color = color_math::rgb_to_hex(&color_math::hex_to_rgb(color)?);
+        }
+        Ok(scheme)
+    }
+}
@@ -1,28 +1,15 @@
 use anyhow::{Context, Result};
 use clap::{Arg, Command};
-use std::path::PathBuf;
-
-mod color_extractor;
-mod config;
-mod config_generator;
-mod gui;
-mod palette;
-mod template_engine;
-
-use color_extractor::ColorExtractor;
-use config_generator::ConfigGenerator;
-use template_engine::TemplateEngine;
-
-#[derive(Debug, Clone)]
-pub struct ColorScheme {
-    pub background: String,
-    pub foreground: String,
-    pub colors: Vec<String>, // 16 terminal colors
-    pub accent: String,
-    pub secondary: String,
-    pub surface: String,
-    pub error: String,
-}
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "gui")]
+use iro::gui;
+use iro::{
+    color_math, config, doctor, reload::reload_applications, state, term, themes, wallpaper, watch,
+    ColorExtractor, ColorScheme, ConfigGenerator, PaletteStyle, TemplateEngine,
+};
 
 fn main() -> Result<()> {
     let matches = Command::new("iro")
@@ -46,6 +33,39 @@ fn main() -> Result<()> {
                 .help("Select different random wallpaper for each monitor")
                 .action(clap::ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("blur")
+                .long("blur")
+                .value_name("SIGMA")
+                .help("Blur the wallpaper before setting it (colors still come from the original)")
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("dim")
+                .long("dim")
+                .value_name("AMOUNT")
+                .help("Darken the wallpaper before setting it, from 0.0 (unchanged) to 1.0 (black)")
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("unload-unused")
+                .long("unload-unused")
+                .help("Free the wallpapers hyprpaper still holds but no monitor shows (wallpaper.unload_unused)")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("wallpaper-dir")
+                .long("wallpaper-dir")
+                .value_name("DIR")
+                .help("Pick --random, --random-each and GUI wallpapers from DIR instead of wallpaper_dir")
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Make --random and --random-each pick the same wallpapers every run")
+                .value_parser(clap::value_parser!(u64))
+        )
         .arg(
             Arg::new("primary")
                 .short('p')
@@ -59,15 +79,49 @@ fn main() -> Result<()> {
                 .short('m')
                 .long("monitors")
                 .value_name("MONITOR1,MONITOR2,...")
-                .help("Comma-separated list of monitors (e.g., eDP-1,DP-3). If not specified, uses all monitors")
+                .help("Comma-separated list of monitors (e.g., eDP-1,DP-3), or \"all\". Unplugged ones are skipped. If not specified, uses all monitors")
         )
         .arg(
             Arg::new("theme")
                 .short('t')
                 .long("theme")
                 .value_name("THEME")
-                .help("Color scheme theme (dark, light)")
-                .default_value("dark")
+                .help("Color scheme theme (dark, light, auto), overriding theme.mode")
+                .value_parser(["dark", "light", "auto"])
+        )
+        .arg(
+            Arg::new("algorithm")
+                .long("algorithm")
+                .value_name("ALGORITHM")
+                .help("Color extraction algorithm, overriding palette.algorithm")
+                .value_parser(["histogram", "kmeans", "octree"])
+        )
+        .arg(
+            Arg::new("contrast")
+                .long("contrast")
+                .value_name("FACTOR")
+                .help("Multiply the palette style's contrast, e.g. 1.2 for punchier colors")
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("saturation")
+                .long("saturation")
+                .value_name("FACTOR")
+                .help("Multiply the palette style's saturation")
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("brightness")
+                .long("brightness")
+                .value_name("FACTOR")
+                .help("Multiply the palette style's brightness")
+                .value_parser(clap::value_parser!(f32))
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Extract from the image even if the palette cache has this wallpaper")
+                .action(clap::ArgAction::SetTrue)
         )
         .arg(
             Arg::new("reload")
@@ -76,6 +130,12 @@ fn main() -> Result<()> {
                 .help("Reload applications after generating configs")
                 .action(clap::ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("live")
+                .long("live")
+                .help("Also recolor open terminals (kitty, foot, alacritty, ...) with escape sequences; implies --reload")
+                .action(clap::ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("gui")
                 .short('g')
@@ -83,41 +143,382 @@ fn main() -> Result<()> {
                 .help("Open GUI mode to select wallpaper")
                 .action(clap::ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .value_name("PATH")
+                .num_args(0..=1)
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Keep running and re-theme whenever the wallpaper file, or an image in the directory, changes (default: the wallpaper directory)")
+                .conflicts_with_all(["gui", "apply-last", "previous", "random", "random-each", "wallpapers", "color", "load-theme", "dry-run", "only", "output-dir", "profile", "output", "preview", "extract-only", "save-theme", "pywal"])
+        )
         .arg(
             Arg::new("init")
                 .long("init")
                 .help("Initialize iro: setup directories, copy templates, and integrate with shell")
                 .action(clap::ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("systemd")
+                .long("systemd")
+                .value_name("INTERVAL")
+                .num_args(0..=1)
+                .default_missing_value("30min")
+                .requires("init")
+                .help("With --init, also write systemd user units that restore the theme on login and run --random every INTERVAL (default: 30min)")
+        )
+        .arg(
+            Arg::new("output-dir")
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Write generated configs under DIR instead of ~/.config (skips wallpaper and reload)")
+                .value_parser(clap::value_parser!(PathBuf))
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("HEX")
+                .help("Build the theme around a single accent color instead of a wallpaper")
+                .conflicts_with_all(["gui", "apply-last"])
+        )
+        .arg(
+            Arg::new("save-theme")
+                .long("save-theme")
+                .value_name("NAME")
+                .help("Also save the generated scheme as ~/.config/iro/themes/NAME.toml")
+                .conflicts_with_all(["gui", "apply-last"])
+        )
+        .arg(
+            Arg::new("load-theme")
+                .long("load-theme")
+                .value_name("NAME")
+                .help("Apply a theme saved with --save-theme, without a wallpaper")
+                .conflicts_with_all(["gui", "apply-last", "random", "random-each", "previous", "wallpapers", "color"])
+        )
+        .arg(
+            Arg::new("list-themes")
+                .long("list-themes")
+                .help("List the themes saved with --save-theme")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Write colors to iro-NAME-colors.* include files only, leaving app configs and wallpaper alone")
+                .conflicts_with_all(["gui", "apply-last"])
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print a diff of every config iro would change, without writing anything or touching the wallpaper")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["gui", "apply-last"])
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .value_name("OUTPUTS")
+                .help("Only write these outputs for this run, e.g. --only waybar,kitty")
+                .value_delimiter(',')
+                .conflicts_with_all(["gui", "apply-last", "preview"])
+        )
+        .arg(
+            Arg::new("pywal")
+                .long("pywal")
+                .value_name("PATH")
+                .help("Also write a pywal-compatible colors.json (default ~/.cache/wal/colors.json, or --pywal=PATH)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .num_args(0..=1)
+                .require_equals(true)
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("apply the theme, or print it as json / hex (one color per line) without touching configs or wallpaper")
+                .value_parser(["apply", "json", "hex"])
+                .default_value("apply")
+                .conflicts_with_all(["gui", "apply-last", "profile", "output-dir"])
+        )
+        .arg(
+            Arg::new("preview")
+                .long("preview")
+                .help("Only show the extracted palette, without touching configs or wallpaper")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["gui", "apply-last", "output", "dry-run", "profile", "output-dir"])
+        )
+        .arg(
+            Arg::new("extract-only")
+                .long("extract-only")
+                .value_name("N")
+                .help("Print the wallpaper's N most common colors as extracted, without building a scheme or touching anything")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .conflicts_with_all(["gui", "apply-last", "color", "load-theme", "save-theme", "preview", "dry-run", "profile", "output-dir"])
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .help("Print plain hex values instead of color swatches")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("apply-last")
+                .long("apply-last")
+                .visible_alias("restore")
+                .help("Reapply the most recently applied theme and wallpaper(s) without extraction")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("previous")
+                .long("previous")
+                .help("Go back to the wallpaper applied before the current one, re-extracting its colors")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["gui", "apply-last", "random", "random-each", "wallpapers", "color"])
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .help("List recently applied wallpapers")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("restore-backups")
+                .long("restore-backups")
+                .help("Put back the app configs as they were before iro first edited them")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("clear-backups")
+        )
+        .arg(
+            Arg::new("clear-backups")
+                .long("clear-backups")
+                .help("Delete the backups iro made of app configs before editing them")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("list-styles")
+                .long("list-styles")
+                .help("List the palette styles with a short description")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("describe-style")
+                .long("describe-style")
+                .value_name("STYLE")
+                .help("Show the saturation, brightness, contrast and warmth of a palette style")
+        )
+        .subcommand(
+            Command::new("monitors")
+                .about("List detected monitors for use with --monitors")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print as JSON")
+                        .action(clap::ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("paths")
+                .about("Show where iro reads and writes its files")
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the environment and config, exiting non-zero on critical problems")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the report as JSON")
+                        .action(clap::ArgAction::SetTrue)
+                )
+        )
         .get_matches();
 
-    let theme = matches.get_one::<String>("theme").unwrap();
-    let should_reload = matches.get_flag("reload");
+    if matches.get_flag("no-color") {
+        term::disable_color();
+    }
+
+    // Commands that only look at things shouldn't create or upgrade config.toml
+    let reporting = matches!(
+        matches.subcommand_name(),
+        Some("paths" | "doctor" | "monitors")
+    );
+    if reporting
+        || [
+            "dry-run",
+            "preview",
+            "history",
+            "list-themes",
+            "list-styles",
+        ]
+        .iter()
+        .any(|flag| matches.get_flag(flag))
+        || matches.contains_id("extract-only")
+        || matches.contains_id("describe-style")
+    {
+        config::read_only();
+    }
+
+    // Upgrade an old config.toml before anything else loads it, which would do so quietly
+    if !config::is_read_only() {
+        if let Ok((_, Some((old_version, backup_path)))) = config::IroConfig::load_upgrading() {
+            // stderr, so --output json/hex stays clean
+            eprintln!(
+                "💾 Upgraded config from version {} to {} (old file kept as {})",
+                old_version,
+                config::CONFIG_VERSION,
+                backup_path.display()
+            );
+        }
+    }
+
+    match matches.subcommand() {
+        Some(("paths", _)) => return show_paths(),
+        Some(("doctor", sub)) => return doctor::run(sub.get_flag("json")),
+        Some(("monitors", sub)) => return list_monitors(sub.get_flag("json")),
+        _ => {}
+    }
+
+    if matches.get_flag("history") {
+        return show_history();
+    }
+
+    if matches.get_flag("restore-backups") {
+        return restore_backups();
+    }
+    if matches.get_flag("clear-backups") {
+        return clear_backups();
+    }
+
+    if matches.get_flag("list-themes") {
+        return list_themes();
+    }
+
+    if matches.get_flag("list-styles") {
+        list_styles();
+        return Ok(());
+    }
+    if let Some(style) = matches.get_one::<String>("describe-style") {
+        return describe_style(style);
+    }
+
+    let theme = match matches.get_one::<String>("theme") {
+        Some(theme) => theme.clone(),
+        None => config::IroConfig::load()?.theme.mode,
+    };
+    let theme = theme.as_str();
+    let live = matches.get_flag("live");
+    let unload_unused = matches.get_flag("unload-unused");
+    let should_reload = matches.get_flag("reload") || live;
     let gui_mode = matches.get_flag("gui");
     let init_mode = matches.get_flag("init");
+    let apply_last_mode = matches.get_flag("apply-last");
     let random_mode = matches.get_flag("random");
     let random_each_mode = matches.get_flag("random-each");
+    let previous_mode = matches.get_flag("previous");
     let primary_index = matches.get_one::<usize>("primary").copied().unwrap_or(0);
     let monitors = matches.get_one::<String>("monitors");
+    let output_dir = matches.get_one::<PathBuf>("output-dir");
+    let solid_color = matches.get_one::<String>("color");
+    let saved_theme = matches.get_one::<String>("load-theme");
+    let profile = matches.get_one::<String>("profile");
+    // json and hex keep stdout to the scheme alone, for piping into other tools
+    let output_format = matches.get_one::<String>("output").unwrap();
+    let apply = output_format == "apply";
+    let dry_run = matches.get_flag("dry-run");
+    let effects = wallpaper::Effects {
+        blur: matches.get_one::<f32>("blur").copied(),
+        dim: matches.get_one::<f32>("dim").copied(),
+    };
+    if effects.blur.is_some_and(|sigma| sigma < 0.0) {
+        anyhow::bail!("--blur must not be negative");
+    }
+    if effects.dim.is_some_and(|dim| !(0.0..=1.0).contains(&dim)) {
+        anyhow::bail!("--dim must be between 0.0 and 1.0");
+    }
 
     // Handle init mode
     if init_mode {
-        return run_init();
+        return run_init(matches.get_one::<String>("systemd").map(String::as_str));
+    }
+
+    // Handle apply-last mode
+    if apply_last_mode {
+        return apply_last(live, unload_unused);
+    }
+
+    // --wallpaper-dir wins over wallpaper_dir in the config
+    let iro_config = config::IroConfig::load()?;
+    let recursive = iro_config.wallpaper.recursive;
+    let wallpaper_dir = match matches.get_one::<String>("wallpaper-dir") {
+        Some(dir) => {
+            let dir = config::expand_path(dir);
+            if !dir.is_dir() {
+                anyhow::bail!("Wallpaper directory not found: {}", dir.display());
+            }
+            dir
+        }
+        None => iro_config.wallpaper_path(),
+    };
+
+    if matches.contains_id("watch") {
+        let target = matches
+            .get_one::<PathBuf>("watch")
+            .cloned()
+            .unwrap_or_else(|| wallpaper_dir.clone());
+        return watch::run(&target, recursive, theme, live);
     }
 
     // Handle GUI mode
     if gui_mode {
-        open_wallpaper_picker()?;
+        open_wallpaper_picker(wallpaper_dir.clone())?;
+    }
+
+    // Wallpapers given as `monitor:path`
+    let mut mapped_wallpapers: HashMap<String, PathBuf> = HashMap::new();
+
+    // --seed swaps the thread rng for a reproducible one
+    let mut rng: Box<dyn rand::RngCore> = match matches.get_one::<u64>("seed") {
+        Some(&seed) => Box::new(<rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(
+            seed,
+        )),
+        None => Box::new(rand::thread_rng()),
+    };
+
+    // An unplugged monitor should fail before anything is extracted or written
+    if apply && !dry_run && profile.is_none() && output_dir.is_none() {
+        check_monitors(monitors)?;
     }
 
     // Get wallpapers for each monitor
     let (wallpaper_paths, primary_wallpaper) = if random_mode {
         // --random: same random wallpaper on all screens
-        let wp = select_random_wallpaper()?;
-        (vec![wp.clone()], wp)
+        let wp = select_random_wallpaper(&wallpaper_dir, recursive, &mut rng)?;
+        (vec![wp.clone()], Some(wp))
+    } else if previous_mode {
+        let history = state::History::load()?;
+        let wp = history
+            .previous()
+            .context("No earlier wallpaper in history")?
+            .to_path_buf();
+        if !wp.exists() {
+            anyhow::bail!("{} no longer exists", wp.display());
+        }
+        // stderr, so --output json/hex still tells which wallpaper was picked
+        eprintln!(
+            "⏪ Going back to: {}",
+            wp.file_name().unwrap_or_default().to_string_lossy()
+        );
+        (vec![wp.clone()], Some(wp))
     } else if random_each_mode {
         // --random-each: different random wallpaper per screen
-        get_random_wallpapers_per_monitor(monitors, primary_index)?
+        let (paths, primary) = get_random_wallpapers_per_monitor(
+            &wallpaper_dir,
+            recursive,
+            monitors,
+            primary_index,
+            &mut rng,
+        )?;
+        (paths, Some(primary))
     } else {
         // Manual mode: specify wallpapers, use --primary for theme
         let wallpapers: Vec<&str> = matches
@@ -125,212 +526,484 @@ fn main() -> Result<()> {
             .map(|vals| vals.map(|s| s.as_str()).collect())
             .unwrap_or_default();
 
-        if wallpapers.is_empty() {
+        // --color and --load-theme theme without a wallpaper, so paths are optional there
+        if wallpapers.is_empty() && solid_color.is_none() && saved_theme.is_none() {
             anyhow::bail!(
-                "Error: Wallpaper path(s) required (or use --gui/--random/--random-each)"
+                "Error: Wallpaper path(s) required (or use --gui/--random/--random-each/--color/--load-theme)"
             );
         }
 
-        let paths: Vec<PathBuf> = wallpapers.iter().map(PathBuf::from).collect();
-        let primary = paths.get(primary_index).unwrap_or(&paths[0]).clone();
+        let mut paths = Vec::new();
+        for wallpaper in &wallpapers {
+            match parse_monitor_mapping(wallpaper) {
+                Some((monitor, path)) => {
+                    mapped_wallpapers.insert(monitor, path);
+                }
+                None => paths.push(PathBuf::from(wallpaper)),
+            }
+        }
+
+        // --primary counts every wallpaper argument, mapped or not, in the order given
+        let primary = wallpapers
+            .get(primary_index)
+            .or(wallpapers.first())
+            .map(|wallpaper| match parse_monitor_mapping(wallpaper) {
+                Some((_, path)) => path,
+                None => PathBuf::from(wallpaper),
+            });
         (paths, primary)
     };
 
-    println!(
-        "🎨 iro - Generating color scheme from: {}",
-        primary_wallpaper.display()
+    let mut extractor = ColorExtractor::new()?;
+    if let Some(algorithm) = matches.get_one::<String>("algorithm") {
+        extractor = extractor.with_algorithm(algorithm.parse()?);
+    }
+    if matches.get_flag("no-cache") {
+        extractor = extractor.without_cache();
+    }
+    extractor = extractor.with_adjustments(
+        matches.get_one::<f32>("contrast").copied(),
+        matches.get_one::<f32>("saturation").copied(),
+        matches.get_one::<f32>("brightness").copied(),
     );
+    print_notes(&extractor);
+    if let Some(&count) = matches.get_one::<u64>("extract-only") {
+        let wallpaper = primary_wallpaper
+            .as_ref()
+            .context("--extract-only needs a wallpaper")?;
+        let colors = extractor.extract_dominant_colors(wallpaper, count as usize)?;
+        return print_dominant_colors(&colors, output_format);
+    }
 
-    // Extract colors from primary wallpaper
-    let extractor = ColorExtractor::new()?;
-    let color_scheme = extractor.extract_colors(&primary_wallpaper, theme)?;
+    let color_scheme = match (saved_theme, solid_color, &primary_wallpaper) {
+        (Some(name), _, _) => {
+            if apply {
+                println!("🎨 iro - Loading saved theme: {}", name);
+            }
+            themes::load(name)?
+        }
+        (None, Some(color), _) => {
+            if apply {
+                println!("🎨 iro - Generating color scheme from: {}", color);
+            }
+            extractor.scheme_from_color(color, theme)?
+        }
+        (None, None, Some(primary_wallpaper)) => {
+            if apply {
+                println!(
+                    "🎨 iro - Generating color scheme from: {}",
+                    primary_wallpaper.display()
+                );
+            }
+
+            // Extract colors from primary wallpaper
+            extractor.extract_colors(primary_wallpaper, theme)?
+        }
+        (None, None, None) => unreachable!("wallpaper selection always yields a primary"),
+    };
+    print_notes(&extractor);
+
+    if let Some(name) = matches.get_one::<String>("save-theme") {
+        let path = themes::save(name, &color_scheme)?;
+        // stderr, so --output json/hex stays just the scheme
+        eprintln!("💾 Saved theme '{}' to {}", name, path.display());
+    }
+
+    match output_format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&color_scheme)?);
+            return Ok(());
+        }
+        "hex" => {
+            for color in &color_scheme.colors {
+                println!("{}", color);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
     println!("✨ Extracted color scheme:");
     print_color_scheme(&color_scheme);
 
+    if matches.get_flag("preview") {
+        return Ok(());
+    }
+
     // Generate configurations
-    let config_gen = ConfigGenerator::new()?;
+    let mut config_gen = ConfigGenerator::new()?;
+    if let Some(dir) = output_dir {
+        config_gen = config_gen.with_output_dir(dir.clone());
+    }
+    if let Some(profile) = profile {
+        config_gen = config_gen.with_profile(profile)?;
+    }
+    if matches.contains_id("pywal") {
+        config_gen = config_gen.with_pywal(matches.get_one::<PathBuf>("pywal").cloned())?;
+    }
+    if dry_run {
+        config_gen = config_gen.with_dry_run();
+    }
+    if let Some(only) = matches.get_many::<String>("only") {
+        config_gen = config_gen.with_only(&only.cloned().collect::<Vec<_>>())?;
+    }
     config_gen.generate_configs(&color_scheme)?;
 
-    // Set wallpapers
-    set_wallpapers(&wallpaper_paths, monitors)?;
+    if dry_run {
+        println!("✅ Dry run, nothing was written");
+        return Ok(());
+    }
+
+    // A profile is a named palette on the side, not the live theme
+    if let Some(profile) = profile {
+        println!("✅ Profile '{}' written", profile);
+        return Ok(());
+    }
+
+    // Sandboxed runs leave the live session alone
+    if let Some(dir) = output_dir {
+        println!("✅ Configs written to {}", dir.display());
+        return Ok(());
+    }
+
+    // Set wallpapers (a bare --color run keeps the current ones)
+    if !wallpaper_paths.is_empty() || !mapped_wallpapers.is_empty() {
+        set_wallpapers(
+            &wallpaper_paths,
+            &mapped_wallpapers,
+            monitors,
+            effects,
+            &color_scheme.background,
+            unload_unused,
+        )?;
+    }
+
+    // Remember what we applied for --apply-last
+    let applied = state::AppliedState::new(&wallpaper_paths, monitors.cloned(), color_scheme)
+        .with_mapped(&mapped_wallpapers)
+        .with_theme(theme, extractor.style())
+        .with_effects(effects);
+    if let Err(e) = applied.save() {
+        println!("  ⚠ Failed to save state: {}", e);
+    }
+
+    // --previous steps back through the history instead of adding to it
+    if let Some(primary) = &primary_wallpaper {
+        let recorded = state::History::load().and_then(|mut history| {
+            if previous_mode {
+                history.rewind();
+            } else {
+                history.push(primary);
+            }
+            history.save()
+        });
+        if let Err(e) = recorded {
+            println!("  ⚠ Failed to save history: {}", e);
+        }
+    }
 
     // Reload applications
     if gui_mode || should_reload || random_mode || random_each_mode {
         println!("🔄 Reloading applications...");
-        reload_applications()?;
+        reload_applications(live.then_some(&applied.scheme))?;
     }
 
     println!("✅ Color scheme applied successfully!");
     Ok(())
 }
 
+/// Show what the extractor noticed, on stderr so --output json/hex stays clean
+fn print_notes(extractor: &ColorExtractor) {
+    for note in extractor.take_notes() {
+        eprintln!("{}", note);
+    }
+}
+
+/// --extract-only output: hex values one per line, or with each color's share of the
+/// image as json or next to a swatch
+fn print_dominant_colors(colors: &[(String, f32)], format: &str) -> Result<()> {
+    match format {
+        "json" => {
+            let colors: Vec<serde_json::Value> = colors
+                .iter()
+                .map(|(color, share)| serde_json::json!({ "color": color, "share": share }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&colors)?);
+        }
+        "hex" => {
+            for (color, _) in colors {
+                println!("{}", color);
+            }
+        }
+        _ => {
+            for (color, share) in colors {
+                println!("{}{}  {:>5.1}%", term::swatch(color), color, share * 100.0);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn print_color_scheme(scheme: &ColorScheme) {
-    println!("  Background: {}", scheme.background);
-    println!("  Foreground: {}", scheme.foreground);
-    println!("  Accent: {}", scheme.accent);
-    println!("  Secondary: {}", scheme.secondary);
-    println!("  Colors: {:?}", &scheme.colors[..8]);
+    println!(
+        "  Background: {}{}",
+        term::swatch(&scheme.background),
+        scheme.background
+    );
+    println!(
+        "  Foreground: {}{}",
+        term::swatch(&scheme.foreground),
+        scheme.foreground
+    );
+    if let (Ok(bg), Ok(fg)) = (
+        color_math::hex_to_rgb(&scheme.background),
+        color_math::hex_to_rgb(&scheme.foreground),
+    ) {
+        println!("  Contrast: {:.1}:1", color_math::contrast_ratio(&fg, &bg));
+    }
+    println!(
+        "  Accent: {}{}",
+        term::swatch(&scheme.accent),
+        scheme.accent
+    );
+    println!(
+        "  Secondary: {}{}",
+        term::swatch(&scheme.secondary),
+        scheme.secondary
+    );
+    if term::should_colorize() {
+        // Normal colors over their bright variants, so each pair lines up
+        for (label, row) in ["Normal", "Bright"].iter().zip(scheme.colors.chunks(8)) {
+            let swatches: String = row.iter().map(|c| term::swatch(c)).collect();
+            println!("  {}: {}", label, swatches);
+        }
+    } else {
+        println!("  Colors: {:?}", &scheme.colors[..8]);
+    }
 }
 
-fn reload_applications() -> Result<()> {
-    std::process::Command::new("hyprctl")
-        .args(["reload"])
-        .output()
-        .context("Failed to reload hyprland")?;
+fn apply_last(live: bool, unload_unused: bool) -> Result<()> {
+    let applied = state::AppliedState::load()?
+        .context("No previous theme found. Apply a wallpaper with iro first")?;
+
+    println!("♻️  iro - Reapplying last theme");
+    if let (Some(theme), Some(style)) = (&applied.theme, &applied.style) {
+        println!("  Theme: {}, style: {}", theme, style);
+    }
+    print_color_scheme(&applied.scheme);
+
+    let config_gen = ConfigGenerator::new()?;
+    config_gen.generate_configs(&applied.scheme)?;
+
+    if let Some(missing) = applied
+        .wallpapers
+        .iter()
+        .chain(applied.mapped.values())
+        .find(|p| !p.exists())
+    {
+        println!(
+            "  ⚠ Skipping wallpaper, {} no longer exists",
+            missing.display()
+        );
+    } else if !applied.wallpapers.is_empty() || !applied.mapped.is_empty() {
+        check_monitors(applied.monitors.as_ref())?;
+        set_wallpapers(
+            &applied.wallpapers,
+            &applied.mapped,
+            applied.monitors.as_ref(),
+            applied.effects,
+            &applied.scheme.background,
+            unload_unused,
+        )?;
+    }
+
+    println!("🔄 Reloading applications...");
+    reload_applications(live.then_some(&applied.scheme))?;
 
-    println!("  ✓ Reloaded Hyprland");
+    println!("✅ Last theme reapplied!");
     Ok(())
 }
 
-fn open_wallpaper_picker() -> Result<PathBuf> {
+#[cfg(feature = "gui")]
+fn open_wallpaper_picker(wallpaper_dir: PathBuf) -> Result<PathBuf> {
     // Launch the Rust GUI
     println!("🎨 Launching iro GUI viewer...");
-    gui::launch_gui()?;
+    gui::launch_gui(wallpaper_dir)?;
 
     // GUI handles everything, so we can exit
     std::process::exit(0);
 }
 
-fn set_wallpapers(wallpaper_paths: &[PathBuf], monitors: Option<&String>) -> Result<()> {
-    println!("🖼️  Setting wallpaper(s)...");
-
-    // Get list of monitors
-    let monitor_list = if let Some(mon_str) = monitors {
-        mon_str.split(',').map(|s| s.trim().to_string()).collect()
-    } else {
-        get_all_monitors()?
-    };
+#[cfg(not(feature = "gui"))]
+fn open_wallpaper_picker(_wallpaper_dir: PathBuf) -> Result<PathBuf> {
+    anyhow::bail!("This iro was built without the gui feature")
+}
 
-    if monitor_list.is_empty() {
-        return Err(anyhow::anyhow!("No monitors found"));
+/// Monitors named by --monitors in the order given, or every connected one for "all"
+/// or no list
+fn requested_monitors(monitors: Option<&String>) -> Result<Vec<String>> {
+    match monitors.map(|list| list.trim()) {
+        Some(list) if list != "all" => Ok(list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect()),
+        _ => wallpaper::get_all_monitors(),
     }
+}
 
-    let config_dir = dirs::config_dir().context("Failed to get config directory")?;
-    let hyprpaper_conf = config_dir.join("hypr/hyprpaper.conf");
-
-    // Build hyprpaper config content
-    let mut config_content = String::from("# Generated by iro\nsplash = false\nipc = on\n\n");
-
-    // Preload all unique wallpapers
-    for wallpaper_path in wallpaper_paths {
-        let wallpaper_str = wallpaper_path.to_str().context("Invalid wallpaper path")?;
-        config_content.push_str(&format!("preload = {}\n", wallpaper_str));
-    }
-    config_content.push('\n');
-
-    // Assign wallpapers to monitors
-    for (i, monitor) in monitor_list.iter().enumerate() {
-        let wallpaper_idx = i.min(wallpaper_paths.len() - 1);
-        let wallpaper_path = &wallpaper_paths[wallpaper_idx];
-        let wallpaper_str = wallpaper_path.to_str().unwrap();
-        config_content.push_str(&format!("wallpaper = {},{}\n", monitor, wallpaper_str));
-    }
-
-    // Write config (for persistence on restart)
-    std::fs::write(&hyprpaper_conf, &config_content).context("Failed to write hyprpaper.conf")?;
-
-    // Check if hyprpaper is running
-    let hyprpaper_running = std::process::Command::new("pgrep")
-        .args(["-x", "hyprpaper"])
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false);
-
-    if !hyprpaper_running {
-        // Start hyprpaper if not running
-        std::process::Command::new("hyprpaper")
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to start hyprpaper")?;
-        std::thread::sleep(std::time::Duration::from_millis(400));
-    }
-
-    // Set wallpapers via IPC (fast, no restart needed)
-    for (i, monitor) in monitor_list.iter().enumerate() {
-        let wallpaper_idx = i.min(wallpaper_paths.len() - 1);
-        let wallpaper_path = &wallpaper_paths[wallpaper_idx];
-        let wallpaper_str = wallpaper_path.to_str().unwrap();
-
-        let _ = std::process::Command::new("hyprctl")
-            .args([
-                "hyprpaper",
-                "wallpaper",
-                &format!("{},{}", monitor, wallpaper_str),
-            ])
-            .output();
+/// Warn about --monitors names that aren't connected, failing when none of them are.
+/// Outside Hyprland there is no monitor list to check against.
+fn check_monitors(monitors: Option<&String>) -> Result<()> {
+    let Some(list) = monitors.filter(|list| list.trim() != "all") else {
+        return Ok(());
+    };
+    let Ok(connected) = wallpaper::get_all_monitors() else {
+        return Ok(());
+    };
 
-        println!(
-            "  ✓ Set {} on {}",
-            wallpaper_path.file_name().unwrap().to_string_lossy(),
-            monitor
+    let requested = requested_monitors(Some(list))?;
+    let (found, missing): (Vec<&String>, Vec<&String>) = requested
+        .iter()
+        .partition(|monitor| connected.contains(monitor));
+    if found.is_empty() {
+        anyhow::bail!(
+            "None of the monitors {} are connected (connected: {})",
+            list,
+            connected.join(", ")
         );
     }
-
+    for monitor in missing {
+        println!("  ⚠ Skipping monitor {}, it isn't connected", monitor);
+    }
     Ok(())
 }
 
-fn get_all_monitors() -> Result<Vec<String>> {
-    let output = std::process::Command::new("hyprctl")
-        .args(["monitors", "-j"])
-        .output()
-        .context("Failed to get monitors")?;
+/// Split a `monitor:path` wallpaper argument. Anything that exists as a file, or
+/// whose part before the colon looks like a path, is a plain wallpaper. The shell
+/// doesn't expand `~` after the colon, so that happens here.
+fn parse_monitor_mapping(arg: &str) -> Option<(String, PathBuf)> {
+    let (monitor, path) = arg.split_once(':')?;
+    if monitor.is_empty() || path.is_empty() || monitor.contains('/') || Path::new(arg).exists() {
+        return None;
+    }
+    Some((monitor.to_string(), config::expand_path(path)))
+}
 
-    let monitors_json: serde_json::Value =
-        serde_json::from_slice(&output.stdout).context("Failed to parse monitors JSON")?;
+/// Set `mapped` wallpapers on their monitors, then spread `wallpaper_paths` over the
+/// remaining monitors in order, repeating the last one. Monitors that get neither
+/// keep their current wallpaper. With `effects`, processed copies are shown instead.
+/// `background` fills the bars in contain mode. `unload_unused` turns on
+/// wallpaper.unload_unused for this run.
+fn set_wallpapers(
+    wallpaper_paths: &[PathBuf],
+    mapped: &HashMap<String, PathBuf>,
+    monitors: Option<&String>,
+    effects: wallpaper::Effects,
+    background: &str,
+    unload_unused: bool,
+) -> Result<()> {
+    if wallpaper_paths.is_empty() && mapped.is_empty() {
+        anyhow::bail!("No wallpapers to set");
+    }
 
-    Ok(monitors_json
-        .as_array()
-        .map(|array| {
-            array
-                .iter()
-                .filter_map(|monitor| monitor.get("name")?.as_str().map(String::from))
-                .collect()
-        })
-        .unwrap_or_default())
-}
+    println!("🖼️  Setting wallpaper(s)...");
+
+    let mut assignments: Vec<(String, PathBuf)> = mapped
+        .iter()
+        .map(|(monitor, path)| (monitor.clone(), path.clone()))
+        .collect();
+    assignments.sort();
 
-fn get_wallpapers_list() -> Result<Vec<PathBuf>> {
-    let config = config::IroConfig::load().unwrap_or_default();
-    let wallpaper_dir = config.wallpaper_path();
+    if !wallpaper_paths.is_empty() {
+        let monitor_list = requested_monitors(monitors)?;
+        if monitor_list.is_empty() {
+            return Err(anyhow::anyhow!("No monitors found"));
+        }
 
-    if !wallpaper_dir.exists() {
-        anyhow::bail!(
-            "Wallpaper directory not found: {}. Run 'iro --init' first or set wallpaper_dir in ~/.config/iro/config.toml",
-            wallpaper_dir.display()
+        assignments.extend(
+            monitor_list
+                .into_iter()
+                .filter(|monitor| !mapped.contains_key(monitor))
+                .enumerate()
+                .filter_map(|(i, monitor)| {
+                    let path = wallpaper_paths.get(i).or(wallpaper_paths.last())?;
+                    Some((monitor, path.clone()))
+                }),
         );
     }
 
-    let wallpapers: Vec<PathBuf> = std::fs::read_dir(&wallpaper_dir)?
-        .filter_map(Result::ok)
-        .map(|e| e.path())
-        .filter(|path| {
-            path.is_file()
-                && matches!(
-                    path.extension().and_then(|s| s.to_str()),
-                    Some("jpg" | "jpeg" | "png" | "webp")
-                )
-        })
-        .collect();
+    // Unplugged monitors are dropped here rather than failing a hyprctl call each.
+    // Outside Hyprland there is no monitor list to check against.
+    if let Ok(connected) = wallpaper::get_all_monitors() {
+        assignments.retain(|(monitor, path)| {
+            let known = connected.contains(monitor);
+            if !known && mapped.get(monitor) == Some(path) {
+                println!(
+                    "  ⚠ Skipping {}, monitor {} isn't connected",
+                    path.display(),
+                    monitor
+                );
+            }
+            known
+        });
+        if assignments.is_empty() {
+            anyhow::bail!(
+                "None of the requested monitors are connected (connected: {})",
+                connected.join(", ")
+            );
+        }
+    }
 
-    if wallpapers.is_empty() {
-        anyhow::bail!("No wallpapers found in {}", wallpaper_dir.display());
+    if !effects.is_none() {
+        // The same wallpaper on several monitors is only processed once
+        let mut processed: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for (_, path) in &mut assignments {
+            if !processed.contains_key(path) {
+                processed.insert(path.clone(), effects.apply(path)?);
+            }
+            *path = processed[path].clone();
+        }
     }
 
-    Ok(wallpapers)
+    let mut config = config::IroConfig::load()?;
+    config.wallpaper.unload_unused |= unload_unused;
+    wallpaper::set_wallpaper_with_backend(&config.wallpaper, &assignments, background)
+}
+
+fn list_monitors(json: bool) -> Result<()> {
+    let monitors = wallpaper::get_monitor_info()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&monitors)?);
+        return Ok(());
+    }
+
+    println!("{:<12} {:>11} {:>6}", "NAME", "RESOLUTION", "SCALE");
+    for monitor in &monitors {
+        println!(
+            "{:<12} {:>11} {:>6.2}",
+            monitor.name,
+            format!("{}x{}", monitor.width, monitor.height),
+            monitor.scale
+        );
+    }
+    Ok(())
 }
 
-fn select_random_wallpaper() -> Result<PathBuf> {
+fn select_random_wallpaper(
+    wallpaper_dir: &Path,
+    recursive: bool,
+    rng: &mut dyn rand::RngCore,
+) -> Result<PathBuf> {
     use rand::seq::SliceRandom;
-    use rand::thread_rng;
 
-    let wallpapers = get_wallpapers_list()?;
-    let mut rng = thread_rng();
-    let selected = wallpapers.choose(&mut rng).unwrap().clone();
+    let wallpapers = wallpaper::list_wallpapers(wallpaper_dir, recursive)?;
+    let selected = wallpapers.choose(rng).unwrap().clone();
 
-    println!(
+    // stderr, so --output json/hex still tells which wallpaper was picked
+    eprintln!(
         "🎲 Selected random wallpaper: {}",
         selected.file_name().unwrap().to_string_lossy()
     );
@@ -339,22 +1012,17 @@ fn select_random_wallpaper() -> Result<PathBuf> {
 }
 
 fn get_random_wallpapers_per_monitor(
+    wallpaper_dir: &Path,
+    recursive: bool,
     monitors: Option<&String>,
     primary_index: usize,
+    rng: &mut dyn rand::RngCore,
 ) -> Result<(Vec<PathBuf>, PathBuf)> {
     use rand::seq::SliceRandom;
-    use rand::thread_rng;
 
-    let wallpapers = get_wallpapers_list()?;
-    let mut rng = thread_rng();
-
-    // Get monitor list
-    let monitor_list = if let Some(mon_str) = monitors {
-        mon_str.split(',').map(|s| s.trim().to_string()).collect()
-    } else {
-        get_all_monitors()?
-    };
+    let wallpapers = wallpaper::list_wallpapers(wallpaper_dir, recursive)?;
 
+    let monitor_list = requested_monitors(monitors)?;
     if monitor_list.is_empty() {
         anyhow::bail!("No monitors found");
     }
@@ -363,10 +1031,11 @@ fn get_random_wallpapers_per_monitor(
     let mut selected_wallpapers = Vec::with_capacity(monitor_list.len());
     let mut available_wallpapers = wallpapers;
 
-    println!("🎲 Selecting random wallpaper for each monitor:");
+    // stderr, so --output json/hex still tells which wallpapers were picked
+    eprintln!("🎲 Selecting random wallpaper for each monitor:");
     for monitor in &monitor_list {
-        if let Some(selected) = available_wallpapers.choose(&mut rng).cloned() {
-            println!(
+        if let Some(selected) = available_wallpapers.choose(rng).cloned() {
+            eprintln!(
                 "  {} → {}",
                 monitor,
                 selected.file_name().unwrap().to_string_lossy()
@@ -390,7 +1059,216 @@ fn get_random_wallpapers_per_monitor(
     Ok((selected_wallpapers, primary_wallpaper))
 }
 
-fn run_init() -> Result<()> {
+/// Print the most recently applied wallpapers, newest first
+fn show_history() -> Result<()> {
+    let history = state::History::load()?;
+    if history.entries().is_empty() {
+        println!("No wallpapers applied yet");
+        return Ok(());
+    }
+
+    println!("📜 Recent wallpapers");
+    for (i, path) in history.entries().iter().rev().take(20).enumerate() {
+        let current = if i == 0 { " (current)" } else { "" };
+        println!("  {:>2}. {}{}", i + 1, path.display(), current);
+    }
+    Ok(())
+}
+
+fn list_themes() -> Result<()> {
+    let names = themes::list()?;
+    if names.is_empty() {
+        println!("No saved themes. Save one with --save-theme NAME");
+        return Ok(());
+    }
+
+    println!("🎨 Saved themes");
+    for name in &names {
+        match themes::load(name) {
+            Ok(scheme) => {
+                let swatches: String = [&scheme.background, &scheme.accent, &scheme.secondary]
+                    .into_iter()
+                    .map(|c| term::swatch(c))
+                    .collect();
+                println!("  {}{}", swatches, name);
+            }
+            Err(e) => println!("  ⚠ {}: {:#}", name, e),
+        }
+    }
+    Ok(())
+}
+
+fn show_paths() -> Result<()> {
+    let config = config::IroConfig::load()?;
+    let config_gen = ConfigGenerator::new()?;
+    let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+
+    println!("📂 iro paths");
+    println!(
+        "  Config:     {}",
+        config::IroConfig::config_path()?.display()
+    );
+    println!("  Templates:  {}", config_gen.templates_dir().display());
+    println!(
+        "  State:      {}",
+        state::AppliedState::state_path()?.display()
+    );
+    println!("  Themes:     {}", themes::themes_dir()?.display());
+    println!("  GUI state:  {}", state::GuiState::state_path()?.display());
+    println!("  Wallpapers: {}", config.wallpaper_path().display());
+    println!("  Trash:      {}", wallpaper::trash_dir()?.display());
+    println!("  Palettes:   {}", ColorExtractor::cache_dir()?.display());
+    println!(
+        "  Hyprpaper:  {}",
+        config_dir.join("hypr/hyprpaper.conf").display()
+    );
+
+    println!("📝 Outputs:");
+    for target in config_gen.output_targets() {
+        let Some(path) = target.path else {
+            println!("  ⊘ {} (not installed)", target.name);
+            continue;
+        };
+
+        println!("  {} [{}]: {}", target.name, target.mode, path.display());
+        if let Some(include) = target.include {
+            println!("    colors: {}", include.display());
+        }
+        let backup = ConfigGenerator::backup_path(&path);
+        if backup.exists() {
+            println!("    backup: {}", backup.display());
+        }
+        if let Some(newest) = ConfigGenerator::timestamped_backups(&path).pop() {
+            println!("    newest backup: {}", newest.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy the backups back over the configs they were made from: the original where
+/// there is one, else the newest timestamped backup. The backups are kept, so running
+/// iro again won't back up its own edits in their place.
+fn restore_backups() -> Result<()> {
+    let backups = ConfigGenerator::new()?.backups();
+    if backups.is_empty() {
+        println!("No backups to restore");
+        return Ok(());
+    }
+
+    println!("💾 Backups:");
+    for (config, backup) in &backups {
+        println!("  {} → {}", backup.display(), config.display());
+    }
+    if !confirm(&format!("Restore {} config(s)?", backups.len()))? {
+        println!("  ⊘ Skipped");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (config, backup) in &backups {
+        match std::fs::copy(backup, config) {
+            Ok(_) => println!("  ✓ Restored {}", config.display()),
+            Err(e) => {
+                println!("  ⚠ Failed to restore {}: {}", config.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} config(s) could not be restored", failed);
+    }
+
+    println!("✅ Configs restored; run iro again to re-theme them");
+    Ok(())
+}
+
+/// Delete every backup, so the next run backs up the configs as they are now
+fn clear_backups() -> Result<()> {
+    let backups = ConfigGenerator::new()?.backup_files();
+    if backups.is_empty() {
+        println!("No backups to clear");
+        return Ok(());
+    }
+
+    println!("💾 Backups:");
+    for backup in &backups {
+        println!("  {}", backup.display());
+    }
+    if !confirm(&format!("Delete {} backup(s)?", backups.len()))? {
+        println!("  ⊘ Skipped");
+        return Ok(());
+    }
+
+    for backup in &backups {
+        std::fs::remove_file(backup)
+            .with_context(|| format!("Failed to delete {}", backup.display()))?;
+        println!("  ✓ Deleted {}", backup.display());
+    }
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal; anything but "y" or "yes" is a no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout()
+        .flush()
+        .context("Failed to write prompt")?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn list_styles() {
+    println!("🎨 Palette styles");
+    for name in PaletteStyle::all_styles() {
+        let style = PaletteStyle::from_name(name).unwrap_or_default();
+        println!("  {:<12} {}", name, style.description);
+    }
+}
+
+fn describe_style(name: &str) -> Result<()> {
+    let style = PaletteStyle::from_name(name).with_context(|| {
+        format!(
+            "Unknown palette style '{}', available: {}",
+            name,
+            PaletteStyle::all_styles().join(", ")
+        )
+    })?;
+
+    println!("🎨 {} - {}", name, style.description);
+    println!(
+        "  Saturation:  {:.2} dark, {:.2} light",
+        style.dark_saturation, style.light_saturation
+    );
+    println!(
+        "  Brightness:  {:.2} dark, {:.2} light",
+        style.dark_brightness, style.light_brightness
+    );
+    if let Some(floor) = style.saturation_floor {
+        println!("  Sat floor:   {:.2}", floor);
+    }
+    if let Some(ceiling) = style.saturation_ceiling {
+        println!("  Sat ceiling: {:.2}", ceiling);
+    }
+    if let Some((low, high)) = style.lightness_range {
+        println!("  Lightness:   {:.2}-{:.2}", low, high);
+    }
+    println!("  Contrast:    {:.2}", style.contrast);
+    println!("  Warmth:      {:+.2}", style.warmth_shift);
+    println!("  Background:  {:.2} tint", style.bg_tint_strength);
+    println!("  Harmony:     {:?}", style.color_harmony);
+    if let Some(hues) = style.target_hues {
+        let hues: Vec<String> = hues.iter().map(|hue| format!("{}°", hue)).collect();
+        println!("  Target hues: {}", hues.join(", "));
+    }
+    Ok(())
+}
+
+fn run_init(systemd_interval: Option<&str>) -> Result<()> {
     println!("🚀 Initializing iro...\n");
 
     let home = dirs::home_dir().context("Failed to get home directory")?;
@@ -418,27 +1296,70 @@ fn run_init() -> Result<()> {
 
     // 3. Shell integration
     println!("\n🐚 Shell integration...");
-    let shell_rc = if std::env::var("SHELL").unwrap_or_default().contains("zsh") {
-        home.join(".zshrc")
-    } else {
-        home.join(".bashrc")
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let shell_name = Path::new(&shell)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+    // (rc file, line that sources the colors, colors file it sources)
+    let (shell_rc, source_line, colors_file) = match shell_name {
+        "fish" => (
+            config_dir.join("fish").join("config.fish"),
+            "test -f ~/.config/iro/colors.fish; and source ~/.config/iro/colors.fish",
+            "iro/colors.fish",
+        ),
+        "nu" => (
+            config_dir.join("nushell").join("env.nu"),
+            "source ~/.config/iro/colors.nu",
+            "iro/colors.nu",
+        ),
+        "zsh" => (
+            home.join(".zshrc"),
+            "[ -f ~/.config/iro/colors.sh ] && source ~/.config/iro/colors.sh",
+            "iro/colors.sh",
+        ),
+        _ => (
+            home.join(".bashrc"),
+            "[ -f ~/.config/iro/colors.sh ] && source ~/.config/iro/colors.sh",
+            "iro/colors.sh",
+        ),
     };
+    let shell_integration = format!("\n# iro - dynamic color scheme\n{}\n", source_line);
 
-    let shell_integration = "\n# iro - dynamic color scheme\n[ -f ~/.config/iro/colors.sh ] && source ~/.config/iro/colors.sh\n";
+    // nushell resolves `source` before running anything, so the file has to exist
+    // ahead of the first theme
+    let nu_colors = iro_config.join("colors.nu");
+    if shell_name == "nu" && !nu_colors.exists() {
+        std::fs::write(&nu_colors, "# Replaced by iro with the theme colors\n")?;
+    }
 
     if shell_rc.exists() {
         let content = std::fs::read_to_string(&shell_rc)?;
-        if !content.contains("iro") {
+        if !sources_colors(&content, colors_file) {
             std::fs::write(&shell_rc, format!("{}{}", content, shell_integration))?;
             println!("  ✓ Added iro to {}", shell_rc.display());
         } else {
             println!("  ✓ iro already in {}", shell_rc.display());
         }
     } else {
+        if let Some(parent) = shell_rc.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         std::fs::write(&shell_rc, shell_integration)?;
         println!("  ✓ Created {} with iro integration", shell_rc.display());
     }
 
+    // 4. systemd user units, only where systemd runs
+    let systemd_installed = match systemd_interval {
+        Some(interval) if Path::new("/run/systemd/system").exists() => {
+            println!("\n⏱  systemd units...");
+            install_systemd_units(&config_dir.join("systemd").join("user"), interval)?;
+            true
+        }
+        _ => false,
+    };
+
     println!("\n✅ iro initialization complete!");
     println!("\n📝 Next steps:");
     println!("  1. Add wallpapers to {}", wallpaper_dir.display());
@@ -449,8 +1370,145 @@ fn run_init() -> Result<()> {
     );
     println!("\n💡 Optional: Add to your hyprland.conf for automatic wallpaper on startup:");
     println!("    exec-once = iro --random");
+    if systemd_installed {
+        println!("\n⏱  Enable the restore on login and the wallpaper rotation with:");
+        println!("    systemctl --user daemon-reload");
+        println!("    systemctl --user enable iro.service iro.timer");
+        println!("    systemctl --user start iro.timer");
+        println!("  Hyprland has to share its environment with systemd for these to reach it:");
+        println!("    exec-once = dbus-update-activation-environment --systemd --all");
+    }
     println!("\n⚙️  Config: ~/.config/iro/config.toml");
     println!("    wallpaper_dir = \"{}\"", iro_cfg.wallpaper_dir);
 
     Ok(())
 }
+
+/// Whether the shell rc `content` already sources `colors_file`. Looks for the file
+/// itself, since a bare "iro" also matches words like "environment".
+fn sources_colors(content: &str, colors_file: &str) -> bool {
+    content.contains(colors_file)
+}
+
+/// Write iro.service, which runs --restore when the graphical session starts, and
+/// iro.timer, which runs --random through iro-random.service every `interval`
+/// (a systemd time span such as "30min" or "2h")
+fn install_systemd_units(unit_dir: &Path, interval: &str) -> Result<()> {
+    if !interval.starts_with(|c: char| c.is_ascii_digit())
+        || !interval
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ')
+    {
+        anyhow::bail!(
+            "Invalid --systemd interval '{}', expected a time span like 30min or 2h",
+            interval
+        );
+    }
+
+    let iro = std::env::current_exe().context("Failed to locate the iro binary")?;
+    let units = [
+        (
+            "iro.service",
+            format!(
+                "[Unit]\n\
+                 Description=Restore the iro theme and wallpaper\n\
+                 PartOf=graphical-session.target\n\
+                 After=graphical-session.target\n\
+                 \n\
+                 [Service]\n\
+                 Type=oneshot\n\
+                 ExecStart={} --restore\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=graphical-session.target\n",
+                iro.display()
+            ),
+        ),
+        (
+            "iro-random.service",
+            format!(
+                "[Unit]\n\
+                 Description=Apply a random wallpaper and its theme with iro\n\
+                 After=graphical-session.target\n\
+                 \n\
+                 [Service]\n\
+                 Type=oneshot\n\
+                 ExecStart={} --random\n",
+                iro.display()
+            ),
+        ),
+        (
+            "iro.timer",
+            format!(
+                "[Unit]\n\
+                 Description=Rotate the wallpaper with iro every {interval}\n\
+                 \n\
+                 [Timer]\n\
+                 OnStartupSec={interval}\n\
+                 OnUnitActiveSec={interval}\n\
+                 Unit=iro-random.service\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=timers.target\n"
+            ),
+        ),
+    ];
+
+    std::fs::create_dir_all(unit_dir).context("Failed to create systemd user directory")?;
+    for (name, content) in units {
+        let path = unit_dir.join(name);
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("  ✓ Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_mappings_expand_the_home_dir() {
+        let (monitor, path) = parse_monitor_mapping("DP-1:~/walls/a.png").unwrap();
+        assert_eq!(monitor, "DP-1");
+        assert_eq!(path, dirs::home_dir().unwrap().join("walls/a.png"));
+    }
+
+    #[test]
+    fn paths_are_not_monitor_mappings() {
+        assert_eq!(parse_monitor_mapping("~/walls/a.png"), None);
+        assert_eq!(parse_monitor_mapping("./dir:x/a.png"), None);
+        assert_eq!(parse_monitor_mapping(":a.png"), None);
+    }
+
+    #[test]
+    fn setting_no_wallpapers_is_an_error() {
+        let error = set_wallpapers(
+            &[],
+            &HashMap::new(),
+            None,
+            wallpaper::Effects::default(),
+            "#000000",
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "No wallpapers to set");
+    }
+
+    #[test]
+    fn rc_files_only_count_as_integrated_when_they_source_the_colors() {
+        let unrelated = "# environment\nexport PATH=\"$HOME/.local/bin:$PATH\"\n";
+        assert!(!sources_colors(unrelated, "iro/colors.sh"));
+
+        let integrated = format!(
+            "{}\n# iro - dynamic color scheme\n\
+             [ -f ~/.config/iro/colors.sh ] && source ~/.config/iro/colors.sh\n",
+            unrelated
+        );
+        assert!(sources_colors(&integrated, "iro/colors.sh"));
+        // Sourcing the bash colors doesn't cover fish
+        assert!(!sources_colors(&integrated, "iro/colors.fish"));
+    }
+}
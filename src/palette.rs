@@ -1,12 +1,31 @@
-use crate::config::{ColorHarmony, PaletteStyle};
+use crate::color_math::{contrast_ratio, oklch_to_rgb, quantize, to_srgb};
+use crate::config::{
+    BrightnessSpace, ColorHarmony, DistanceMetric, Harmony, PaletteAlgorithm, PaletteStyle,
+};
 use anyhow::Result;
 use image::{Rgb, RgbImage};
-use palette::{Hsl, IntoColor, Srgb};
+use palette::{color_difference::Ciede2000, Hsl, IntoColor, Lab, Oklch, Srgb};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// WCAG contrast the foreground aims for against the background (AAA is 7.0)
+const FOREGROUND_TARGET_CONTRAST: f32 = 10.0;
+
+/// Hues closer than this many degrees count as the same hue
+const DISTINCT_HUE_SEPARATION: f32 = 30.0;
+
+/// k-means stops once no centroid moves further than this (in 0-255 RGB units)
+const KMEANS_EPSILON: f32 = 0.5;
+const KMEANS_MAX_ITERATIONS: usize = 20;
 
 pub struct PaletteGenerator {
     diversity_threshold: f32,
     style: PaletteStyle,
+    algorithm: PaletteAlgorithm,
+    distance_metric: DistanceMetric,
+    brightness_space: BrightnessSpace,
+    brightness_range: RangeInclusive<u8>,
 }
 
 impl PaletteGenerator {
@@ -14,18 +33,85 @@ impl PaletteGenerator {
         Self {
             diversity_threshold,
             style,
+            algorithm: PaletteAlgorithm::default(),
+            distance_metric: DistanceMetric::default(),
+            brightness_space: BrightnessSpace::default(),
+            brightness_range: 20..=240,
         }
     }
 
-    /// Extract diverse colors from an image
-    pub fn extract_palette(&self, img: &RgbImage, count: usize) -> Result<Vec<Rgb<u8>>> {
+    /// Extract candidate colors with `algorithm` instead of the frequency histogram
+    pub fn with_algorithm(mut self, algorithm: PaletteAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Compare candidate colors with `metric`; `diversity_threshold` must be on its scale
+    pub fn with_distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = metric;
+        self
+    }
+
+    /// Scale lightness in `space` in `adjust_brightness`
+    pub fn with_brightness_space(mut self, space: BrightnessSpace) -> Self {
+        self.brightness_space = space;
+        self
+    }
+
+    /// Only extract from pixels whose average channel value falls in `range`
+    pub fn with_brightness_range(mut self, range: RangeInclusive<u8>) -> Self {
+        self.brightness_range = range;
+        self
+    }
+
+    /// Whether a pixel counts towards the palette; ones outside the brightness range are skipped
+    fn in_extraction_range(&self, pixel: &Rgb<u8>) -> bool {
+        let brightness = (pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3;
+        self.brightness_range.contains(&(brightness as u8))
+    }
+
+    /// Extract diverse colors from an image, each with the share of the image it covers
+    pub fn extract_palette(&self, img: &RgbImage, count: usize) -> Result<Vec<(Rgb<u8>, f32)>> {
+        Ok(self.extract_palette_variants(img, count, 1).remove(0))
+    }
+
+    /// Extract `variants` alternative palettes from the same image. The first one is
+    /// what `extract_palette` returns; each later one starts further down the frequency
+    /// ranking and asks for more spread between colors, so it lands on other accents.
+    pub fn extract_palette_variants(
+        &self,
+        img: &RgbImage,
+        count: usize,
+        variants: usize,
+    ) -> Vec<Vec<(Rgb<u8>, f32)>> {
+        match self.algorithm {
+            PaletteAlgorithm::Histogram => {}
+            PaletteAlgorithm::Kmeans => {
+                // Each variant seeds k-means++ differently, so it settles on other clusters
+                return (0..variants.max(1))
+                    .map(|variant| {
+                        let threshold = self.variant_threshold(variant);
+                        self.extract_palette_kmeans(img, count, variant as u64, threshold)
+                    })
+                    .collect();
+            }
+            PaletteAlgorithm::Octree => {
+                // Later variants keep a few more leaves and drop the most common ones
+                return (0..variants.max(1))
+                    .map(|variant| {
+                        let mut palette = self.extract_palette_octree(img, count + variant);
+                        palette.drain(..variant);
+                        palette
+                    })
+                    .collect();
+            }
+        }
+
         let mut color_counts: HashMap<(u8, u8, u8), u32> = HashMap::with_capacity(4096);
 
         // Count color frequencies with quantization - optimized
         for pixel in img.pixels() {
-            // Skip very dark and very bright pixels for better palette
-            let brightness = (pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3;
-            if !(20..=240).contains(&brightness) {
+            if !self.in_extraction_range(pixel) {
                 continue;
             }
 
@@ -38,14 +124,46 @@ impl PaletteGenerator {
             *color_counts.entry(quantized).or_insert(0) += 1;
         }
 
+        let total = color_counts.values().sum::<u32>().max(1) as f32;
+
         // Sort by frequency
         let mut colors: Vec<_> = color_counts.into_iter().collect();
-        colors.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+        colors.sort_unstable_by_key(|c| std::cmp::Reverse(c.1));
+
+        (0..variants.max(1))
+            .map(|variant| {
+                let threshold = self.variant_threshold(variant);
+                self.select_diverse_colors(&colors, total, count, variant, threshold)
+            })
+            .collect()
+    }
+
+    /// Spread `variant` asks for between colors, growing for later variants
+    fn variant_threshold(&self, variant: usize) -> f32 {
+        self.diversity_threshold * (1.0 + 0.25 * variant as f32)
+    }
+
+    /// Whether `color` is more than `threshold` away from every color in `palette`
+    fn is_distinct(&self, color: &Rgb<u8>, palette: &[(Rgb<u8>, f32)], threshold: f32) -> bool {
+        palette
+            .iter()
+            .all(|(existing, _)| self.color_distance(color, existing) > threshold)
+    }
 
+    /// Pick up to `count` colors from frequency-sorted `colors`, starting `skip` entries
+    /// in and keeping every pick at least `threshold` away from the others
+    fn select_diverse_colors(
+        &self,
+        colors: &[((u8, u8, u8), u32)],
+        total: f32,
+        count: usize,
+        skip: usize,
+        threshold: f32,
+    ) -> Vec<(Rgb<u8>, f32)> {
         // Select diverse colors - optimized
         let mut selected_colors = Vec::with_capacity(count);
 
-        for ((r, g, b), _) in colors.iter().take(count * 3) {
+        for ((r, g, b), pixels) in colors.iter().skip(skip).take(count * 3) {
             if selected_colors.len() >= count {
                 break;
             }
@@ -53,26 +171,212 @@ impl PaletteGenerator {
             let color = Rgb([*r, *g, *b]);
 
             // Check diversity only against existing colors
-            if selected_colors.is_empty()
-                || selected_colors.iter().all(|existing| {
-                    self.color_distance(&color, existing) > self.diversity_threshold
-                })
+            if self.is_distinct(&color, &selected_colors, threshold) {
+                selected_colors.push((color, *pixels as f32 / total));
+            }
+        }
+
+        self.pad_with_complementary(&mut selected_colors, count);
+        selected_colors
+    }
+
+    /// Fill `palette` up to `count` colors with complementary ones, which cover none
+    /// of the image
+    fn pad_with_complementary(&self, palette: &mut Vec<(Rgb<u8>, f32)>, count: usize) {
+        while palette.len() < count {
+            let colors: Vec<Rgb<u8>> = palette.iter().map(|(c, _)| *c).collect();
+            palette.push((self.generate_complementary_color(&colors), 0.0));
+        }
+    }
+
+    /// Quantize the image with an octree, merging the least populated branches until
+    /// `count` leaves are left. Works on full 8-bit colors, so unlike the histogram it
+    /// doesn't lose detail to coarse buckets. Leaves come back sorted by the share of
+    /// the image they cover.
+    fn extract_palette_octree(&self, img: &RgbImage, count: usize) -> Vec<(Rgb<u8>, f32)> {
+        let mut octree = Octree::new();
+        for pixel in img.pixels().filter(|pixel| self.in_extraction_range(pixel)) {
+            octree.insert(pixel);
+        }
+        octree.reduce(count.max(1));
+
+        let total = octree.nodes[0].pixels.max(1) as f32;
+        let mut leaves = octree.leaves();
+        leaves.sort_by_key(|&(_, pixels)| std::cmp::Reverse(pixels));
+
+        let mut palette: Vec<(Rgb<u8>, f32)> = leaves
+            .into_iter()
+            .take(count)
+            .map(|(color, pixels)| (color, pixels as f32 / total))
+            .collect();
+
+        // Images with fewer distinct colors than `count` have fewer leaves
+        self.pad_with_complementary(&mut palette, count);
+        palette
+    }
+
+    /// Cluster the image into `count` colors with Lloyd's k-means, seeded with k-means++.
+    /// Unlike the histogram this keeps close but distinct accents apart. Clusters come
+    /// back sorted by the share of the image they cover, and like the histogram's picks
+    /// each is at least `threshold` away from the larger ones.
+    fn extract_palette_kmeans(
+        &self,
+        img: &RgbImage,
+        count: usize,
+        seed: u64,
+        threshold: f32,
+    ) -> Vec<(Rgb<u8>, f32)> {
+        let points: Vec<[f32; 3]> = img
+            .pixels()
+            .filter(|pixel| self.in_extraction_range(pixel))
+            .map(|pixel| [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32])
+            .collect();
+
+        let mut palette = Vec::with_capacity(count);
+        if !points.is_empty() && count > 0 {
+            let mut centroids = kmeans_plus_plus(&points, count, seed);
+            let mut assignments = vec![0; points.len()];
+
+            for _ in 0..KMEANS_MAX_ITERATIONS {
+                for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+                    *assignment = nearest_centroid(point, &centroids).0;
+                }
+
+                let mut sums = vec![[0.0_f64; 3]; centroids.len()];
+                let mut sizes = vec![0_usize; centroids.len()];
+                for (point, &assignment) in points.iter().zip(&assignments) {
+                    for channel in 0..3 {
+                        sums[assignment][channel] += point[channel] as f64;
+                    }
+                    sizes[assignment] += 1;
+                }
+
+                // An empty cluster keeps its old centroid
+                let mut shift: f32 = 0.0;
+                for (centroid, (sum, &size)) in centroids.iter_mut().zip(sums.iter().zip(&sizes)) {
+                    if size == 0 {
+                        continue;
+                    }
+                    let mean = sum.map(|total| (total / size as f64) as f32);
+                    shift = shift.max(squared_distance(centroid, &mean).sqrt());
+                    *centroid = mean;
+                }
+
+                if shift < KMEANS_EPSILON {
+                    break;
+                }
+            }
+
+            let mut sizes = vec![0_usize; centroids.len()];
+            for point in &points {
+                sizes[nearest_centroid(point, &centroids).0] += 1;
+            }
+
+            let mut clusters: Vec<_> = centroids.into_iter().zip(sizes).collect();
+            clusters.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+            for (centroid, size) in clusters.into_iter().filter(|(_, size)| *size > 0) {
+                // Near duplicates of a larger cluster would waste a slot
+                let color = Rgb(centroid.map(|c| c.round().clamp(0.0, 255.0) as u8));
+                if self.is_distinct(&color, &palette, threshold) {
+                    palette.push((color, size as f32 / points.len() as f32));
+                }
+            }
+        }
+
+        // Images with fewer distinct colors than `count` yield fewer clusters
+        self.pad_with_complementary(&mut palette, count);
+        palette
+    }
+
+    /// Make sure the palette has at least `min_hues` distinct hues by replacing its
+    /// least common colors with rotations of the most saturated one. Keeps monochrome
+    /// wallpapers (sepia, single-tone art) from producing a flat, single-hue theme.
+    pub fn ensure_distinct_hues(&self, palette: &mut [(Rgb<u8>, f32)], min_hues: usize) {
+        let to_hsl = |c: &Rgb<u8>| -> Hsl {
+            Srgb::new(c[0], c[1], c[2])
+                .into_format::<f32>()
+                .into_color()
+        };
+
+        // Greedily group hues, remembering which palette entry represents each group
+        let mut distinct: Vec<(f32, usize)> = Vec::new();
+        for (i, (color, _)) in palette.iter().enumerate() {
+            let hsl = to_hsl(color);
+            if hsl.saturation < 0.15 || !(0.1..0.9).contains(&hsl.lightness) {
+                continue;
+            }
+            let hue = hsl.hue.into_positive_degrees();
+            if distinct
+                .iter()
+                .all(|&(h, _)| self.normalize_hue_diff(hue - h).abs() > DISTINCT_HUE_SEPARATION)
             {
-                selected_colors.push(color);
+                distinct.push((hue, i));
             }
         }
 
-        // Fill remaining with complementary if needed
-        while selected_colors.len() < count {
-            selected_colors.push(self.generate_complementary_color(&selected_colors));
+        if distinct.len() >= min_hues {
+            return;
+        }
+
+        let Some(anchor) = palette
+            .iter()
+            .map(|(c, _)| to_hsl(c))
+            .max_by(|a, b| a.saturation.total_cmp(&b.saturation))
+        else {
+            return;
+        };
+        let anchor_hue = anchor.hue.into_positive_degrees();
+
+        // Replace from the least common end, keeping the colors that already carry a hue
+        let mut replaceable: Vec<usize> = (0..palette.len())
+            .filter(|i| distinct.iter().all(|&(_, rep)| rep != *i))
+            .collect();
+
+        let step = 360.0 / min_hues as f32;
+        for k in 1..min_hues {
+            if distinct.len() >= min_hues {
+                break;
+            }
+
+            let hue = (anchor_hue + step * k as f32) % 360.0;
+            if distinct
+                .iter()
+                .any(|&(h, _)| self.normalize_hue_diff(hue - h).abs() <= DISTINCT_HUE_SEPARATION)
+            {
+                continue;
+            }
+
+            let Some(index) = replaceable.pop() else {
+                break;
+            };
+
+            let hsl = Hsl::new(
+                hue,
+                anchor.saturation.max(0.5),
+                anchor.lightness.clamp(0.4, 0.65),
+            );
+            let rgb: Srgb = hsl.into_color();
+            let rgb = rgb.into_format::<u8>();
+            palette[index] = (Rgb([rgb.red, rgb.green, rgb.blue]), 0.0);
+            distinct.push((hue, index));
         }
+    }
 
-        Ok(selected_colors)
+    /// Difference between two colors on the scale of `distance_metric`
+    fn color_distance(&self, c1: &Rgb<u8>, c2: &Rgb<u8>) -> f32 {
+        match self.distance_metric {
+            DistanceMetric::Lab => {
+                let lab1: Lab = to_srgb(c1).into_color();
+                let lab2: Lab = to_srgb(c2).into_color();
+                lab1.difference(lab2)
+            }
+            DistanceMetric::Rgb => Self::rgb_distance(c1, c2),
+        }
     }
 
     /// Calculate color distance - simplified for speed
     #[inline]
-    fn color_distance(&self, c1: &Rgb<u8>, c2: &Rgb<u8>) -> f32 {
+    fn rgb_distance(c1: &Rgb<u8>, c2: &Rgb<u8>) -> f32 {
         // Simple euclidean distance in RGB space - much faster than HSL conversion
         let dr = (c1[0] as i16 - c2[0] as i16).abs() as f32;
         let dg = (c1[1] as i16 - c2[1] as i16).abs() as f32;
@@ -89,11 +393,7 @@ impl PaletteGenerator {
         }
 
         let last = existing.last().unwrap();
-        let rgb = Srgb::new(
-            last[0] as f32 / 255.0,
-            last[1] as f32 / 255.0,
-            last[2] as f32 / 255.0,
-        );
+        let rgb = to_srgb(last);
 
         let mut hsl: Hsl = rgb.into_color();
 
@@ -105,23 +405,13 @@ impl PaletteGenerator {
         hsl.lightness = 0.5;
 
         let rgb_out: Srgb = hsl.into_color();
-        Rgb([
-            (rgb_out.red * 255.0) as u8,
-            (rgb_out.green * 255.0) as u8,
-            (rgb_out.blue * 255.0) as u8,
-        ])
+        quantize(rgb_out)
     }
 
     /// Adjust color with style-specific modifications
     #[inline]
-    pub fn adjust_with_style(&self, color: &Rgb<u8>, is_light: bool) -> Rgb<u8> {
-        let rgb = Srgb::new(
-            color[0] as f32 / 255.0,
-            color[1] as f32 / 255.0,
-            color[2] as f32 / 255.0,
-        );
-
-        let mut hsl: Hsl = rgb.into_color();
+    pub fn adjust_with_style(&self, color: &Srgb, is_light: bool) -> Srgb {
+        let mut hsl: Hsl = (*color).into_color();
 
         // Apply style-specific adjustments
         let (sat_factor, bright_factor) = if is_light {
@@ -135,75 +425,97 @@ impl PaletteGenerator {
             hsl.hue += self.style.warmth_shift * 30.0;
         }
 
+        // Grays keep zero saturation, a floor would give them the meaningless hue 0 (red)
+        let has_hue = hsl.saturation > 0.05;
+
         // Apply saturation and brightness
         hsl.saturation = (hsl.saturation * sat_factor).clamp(0.0, 1.0);
         hsl.lightness = (hsl.lightness * bright_factor).clamp(0.0, 1.0);
 
+        if let Some(floor) = self.style.saturation_floor.filter(|_| has_hue) {
+            hsl.saturation = hsl.saturation.max(floor);
+        }
+        if let Some(ceiling) = self.style.saturation_ceiling {
+            hsl.saturation = hsl.saturation.min(ceiling);
+        }
+
         // Apply contrast if needed
         if (self.style.contrast - 1.0).abs() > 0.001 {
             hsl.lightness = 0.5 + (hsl.lightness - 0.5) * self.style.contrast;
             hsl.lightness = hsl.lightness.clamp(0.0, 1.0);
         }
 
-        let rgb_out: Srgb = hsl.into_color();
-        Rgb([
-            (rgb_out.red * 255.0) as u8,
-            (rgb_out.green * 255.0) as u8,
-            (rgb_out.blue * 255.0) as u8,
-        ])
+        if let Some((low, high)) = self.style.lightness_range {
+            hsl.lightness = low + hsl.lightness * (high - low);
+        }
+
+        hsl.into_color()
     }
 
-    /// Adjust color brightness
+    /// Adjust color brightness by scaling its lightness in the configured space
     pub fn adjust_brightness(&self, color: &Rgb<u8>, factor: f32) -> Rgb<u8> {
-        let rgb = Srgb::new(
-            color[0] as f32 / 255.0,
-            color[1] as f32 / 255.0,
-            color[2] as f32 / 255.0,
-        );
+        let rgb = to_srgb(color);
+
+        if self.brightness_space == BrightnessSpace::Oklab {
+            let mut lch: Oklch = rgb.into_color();
+            lch.l = (lch.l * factor).clamp(0.0, 1.0);
+            return oklch_to_rgb(lch);
+        }
 
         let mut hsl: Hsl = rgb.into_color();
         hsl.lightness = (hsl.lightness * factor).clamp(0.0, 1.0);
 
         let rgb_out: Srgb = hsl.into_color();
-        Rgb([
-            (rgb_out.red * 255.0) as u8,
-            (rgb_out.green * 255.0) as u8,
-            (rgb_out.blue * 255.0) as u8,
-        ])
+        quantize(rgb_out)
     }
 
     /// Generate a foreground color that contrasts with background
     pub fn generate_foreground(&self, background: &Rgb<u8>, is_light: bool) -> Rgb<u8> {
         // Extract hue from background
-        let bg_rgb = Srgb::new(
-            background[0] as f32 / 255.0,
-            background[1] as f32 / 255.0,
-            background[2] as f32 / 255.0,
-        );
+        let bg_rgb = to_srgb(background);
         let bg_hsl: Hsl = bg_rgb.into_color();
 
         // Create foreground with same hue but high contrast
         let mut fg_hsl = bg_hsl;
 
-        if is_light {
+        // Search the lightness range on the readable side of the background
+        let (mut low, mut high) = if is_light {
             // Dark text on light background
-            fg_hsl.lightness = 0.25;
             fg_hsl.saturation = (bg_hsl.saturation * 0.5).min(0.15); // Subtle tint
+            (0.0_f32, 0.5_f32)
         } else {
             // Light text on dark background
-            fg_hsl.lightness = 0.85;
             fg_hsl.saturation = (bg_hsl.saturation * 0.4).min(0.12); // Subtle tint
+            (0.5_f32, 1.0_f32)
+        };
+
+        let to_rgb = |hsl: Hsl| {
+            let rgb: Srgb = hsl.into_color();
+            quantize(rgb)
+        };
+
+        // Bisect for the lightness closest to the background that still hits the target
+        // contrast; contrast grows monotonically as we move away from the background
+        for _ in 0..20 {
+            let mid = (low + high) / 2.0;
+            fg_hsl.lightness = mid;
+            let meets_target =
+                contrast_ratio(&to_rgb(fg_hsl), background) >= FOREGROUND_TARGET_CONTRAST;
+
+            if meets_target == is_light {
+                low = mid;
+            } else {
+                high = mid;
+            }
         }
 
-        let fg_rgb: Srgb = fg_hsl.into_color();
-        Rgb([
-            (fg_rgb.red * 255.0) as u8,
-            (fg_rgb.green * 255.0) as u8,
-            (fg_rgb.blue * 255.0) as u8,
-        ])
+        // Keep the side of the interval that satisfies the target (or the extreme if none does)
+        fg_hsl.lightness = if is_light { low } else { high };
+        to_rgb(fg_hsl)
     }
 
-    pub fn apply_harmony(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    /// Nudge the palette's hues towards the style's `color_harmony`
+    pub fn apply_style_harmony(&self, colors: &[Srgb]) -> Vec<Srgb> {
         if colors.is_empty() {
             return vec![];
         }
@@ -217,17 +529,39 @@ impl PaletteGenerator {
         }
     }
 
-    fn get_dominant_hue(&self, colors: &[Rgb<u8>]) -> f32 {
+    /// `base` followed by its partners on the hue wheel for `mode`, with the same
+    /// saturation and lightness so they read alike on the background. Only `base`
+    /// for "natural" or a grey `base`.
+    pub fn apply_harmony(&self, base: Rgb<u8>, mode: Harmony) -> Vec<Rgb<u8>> {
+        let rotations: &[f32] = match mode {
+            Harmony::Natural => &[],
+            Harmony::Complementary => &[180.0],
+            Harmony::Triadic => &[120.0, 240.0],
+            Harmony::Analogous => &[30.0, -30.0],
+        };
+
+        let base_hsl: Hsl = to_srgb(&base).into_color();
+        if base_hsl.saturation <= 0.05 {
+            // A grey has no hue to rotate
+            return vec![base];
+        }
+
+        std::iter::once(base)
+            .chain(rotations.iter().map(|degrees| {
+                let mut hsl = base_hsl;
+                hsl.hue += *degrees;
+                quantize(hsl.into_color())
+            }))
+            .collect()
+    }
+
+    /// Circular mean of the palette's hues, weighted by saturation and frequency rank
+    pub fn get_dominant_hue(&self, colors: &[Srgb]) -> f32 {
         let mut hue_accumulator = (0.0_f32, 0.0_f32);
         let mut total_weight = 0.0_f32;
 
         for (i, color) in colors.iter().enumerate() {
-            let rgb = Srgb::new(
-                color[0] as f32 / 255.0,
-                color[1] as f32 / 255.0,
-                color[2] as f32 / 255.0,
-            );
-            let hsl: Hsl = rgb.into_color();
+            let hsl: Hsl = (*color).into_color();
 
             let weight = (colors.len() - i) as f32 * hsl.saturation;
             let hue_rad = hsl.hue.into_positive_degrees() * std::f32::consts::PI / 180.0;
@@ -248,19 +582,14 @@ impl PaletteGenerator {
         degrees
     }
 
-    fn apply_analogous_harmony(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    fn apply_analogous_harmony(&self, colors: &[Srgb]) -> Vec<Srgb> {
         let dominant_hue = self.get_dominant_hue(colors);
         let analogous_range = 30.0;
 
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 let hue_diff = self.normalize_hue_diff(current_hue - dominant_hue);
@@ -270,99 +599,64 @@ impl PaletteGenerator {
                     hsl.hue -= shift;
                 }
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
 
-    fn apply_triadic_harmony(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    fn apply_triadic_harmony(&self, colors: &[Srgb]) -> Vec<Srgb> {
         let dominant_hue = self.get_dominant_hue(colors);
         let triadic_hues = [dominant_hue, dominant_hue + 120.0, dominant_hue + 240.0];
 
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 let closest = self.find_closest_target_hue(current_hue, &triadic_hues);
                 let diff = self.normalize_hue_diff(current_hue - closest);
                 hsl.hue -= diff * 0.4;
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
 
-    fn apply_split_complementary_harmony(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    fn apply_split_complementary_harmony(&self, colors: &[Srgb]) -> Vec<Srgb> {
         let dominant_hue = self.get_dominant_hue(colors);
         let split_hues = [dominant_hue, dominant_hue + 150.0, dominant_hue + 210.0];
 
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 let closest = self.find_closest_target_hue(current_hue, &split_hues);
                 let diff = self.normalize_hue_diff(current_hue - closest);
                 hsl.hue -= diff * 0.35;
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
 
-    fn apply_complementary_harmony(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    fn apply_complementary_harmony(&self, colors: &[Srgb]) -> Vec<Srgb> {
         let dominant_hue = self.get_dominant_hue(colors);
         let comp_hues = [dominant_hue, dominant_hue + 180.0];
 
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 let closest = self.find_closest_target_hue(current_hue, &comp_hues);
                 let diff = self.normalize_hue_diff(current_hue - closest);
                 hsl.hue -= diff * 0.45;
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
@@ -389,7 +683,7 @@ impl PaletteGenerator {
             .unwrap_or(hue)
     }
 
-    pub fn boost_hue_ranges(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    pub fn boost_hue_ranges(&self, colors: &[Srgb]) -> Vec<Srgb> {
         if self.style.hue_boosts.is_empty() {
             return colors.to_vec();
         }
@@ -397,12 +691,7 @@ impl PaletteGenerator {
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 for &(center, range, boost) in self.style.hue_boosts {
@@ -413,17 +702,12 @@ impl PaletteGenerator {
                     }
                 }
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
 
-    pub fn apply_target_hue_shift(&self, colors: &[Rgb<u8>]) -> Vec<Rgb<u8>> {
+    pub fn apply_target_hue_shift(&self, colors: &[Srgb]) -> Vec<Srgb> {
         let targets = match self.style.target_hues {
             Some(t) => t,
             None => return colors.to_vec(),
@@ -436,12 +720,7 @@ impl PaletteGenerator {
         colors
             .iter()
             .map(|color| {
-                let rgb = Srgb::new(
-                    color[0] as f32 / 255.0,
-                    color[1] as f32 / 255.0,
-                    color[2] as f32 / 255.0,
-                );
-                let mut hsl: Hsl = rgb.into_color();
+                let mut hsl: Hsl = (*color).into_color();
                 let current_hue = hsl.hue.into_positive_degrees();
 
                 let closest = self.find_closest_target_hue(current_hue, targets);
@@ -449,28 +728,18 @@ impl PaletteGenerator {
 
                 hsl.hue -= diff * 0.25;
 
-                let rgb_out: Srgb = hsl.into_color();
-                Rgb([
-                    (rgb_out.red * 255.0) as u8,
-                    (rgb_out.green * 255.0) as u8,
-                    (rgb_out.blue * 255.0) as u8,
-                ])
+                hsl.into_color()
             })
             .collect()
     }
 
-    pub fn ensure_color_coverage(&self, colors: &[Rgb<u8>], is_light: bool) -> Vec<Rgb<u8>> {
+    pub fn ensure_color_coverage(&self, colors: &[Srgb], is_light: bool) -> Vec<Srgb> {
         let required_hues = [0.0, 60.0, 120.0, 180.0, 240.0, 300.0];
         let mut result = colors.to_vec();
 
         for &required_hue in &required_hues {
             let has_hue = colors.iter().any(|c| {
-                let rgb = Srgb::new(
-                    c[0] as f32 / 255.0,
-                    c[1] as f32 / 255.0,
-                    c[2] as f32 / 255.0,
-                );
-                let hsl: Hsl = rgb.into_color();
+                let hsl: Hsl = (*c).into_color();
                 let hue = hsl.hue.into_positive_degrees();
 
                 self.normalize_hue_diff(hue - required_hue).abs() < 45.0 && hsl.saturation > 0.2
@@ -479,12 +748,7 @@ impl PaletteGenerator {
             if !has_hue && result.len() < 16 {
                 let (sat, light) = if is_light { (0.65, 0.45) } else { (0.70, 0.60) };
                 let hsl = Hsl::new(required_hue, sat, light);
-                let rgb: Srgb = hsl.into_color();
-                result.push(Rgb([
-                    (rgb.red * 255.0) as u8,
-                    (rgb.green * 255.0) as u8,
-                    (rgb.blue * 255.0) as u8,
-                ]));
+                result.push(hsl.into_color());
             }
         }
 
@@ -500,17 +764,13 @@ impl PaletteGenerator {
             };
         }
 
-        let dominant_hue = self.get_dominant_hue(colors);
+        let colors: Vec<Srgb> = colors.iter().map(to_srgb).collect();
+        let dominant_hue = self.get_dominant_hue(&colors);
 
         let mut total_saturation = 0.0;
         let mut total_lightness = 0.0;
-        for color in colors {
-            let rgb = Srgb::new(
-                color[0] as f32 / 255.0,
-                color[1] as f32 / 255.0,
-                color[2] as f32 / 255.0,
-            );
-            let hsl: Hsl = rgb.into_color();
+        for color in &colors {
+            let hsl: Hsl = (*color).into_color();
             total_saturation += hsl.saturation;
             total_lightness += hsl.lightness;
         }
@@ -530,10 +790,444 @@ impl PaletteGenerator {
         }
 
         let rgb_out: Srgb = hsl.into_color();
-        Rgb([
-            (rgb_out.red * 255.0) as u8,
-            (rgb_out.green * 255.0) as u8,
-            (rgb_out.blue * 255.0) as u8,
-        ])
+        quantize(rgb_out)
+    }
+}
+
+/// Depth of the octree's leaves, one level per bit of each channel
+const OCTREE_DEPTH: usize = 8;
+
+#[derive(Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    /// Pixels in this subtree and the sum of their channels
+    pixels: u64,
+    sums: [u64; 3],
+    /// Leaves in this subtree, brought up to date level by level while reducing
+    leaves: usize,
+    is_leaf: bool,
+}
+
+/// Color octree: every level splits the RGB cube in eight by the next bit of each channel
+struct Octree {
+    /// Arena of nodes, the root first
+    nodes: Vec<OctreeNode>,
+    /// Inner nodes by level, the candidates for merging
+    levels: [Vec<usize>; OCTREE_DEPTH],
+    leaf_count: usize,
+}
+
+impl Octree {
+    fn new() -> Self {
+        let mut levels: [Vec<usize>; OCTREE_DEPTH] = Default::default();
+        levels[0].push(0);
+        Self {
+            nodes: vec![OctreeNode::default()],
+            levels,
+            leaf_count: 0,
+        }
+    }
+
+    fn insert(&mut self, pixel: &Rgb<u8>) {
+        let mut node = 0;
+        for level in 0..=OCTREE_DEPTH {
+            self.nodes[node].pixels += 1;
+            for channel in 0..3 {
+                self.nodes[node].sums[channel] += pixel[channel] as u64;
+            }
+            if level == OCTREE_DEPTH || self.nodes[node].is_leaf {
+                break;
+            }
+
+            let shift = 7 - level;
+            let branch = (((pixel[0] >> shift) & 1) << 2
+                | ((pixel[1] >> shift) & 1) << 1
+                | ((pixel[2] >> shift) & 1)) as usize;
+            node = match self.nodes[node].children[branch] {
+                Some(child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    let is_leaf = level + 1 == OCTREE_DEPTH;
+                    self.nodes.push(OctreeNode {
+                        is_leaf,
+                        ..Default::default()
+                    });
+                    self.nodes[node].children[branch] = Some(child);
+                    if is_leaf {
+                        self.leaf_count += 1;
+                    } else {
+                        self.levels[level + 1].push(child);
+                    }
+                    child
+                }
+            };
+        }
+    }
+
+    /// Merge the deepest, least populated inner nodes into leaves until `max_leaves`
+    /// remain. A merge that would leave fewer is passed over for a smaller one, moving
+    /// up a level once none on this one fits, so the palette doesn't come up short of
+    /// colors the image has. More than `max_leaves` only remain if no merge fits at all.
+    fn reduce(&mut self, max_leaves: usize) {
+        for level in (0..OCTREE_DEPTH).rev() {
+            let mut candidates = std::mem::take(&mut self.levels[level]);
+            for &node in &candidates {
+                self.nodes[node].leaves = self.nodes[node]
+                    .children
+                    .iter()
+                    .flatten()
+                    .map(|&child| {
+                        let child = &self.nodes[child];
+                        if child.is_leaf {
+                            1
+                        } else {
+                            child.leaves
+                        }
+                    })
+                    .sum();
+            }
+            candidates.sort_by_key(|&node| std::cmp::Reverse(self.nodes[node].pixels));
+
+            while self.leaf_count > max_leaves {
+                let excess = self.leaf_count - max_leaves;
+                let fitting = candidates
+                    .iter()
+                    .rposition(|&node| self.nodes[node].leaves <= excess + 1);
+                let Some(index) = fitting else {
+                    break;
+                };
+                let node = candidates.remove(index);
+                self.nodes[node].children = [None; 8];
+                self.nodes[node].is_leaf = true;
+                self.leaf_count = self.leaf_count + 1 - self.nodes[node].leaves;
+            }
+
+            if self.leaf_count <= max_leaves {
+                return;
+            }
+        }
+    }
+
+    /// Average color and pixel count of every leaf
+    fn leaves(&self) -> Vec<(Rgb<u8>, u64)> {
+        let mut leaves = Vec::with_capacity(self.leaf_count);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.is_leaf {
+                if node.pixels > 0 {
+                    let average = node.sums.map(|sum| (sum / node.pixels) as u8);
+                    leaves.push((Rgb(average), node.pixels));
+                }
+            } else {
+                stack.extend(node.children.iter().flatten());
+            }
+        }
+        leaves
+    }
+}
+
+fn squared_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+/// Index of the centroid closest to `point`, and its squared distance
+fn nearest_centroid(point: &[f32; 3], centroids: &[[f32; 3]]) -> (usize, f32) {
+    centroids
+        .iter()
+        .map(|centroid| squared_distance(point, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+/// Pick up to `count` initial centroids, each new one with probability proportional to
+/// its squared distance from the closest centroid so far. Stops early once every point
+/// sits on a centroid, i.e. the image has fewer distinct colors than `count`.
+fn kmeans_plus_plus(points: &[[f32; 3]], count: usize, seed: u64) -> Vec<[f32; 3]> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut centroids = vec![points[rng.gen_range(0..points.len())]];
+    let mut distances: Vec<f32> = points
+        .iter()
+        .map(|point| squared_distance(point, &centroids[0]))
+        .collect();
+
+    while centroids.len() < count {
+        let total: f32 = distances.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+
+        let mut target = rng.gen_range(0.0..total);
+        let next = distances
+            .iter()
+            .position(|&d| {
+                target -= d;
+                target < 0.0
+            })
+            .unwrap_or(points.len() - 1);
+        let centroid = points[next];
+        centroids.push(centroid);
+
+        for (distance, point) in distances.iter_mut().zip(points) {
+            *distance = distance.min(squared_distance(point, &centroid));
+        }
+    }
+
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn generator() -> PaletteGenerator {
+        PaletteGenerator::new(20.0, PaletteStyle::default())
+    }
+
+    fn hsl(color: &Rgb<u8>) -> Hsl {
+        to_srgb(color).into_color()
+    }
+
+    /// Hues the way `ensure_distinct_hues` counts them
+    fn distinct_hues(palette: &[(Rgb<u8>, f32)]) -> usize {
+        let mut hues: Vec<f32> = Vec::new();
+        for (color, _) in palette {
+            let hsl = hsl(color);
+            if hsl.saturation < 0.15 || !(0.1..0.9).contains(&hsl.lightness) {
+                continue;
+            }
+            let hue = hsl.hue.into_positive_degrees();
+            let separated = |h: &f32| {
+                let diff = (hue - h).rem_euclid(360.0);
+                diff.min(360.0 - diff) > DISTINCT_HUE_SEPARATION
+            };
+            if hues.iter().all(separated) {
+                hues.push(hue);
+            }
+        }
+        hues.len()
+    }
+
+    #[test]
+    fn sepia_gets_the_requested_hues() {
+        // Brown tones from dark to light, all around the same orange hue
+        let img = RgbImage::from_fn(64, 64, |x, y| {
+            let t = (x + y) as f32 / 126.0;
+            Rgb([
+                (60.0 + 160.0 * t) as u8,
+                (40.0 + 130.0 * t) as u8,
+                (20.0 + 90.0 * t) as u8,
+            ])
+        });
+        let palette_gen = generator();
+        let mut palette = palette_gen.extract_palette(&img, 8).unwrap();
+        assert!(distinct_hues(&palette) < 4);
+
+        palette_gen.ensure_distinct_hues(&mut palette, 4);
+        assert_eq!(palette.len(), 8);
+        assert!(distinct_hues(&palette) >= 4);
+    }
+
+    #[test]
+    fn identity_adjustments_are_lossless() {
+        let identity = PaletteStyle {
+            dark_saturation: 1.0,
+            light_saturation: 1.0,
+            dark_brightness: 1.0,
+            light_brightness: 1.0,
+            contrast: 1.0,
+            warmth_shift: 0.0,
+            ..PaletteStyle::default()
+        };
+        let hsl_gen = PaletteGenerator::new(20.0, identity.clone());
+        let oklab_gen =
+            PaletteGenerator::new(20.0, identity).with_brightness_space(BrightnessSpace::Oklab);
+
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let color = Rgb([r as u8, g as u8, b as u8]);
+                    for is_light in [false, true] {
+                        let adjusted = hsl_gen.adjust_with_style(&to_srgb(&color), is_light);
+                        assert_eq!(quantize(adjusted), color);
+                    }
+                    assert_eq!(hsl_gen.adjust_brightness(&color, 1.0), color);
+                    assert_eq!(oklab_gen.adjust_brightness(&color, 1.0), color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kmeans_recovers_three_clusters() {
+        let centers = [Rgb([200, 40, 40]), Rgb([40, 180, 60]), Rgb([50, 70, 200])];
+        // Three bands with a little noise around each center
+        let img = RgbImage::from_fn(90, 30, |x, y| {
+            let center = centers[(x / 30) as usize];
+            let noise = ((x * 7 + y * 13) % 9) as i16 - 4;
+            Rgb(center.0.map(|c| (c as i16 + noise) as u8))
+        });
+
+        let palette = generator()
+            .with_algorithm(PaletteAlgorithm::Kmeans)
+            .extract_palette(&img, 3)
+            .unwrap();
+        assert_eq!(palette.len(), 3);
+
+        for center in centers {
+            let nearest = palette
+                .iter()
+                .map(|(color, _)| PaletteGenerator::rgb_distance(color, &center))
+                .fold(f32::MAX, f32::min);
+            assert!(nearest < 3.0, "no cluster near {:?}: {:?}", center, palette);
+        }
+        for (_, share) in &palette {
+            assert!((share - 1.0 / 3.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn kmeans_drops_near_duplicate_clusters() {
+        // Two reds a few steps apart, which k-means asked for three clusters splits
+        let centers = [Rgb([200, 40, 40]), Rgb([206, 44, 40]), Rgb([50, 70, 200])];
+        let img = RgbImage::from_fn(90, 30, |x, _| centers[(x / 30) as usize]);
+
+        let generator = generator().with_algorithm(PaletteAlgorithm::Kmeans);
+        let palette = generator.extract_palette(&img, 3).unwrap();
+        assert_eq!(palette.len(), 3);
+
+        let extracted: Vec<_> = palette.iter().filter(|(_, share)| *share > 0.0).collect();
+        assert_eq!(extracted.len(), 2, "{:?}", palette);
+        assert!(generator.color_distance(&extracted[0].0, &extracted[1].0) > 20.0);
+    }
+
+    /// Gradients over every channel, so there are far more colors than palette slots
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                ((x + y) % 256) as u8,
+            ])
+        })
+    }
+
+    #[test]
+    fn octree_fills_every_requested_color() {
+        let palette = generator()
+            .with_algorithm(PaletteAlgorithm::Octree)
+            .extract_palette(&gradient(480, 270), 16)
+            .unwrap();
+        assert_eq!(palette.len(), 16);
+        assert!(palette.iter().all(|(_, share)| *share > 0.0));
+
+        // The colors come from across the gradient, not from one corner of it
+        for channel in 0..2 {
+            let values = palette.iter().map(|(color, _)| color[channel]);
+            assert!(values.clone().min().unwrap() < 64);
+            assert!(values.max().unwrap() > 192);
+        }
+    }
+
+    #[test]
+    fn octree_reduces_close_to_the_requested_leaves() {
+        let img = gradient(480, 270);
+        for max_leaves in [1, 16, 64, 256] {
+            let mut octree = Octree::new();
+            for pixel in img.pixels() {
+                octree.insert(pixel);
+            }
+            octree.reduce(max_leaves);
+            // Never fewer, and no more than one node's worth of children over
+            let leaves = octree.leaves().len();
+            assert_eq!(leaves, octree.leaf_count);
+            assert!((max_leaves..max_leaves + 8).contains(&leaves), "{}", leaves);
+        }
+    }
+
+    #[test]
+    #[ignore = "timing, run with --ignored on an otherwise idle machine"]
+    fn octree_keeps_up_with_histogram_on_4k() {
+        let img = gradient(3840, 2160);
+        let time = |algorithm| {
+            let palette_gen = generator().with_algorithm(algorithm);
+            let start = Instant::now();
+            palette_gen.extract_palette(&img, 16).unwrap();
+            start.elapsed()
+        };
+        let histogram = time(PaletteAlgorithm::Histogram);
+        let octree = time(PaletteAlgorithm::Octree);
+
+        // Both are a single pass over the pixels, octree insertion just costs more
+        assert!(
+            octree < histogram * 20,
+            "octree took {:?}, histogram {:?}",
+            octree,
+            histogram
+        );
+    }
+
+    #[test]
+    fn neon_and_pastel_keep_greys_neutral() {
+        for name in ["neon", "pastel"] {
+            let palette_gen = PaletteGenerator::new(20.0, PaletteStyle::from_name(name).unwrap());
+            for value in (0..=255).step_by(5) {
+                let grey = Rgb([value as u8; 3]);
+                for is_light in [false, true] {
+                    let adjusted =
+                        quantize(palette_gen.adjust_with_style(&to_srgb(&grey), is_light));
+                    let spread =
+                        adjusted.0.iter().max().unwrap() - adjusted.0.iter().min().unwrap();
+                    assert!(
+                        spread <= 1,
+                        "{} turned {:?} into {:?}",
+                        name,
+                        grey,
+                        adjusted
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oklab_brightness_keeps_hue() {
+        let lch = |color: &Rgb<u8>| -> Oklch { to_srgb(color).into_color() };
+        // None when the color is too grey for its hue to mean anything
+        let drift = |before: &Rgb<u8>, after: &Rgb<u8>| {
+            let (before, after) = (lch(before), lch(after));
+            (after.chroma > 0.03).then(|| {
+                let diff = (after.hue.into_positive_degrees() - before.hue.into_positive_degrees())
+                    .rem_euclid(360.0);
+                diff.min(360.0 - diff)
+            })
+        };
+
+        let hsl_gen = generator();
+        let oklab_gen = generator().with_brightness_space(BrightnessSpace::Oklab);
+        let colors = [
+            Rgb([0xe0, 0x6c, 0x75]),
+            Rgb([0x98, 0xc3, 0x79]),
+            Rgb([0xe5, 0xc0, 0x7b]),
+            Rgb([0x61, 0xaf, 0xef]),
+            Rgb([0xc6, 0x78, 0xdd]),
+            Rgb([0x56, 0xb6, 0xc2]),
+        ];
+
+        let (mut hsl_worst, mut oklab_worst) = (0.0_f32, 0.0_f32);
+        for color in colors {
+            for factor in [0.6, 0.8, 1.2] {
+                if let Some(d) = drift(&color, &hsl_gen.adjust_brightness(&color, factor)) {
+                    hsl_worst = hsl_worst.max(d);
+                }
+                if let Some(d) = drift(&color, &oklab_gen.adjust_brightness(&color, factor)) {
+                    oklab_worst = oklab_worst.max(d);
+                }
+            }
+        }
+        assert!(oklab_worst < 1.0, "OKLab shifted a hue by {}°", oklab_worst);
+        assert!(oklab_worst < hsl_worst);
     }
 }
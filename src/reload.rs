@@ -0,0 +1,174 @@
+use crate::config::{expand_path, IroConfig, OutputMode, WaybarInstance};
+use crate::config_generator::{tmux_colors_path, tmux_config_path};
+use crate::term::recolor_open_terminals;
+use crate::util::find_in_path;
+use crate::wallpaper::spawn_detached;
+use crate::ColorScheme;
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+/// Run the `[reload]` commands, or reload Hyprland and the notification daemons when
+/// none are configured; with `live`, also push its colors to the open terminals
+pub fn reload_applications(live: Option<&ColorScheme>) -> Result<()> {
+    let config = IroConfig::load()?;
+    match &config.reload.commands {
+        Some(commands) => run_reload_commands(commands),
+        None => reload_defaults(&config)?,
+    }
+
+    if let Some(scheme) = live {
+        match recolor_open_terminals(scheme) {
+            Ok(count) => println!("  ✓ Recolored {} open terminal(s)", count),
+            Err(e) => println!("  ⚠ Failed to recolor open terminals: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Run each command with `sh -c`, reporting failures without stopping the rest. Only
+/// the exit status is waited for: a command that backgrounds a process (`waybar &`)
+/// would otherwise keep an output pipe open and hang iro. Errors go straight to the
+/// terminal.
+fn run_reload_commands(commands: &[String]) {
+    for command in commands {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => println!("  ✓ Ran {}", command),
+            Ok(status) => println!("  ⚠ {} failed ({})", command, status),
+            Err(e) => println!("  ⚠ Failed to run {}: {}", command, e),
+        }
+    }
+}
+
+fn reload_defaults(config: &IroConfig) -> Result<()> {
+    Command::new("hyprctl")
+        .args(["reload"])
+        .output()
+        .context("Failed to reload hyprland")?;
+
+    println!("  ✓ Reloaded Hyprland");
+
+    // Notification daemons are optional, so a missing or stopped one is not an error
+    for (name, ctl) in [("mako", "makoctl"), ("dunst", "dunstctl")] {
+        let reloaded = Command::new(ctl)
+            .arg("reload")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if reloaded {
+            println!("  ✓ Reloaded {}", name);
+        }
+    }
+
+    if config.target_enabled("tmux") {
+        reload_tmux(config);
+    }
+
+    if config.target_enabled("waybar") {
+        reload_waybar(&config.reload.waybar);
+    }
+    Ok(())
+}
+
+/// Have a running tmux server pick up the new colors. In include mode only iro's
+/// colors file is sourced, since re-reading all of tmux.conf would also rerun the
+/// user's own commands, like binds and `run-shell` lines. Without a server
+/// `tmux source-file` fails, and there is nothing to recolor.
+fn reload_tmux(config: &IroConfig) {
+    let Some(config_dir) = dirs::config_dir() else {
+        return;
+    };
+    let colors = match config.output_mode("tmux", OutputMode::Include) {
+        OutputMode::Include => Some(tmux_colors_path(&config_dir)).filter(|path| path.exists()),
+        OutputMode::Inline | OutputMode::Template => tmux_config_path(&config_dir),
+    };
+    let Some(colors) = colors else {
+        return;
+    };
+    let reloaded = Command::new("tmux")
+        .arg("source-file")
+        .arg(&colors)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if reloaded {
+        println!("  ✓ Reloaded tmux");
+    }
+}
+
+/// Waybar re-reads its stylesheet on SIGUSR2, which keeps module state and doesn't
+/// flicker; every running bar gets it, so multi-bar setups keep their layout. Bars are
+/// only ever started when listed in `instances` and not running.
+fn reload_waybar(instances: &[WaybarInstance]) {
+    let signalled = Command::new("pkill")
+        .args(["-SIGUSR2", "-x", "waybar"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if signalled {
+        println!("  ✓ Reloaded Waybar");
+    }
+
+    if !instances.is_empty() {
+        start_waybar_instances(instances);
+    }
+}
+
+/// Start the configured bars that no running waybar was started for, e.g. after one
+/// crashed. A bar is matched by the config path on its command line.
+fn start_waybar_instances(instances: &[WaybarInstance]) {
+    if find_in_path("waybar").is_none() {
+        return;
+    }
+
+    // `pgrep -a` prints "<pid> <command line>" for each running bar
+    let running: Vec<String> = Command::new("pgrep")
+        .args(["-a", "-x", "waybar"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for instance in instances {
+        let config = instance.config.as_deref().map(expand_path);
+        let is_running = running.iter().any(|command| match &config {
+            Some(config) => command.contains(&*config.to_string_lossy()),
+            None => !command.contains(" -c") && !command.contains(" --config"),
+        });
+        if is_running {
+            continue;
+        }
+
+        let mut command = Command::new("waybar");
+        if let Some(config) = &config {
+            command.arg("-c").arg(config);
+        }
+        if let Some(style) = &instance.style {
+            command.arg("-s").arg(expand_path(style));
+        }
+        let label = instance.config.as_deref().unwrap_or("default config");
+        match spawn_detached(command) {
+            Ok(()) => println!("  ✓ Started Waybar ({})", label),
+            Err(e) => println!("  ⚠ Failed to start Waybar ({}): {}", label, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn backgrounded_reload_commands_dont_block() {
+        let start = Instant::now();
+        run_reload_commands(&["sleep 5 &".to_string()]);
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+}
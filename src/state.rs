@@ -0,0 +1,219 @@
+use crate::{wallpaper::Effects, ColorScheme};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The most recently applied theme, used by --apply-last
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedState {
+    /// Wallpapers in monitor order
+    pub wallpapers: Vec<PathBuf>,
+    /// Explicit monitor list, if one was given
+    pub monitors: Option<String>,
+    /// Wallpapers given as `monitor:path`, which take precedence over `wallpapers`
+    #[serde(default)]
+    pub mapped: HashMap<String, PathBuf>,
+    /// Theme ("dark", "light", "auto") and palette style the scheme was made with
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub style: Option<String>,
+    /// Blur and dim the wallpapers were shown with
+    #[serde(default)]
+    pub effects: Effects,
+    pub scheme: ColorScheme,
+}
+
+impl AppliedState {
+    pub fn new(wallpapers: &[PathBuf], monitors: Option<String>, scheme: ColorScheme) -> Self {
+        let wallpapers = wallpapers.iter().map(|p| absolute(p)).collect();
+
+        Self {
+            wallpapers,
+            monitors,
+            mapped: HashMap::new(),
+            theme: None,
+            style: None,
+            effects: Effects::default(),
+            scheme,
+        }
+    }
+
+    /// Also remember the `monitor:path` wallpapers
+    pub fn with_mapped(mut self, mapped: &HashMap<String, PathBuf>) -> Self {
+        self.mapped = mapped
+            .iter()
+            .map(|(monitor, path)| (monitor.clone(), absolute(path)))
+            .collect();
+        self
+    }
+
+    /// Also remember the theme and palette style, for display on restore
+    pub fn with_theme(mut self, theme: &str, style: &str) -> Self {
+        self.theme = Some(theme.to_string());
+        self.style = Some(style.to_string());
+        self
+    }
+
+    /// Also remember the wallpaper blur and dim
+    pub fn with_effects(mut self, effects: Effects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    /// Load the last applied state, or None if iro hasn't applied anything yet
+    pub fn load() -> Result<Option<Self>> {
+        let state_path = Self::state_path()?;
+
+        if !state_path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&state_path).context("Failed to read state file")?;
+        let state: AppliedState = toml::from_str(&content).context("Failed to parse state file")?;
+
+        Ok(Some(state))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let state_path = Self::state_path()?;
+
+        if let Some(parent) = state_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+
+        let content = toml::to_string_pretty(self).context("Failed to serialize state")?;
+        std::fs::write(&state_path, content).context("Failed to write state file")?;
+
+        Ok(())
+    }
+
+    pub fn state_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+        Ok(config_dir.join("iro").join("state.toml"))
+    }
+}
+
+/// Store absolute paths so reapplying works from any directory
+fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Most entries kept in history.log
+const HISTORY_LIMIT: usize = 100;
+
+/// Wallpapers applied so far, oldest first, for --previous and --history
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<PathBuf>,
+}
+
+impl History {
+    /// Load history.log, empty if iro hasn't applied anything yet
+    pub fn load() -> Result<Self> {
+        let history_path = Self::history_path()?;
+
+        if !history_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content =
+            std::fs::read_to_string(&history_path).context("Failed to read history file")?;
+        let entries = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(PathBuf::from)
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    /// Applied wallpapers, oldest first
+    pub fn entries(&self) -> &[PathBuf] {
+        &self.entries
+    }
+
+    /// Record `wallpaper` as the latest entry, unless it already is
+    pub fn push(&mut self, wallpaper: &Path) {
+        let wallpaper = absolute(wallpaper);
+        if self.entries.last() != Some(&wallpaper) {
+            self.entries.push(wallpaper);
+        }
+    }
+
+    /// The entry before the latest one, which --previous goes back to
+    pub fn previous(&self) -> Option<&Path> {
+        let len = self.entries.len();
+        (len >= 2).then(|| self.entries[len - 2].as_path())
+    }
+
+    /// Drop the latest entry, so the next --previous goes back one further
+    pub fn rewind(&mut self) {
+        self.entries.pop();
+    }
+
+    /// Write history.log, keeping only the newest `HISTORY_LIMIT` entries
+    pub fn save(&self) -> Result<()> {
+        let history_path = Self::history_path()?;
+
+        if let Some(parent) = history_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+
+        let skip = self.entries.len().saturating_sub(HISTORY_LIMIT);
+        let content: String = self.entries[skip..]
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect();
+        std::fs::write(&history_path, content).context("Failed to write history file")?;
+
+        Ok(())
+    }
+
+    pub fn history_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+        Ok(config_dir.join("iro").join("history.log"))
+    }
+}
+
+/// Picker settings remembered between GUI sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuiState {
+    /// Theme mode last picked with the toggle, overriding theme.mode in the GUI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+impl GuiState {
+    /// Load gui.toml, empty if the GUI hasn't saved anything yet
+    pub fn load() -> Result<Self> {
+        let state_path = Self::state_path()?;
+
+        if !state_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content =
+            std::fs::read_to_string(&state_path).context("Failed to read GUI state file")?;
+        toml::from_str(&content).context("Failed to parse GUI state file")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let state_path = Self::state_path()?;
+
+        if let Some(parent) = state_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+
+        let content = toml::to_string_pretty(self).context("Failed to serialize GUI state")?;
+        std::fs::write(&state_path, content).context("Failed to write GUI state file")?;
+
+        Ok(())
+    }
+
+    pub fn state_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+        Ok(config_dir.join("iro").join("gui.toml"))
+    }
+}
@@ -1,9 +1,35 @@
+use crate::color_math::hex_to_rgb;
+use crate::config::is_read_only;
+use crate::config_generator::report;
 use crate::ColorScheme;
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use palette::{Hsl, IntoColor, Srgb};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Templates `create_default_templates` writes when they are missing
+pub const DEFAULT_TEMPLATES: [&str; 12] = [
+    "gtk-colors.css",
+    "gtk.css",
+    "waybar.css",
+    "kitty.conf",
+    "shell_colors.sh",
+    "shell_colors.fish",
+    "shell_colors.nu",
+    "rofi.rasi",
+    "quickshell-theme.qml",
+    "nvim.lua",
+    "styles.css",
+    "_iro.scss",
+];
 
 pub struct TemplateEngine {
     templates_dir: PathBuf,
+    /// Default templates that weren't written because the config dir is read-only
+    unwritten: Mutex<HashMap<&'static str, &'static str>>,
+    /// Name colors by their ANSI slot, for schemes extracted with `palette.semantic_ansi`
+    semantic_ansi: bool,
 }
 
 impl TemplateEngine {
@@ -14,9 +40,27 @@ impl TemplateEngine {
         let templates_dir = config_dir.join("templates");
 
         // Create templates directory if it doesn't exist
-        std::fs::create_dir_all(&templates_dir).context("Failed to create templates directory")?;
+        if !is_read_only() {
+            std::fs::create_dir_all(&templates_dir)
+                .context("Failed to create templates directory")?;
+        }
+
+        Ok(Self {
+            templates_dir,
+            unwritten: Mutex::new(HashMap::new()),
+            semantic_ansi: false,
+        })
+    }
 
-        Ok(Self { templates_dir })
+    /// Point `{{ red }}`, `{{ green }}` and the other named colors at the ANSI slots
+    /// `palette.semantic_ansi` fills instead of the hue-ordered ones
+    pub fn with_semantic_ansi(mut self, semantic_ansi: bool) -> Self {
+        self.semantic_ansi = semantic_ansi;
+        self
+    }
+
+    pub fn templates_dir(&self) -> &Path {
+        &self.templates_dir
     }
 
     pub fn render_template(
@@ -24,61 +68,294 @@ impl TemplateEngine {
         template_name: &str,
         color_scheme: &ColorScheme,
     ) -> Result<String> {
-        let template_path = self.templates_dir.join(template_name);
-        let mut rendered = std::fs::read_to_string(&template_path)
+        self.render_template_with_vars(template_name, color_scheme, &[])
+    }
+
+    /// Render a template with additional generator-specific variables
+    pub fn render_template_with_vars(
+        &self,
+        template_name: &str,
+        color_scheme: &ColorScheme,
+        extra_vars: &[(&str, &str)],
+    ) -> Result<String> {
+        let template = self
+            .read_template(template_name)
             .with_context(|| format!("Failed to read template: {}", template_name))?;
 
-        // Simple template variable replacement
-        rendered = rendered.replace("{{ background }}", &color_scheme.background);
-        rendered = rendered.replace("{{ foreground }}", &color_scheme.foreground);
-        rendered = rendered.replace("{{ accent }}", &color_scheme.accent);
-        rendered = rendered.replace("{{ secondary }}", &color_scheme.secondary);
-        rendered = rendered.replace("{{ surface }}", &color_scheme.surface);
-        rendered = rendered.replace("{{ error }}", &color_scheme.error);
-
-        // Individual colors - use direct indexing to avoid repeated lookups
-        for i in 0..color_scheme.colors.len() {
-            if let Some(color) = color_scheme.colors.get(i) {
-                rendered = rendered.replace(&format!("{{{{ colors.{} }}}}", i), color);
-            }
+        // Pull in {{> partial }} includes before substituting variables
+        let rendered = self.resolve_partials(&template, &mut vec![template_name.to_string()])?;
+
+        let mut vars: Vec<(String, &str)> = vec![
+            ("background".to_string(), &color_scheme.background),
+            ("foreground".to_string(), &color_scheme.foreground),
+            ("accent".to_string(), &color_scheme.accent),
+            ("secondary".to_string(), &color_scheme.secondary),
+            ("surface".to_string(), &color_scheme.surface),
+            ("error".to_string(), &color_scheme.error),
+        ];
+
+        for (name, value) in extra_vars {
+            vars.push((name.to_string(), value));
+        }
+
+        // Individual colors
+        for (i, color) in color_scheme.colors.iter().enumerate() {
+            vars.push((format!("colors.{}", i), color));
         }
 
         // Named colors for convenience - matches color_extractor.rs hue order:
         // 1=red(345-30°), 2=yellow(30-90°), 3=green(90-150°), 4=cyan(150-210°), 5=blue(210-270°), 6=magenta(270-345°)
-        let named_colors = [
-            ("{{ red }}", 1),
-            ("{{ yellow }}", 2),
-            ("{{ green }}", 3),
-            ("{{ cyan }}", 4),
-            ("{{ blue }}", 5),
-            ("{{ magenta }}", 6),
-            ("{{ white }}", 7),
-        ];
+        // or, with semantic_ansi, the ANSI slots: 1=red, 2=green, 3=yellow, 4=blue, 5=magenta, 6=cyan
+        let named_colors = if self.semantic_ansi {
+            [
+                ("red", 1),
+                ("green", 2),
+                ("yellow", 3),
+                ("blue", 4),
+                ("magenta", 5),
+                ("cyan", 6),
+                ("white", 7),
+            ]
+        } else {
+            [
+                ("red", 1),
+                ("yellow", 2),
+                ("green", 3),
+                ("cyan", 4),
+                ("blue", 5),
+                ("magenta", 6),
+                ("white", 7),
+            ]
+        };
 
         for (name, idx) in &named_colors {
             if let Some(color) = color_scheme.colors.get(*idx) {
-                rendered = rendered.replace(name, color);
+                vars.push((name.to_string(), color));
             }
         }
 
-        Ok(rendered)
+        Self::substitute_variables(template_name, &rendered, &vars)
+            .with_context(|| format!("Failed to render template: {}", template_name))
+    }
+
+    /// Replace every `{{ name | filter | ... }}` with its value. Unknown names are
+    /// reported and replaced with an empty string so the rest of the file still renders.
+    fn substitute_variables(
+        template_name: &str,
+        content: &str,
+        vars: &[(String, &str)],
+    ) -> Result<String> {
+        let mut output = String::with_capacity(content.len());
+        let mut rest = content;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+
+            let token = &rest[start..start + end + 2];
+            let mut parts = token[2..token.len() - 2].split('|').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+
+            output.push_str(&rest[..start]);
+            match vars.iter().find(|(var, _)| var == name) {
+                Some((_, value)) => {
+                    let mut value = value.to_string();
+                    for filter in parts {
+                        value = Self::apply_filter(&value, filter)
+                            .with_context(|| format!("in '{}'", token))?;
+                    }
+                    output.push_str(&value);
+                }
+                None => report!(
+                    "  ⚠ {}: unknown variable '{}', leaving it empty",
+                    template_name,
+                    name
+                ),
+            }
+            rest = &rest[start + end + 2..];
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Apply a single template filter such as `upper` or `lighten:20` to a value
+    fn apply_filter(value: &str, filter: &str) -> Result<String> {
+        let (name, arg) = match filter.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (filter, None),
+        };
+
+        match (name, arg) {
+            ("upper", None) => Ok(value.to_uppercase()),
+            ("lower", None) => Ok(value.to_lowercase()),
+            ("no_hash", None) => Ok(value.trim_start_matches('#').to_string()),
+            ("rgb", None) => {
+                let rgb = hex_to_rgb(value)
+                    .with_context(|| format!("Filter 'rgb' needs a hex color, got '{}'", value))?;
+                Ok(format!("{}, {}, {}", rgb[0], rgb[1], rgb[2]))
+            }
+            ("lighten", Some(amount)) | ("darken", Some(amount)) => {
+                let amount: f32 = amount
+                    .parse()
+                    .with_context(|| format!("Invalid amount for '{}': {}", name, amount))?;
+                let amount = if name == "darken" { -amount } else { amount };
+                let rgb = hex_to_rgb(value).with_context(|| {
+                    format!("Filter '{}' needs a hex color, got '{}'", name, value)
+                })?;
+
+                let srgb = Srgb::new(rgb[0], rgb[1], rgb[2]).into_format::<f32>();
+                let mut hsl: Hsl = srgb.into_color();
+                hsl.lightness = (hsl.lightness + amount / 100.0).clamp(0.0, 1.0);
+                let out: Srgb = hsl.into_color();
+                let out = out.into_format::<u8>();
+                Ok(format!("#{:02x}{:02x}{:02x}", out.red, out.green, out.blue))
+            }
+            _ => anyhow::bail!("Unknown template filter '{}'", filter),
+        }
+    }
+
+    /// Replace `{{> name }}` with the contents of `name` from the templates directory,
+    /// recursively. `stack` holds the chain of templates being rendered to catch cycles.
+    fn resolve_partials(&self, content: &str, stack: &mut Vec<String>) -> Result<String> {
+        let mut output = String::with_capacity(content.len());
+        let mut rest = content;
+
+        while let Some(start) = rest.find("{{>") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+
+            let name = rest[start + 3..start + end].trim();
+            if stack.iter().any(|s| s == name) {
+                anyhow::bail!("Template include cycle: {} -> {}", stack.join(" -> "), name);
+            }
+
+            let partial = self.read_template(name).with_context(|| {
+                format!(
+                    "Failed to read partial '{}' included from {}",
+                    name,
+                    stack.join(" -> ")
+                )
+            })?;
+
+            stack.push(name.to_string());
+            let resolved = self.resolve_partials(&partial, stack)?;
+            stack.pop();
+
+            output.push_str(&rest[..start]);
+            output.push_str(resolved.trim_end_matches('\n'));
+            rest = &rest[start + end + 2..];
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// `name` from the templates directory, or the default that would have been
+    /// written there
+    fn read_template(&self, name: &str) -> std::io::Result<String> {
+        match std::fs::read_to_string(self.templates_dir.join(name)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let unwritten = self.unwritten.lock().unwrap_or_else(|e| e.into_inner());
+                unwritten
+                    .get(name)
+                    .map(|template| template.to_string())
+                    .ok_or(e)
+            }
+            result => result,
+        }
+    }
+
+    /// Write the default template `name` unless it exists. While the config dir is
+    /// read-only it's kept in memory instead, so dry runs still render it.
+    fn install(&self, name: &'static str, content: &'static str, label: &str) -> Result<()> {
+        let template_path = self.templates_dir.join(name);
+        if template_path.exists() {
+            return Ok(()); // Don't overwrite existing templates
+        }
+
+        if is_read_only() {
+            let mut unwritten = self.unwritten.lock().unwrap_or_else(|e| e.into_inner());
+            unwritten.insert(name, content);
+            return Ok(());
+        }
+
+        std::fs::write(&template_path, content)
+            .with_context(|| format!("Failed to write {}", label))?;
+        println!("  ✓ Created {}", label);
+        Ok(())
     }
 
     pub fn create_default_templates(&self) -> Result<()> {
+        self.create_gtk_colors_partial()?;
+        self.create_gtk_template()?;
         self.create_waybar_template()?;
         self.create_kitty_template()?;
         self.create_shell_colors_template()?;
+        self.create_fish_colors_template()?;
+        self.create_nushell_colors_template()?;
         self.create_rofi_template()?;
         self.create_quickshell_template()?;
+        self.create_css_vars_template()?;
+        self.create_scss_vars_template()?;
+        self.create_nvim_template()?;
         Ok(())
     }
 
-    fn create_waybar_template(&self) -> Result<()> {
-        let template_path = self.templates_dir.join("waybar.css");
-        if template_path.exists() {
-            return Ok(()); // Don't overwrite existing templates
-        }
+    fn create_gtk_colors_partial(&self) -> Result<()> {
+        // Shared @define-color block, included from other templates with {{> gtk-colors.css }}
+        let template_content = r#"@define-color red {{ red }};
+@define-color blue {{ blue }};
+@define-color yellow {{ yellow }};
+@define-color magenta {{ magenta }};
+@define-color accent {{ accent }};
+@define-color secondary {{ secondary }};
+@define-color text {{ foreground }};
+@define-color surface {{ surface }};
+@define-color base {{ background }};
+@define-color error {{ error }};
+"#;
+
+        self.install("gtk-colors.css", template_content, "gtk-colors partial")
+    }
+
+    fn create_gtk_template(&self) -> Result<()> {
+        // Named colors libadwaita and GTK 3 themes look up, rendered to colors.css
+        let template_content = r#"/* GTK Colors - Generated by iro */
+/* Load with @import 'colors.css'; at the top of gtk.css in gtk-3.0 and gtk-4.0 */
+
+/* libadwaita */
+@define-color accent_color {{ accent }};
+@define-color accent_bg_color {{ accent }};
+@define-color accent_fg_color {{ background }};
+@define-color window_bg_color {{ background }};
+@define-color window_fg_color {{ foreground }};
+@define-color view_bg_color {{ background }};
+@define-color view_fg_color {{ foreground }};
+@define-color headerbar_bg_color {{ surface }};
+@define-color headerbar_fg_color {{ foreground }};
+@define-color card_bg_color {{ surface }};
+@define-color card_fg_color {{ foreground }};
+@define-color popover_bg_color {{ surface }};
+@define-color popover_fg_color {{ foreground }};
+@define-color destructive_bg_color {{ error }};
+@define-color destructive_fg_color {{ background }};
+
+/* gtk 3 */
+@define-color theme_bg_color {{ background }};
+@define-color theme_fg_color {{ foreground }};
+@define-color theme_base_color {{ background }};
+@define-color theme_text_color {{ foreground }};
+@define-color theme_selected_bg_color {{ accent }};
+@define-color theme_selected_fg_color {{ background }};
+"#;
+
+        self.install("gtk.css", template_content, "GTK template")
+    }
 
+    fn create_waybar_template(&self) -> Result<()> {
         let template_content = r#"/* Dynamic Waybar Theme - Generated by iro */
 
 * {
@@ -92,16 +369,7 @@ impl TemplateEngine {
 }
 
 /* Dynamic Color Variables */
-@define-color red {{ red }};
-@define-color blue {{ blue }};
-@define-color yellow {{ yellow }};
-@define-color magenta {{ magenta }};
-@define-color accent {{ accent }};
-@define-color secondary {{ secondary }};
-@define-color text {{ foreground }};
-@define-color surface {{ surface }};
-@define-color base {{ background }};
-@define-color error {{ error }};
+{{> gtk-colors.css }}
 
 /* Main Window */
 window#waybar {
@@ -111,7 +379,7 @@ window#waybar {
 
 /* Base module styling */
 .module {
-  background: alpha(@base, 0.9);
+  background: {{ module_background }};
   color: @text;
   margin: 2px 3px;
   padding: 6px 14px;
@@ -229,18 +497,10 @@ window#waybar {
 }
 "#;
 
-        std::fs::write(&template_path, template_content)
-            .context("Failed to write waybar template")?;
-        println!("  ✓ Created waybar template");
-        Ok(())
+        self.install("waybar.css", template_content, "waybar template")
     }
 
     fn create_kitty_template(&self) -> Result<()> {
-        let template_path = self.templates_dir.join("kitty.conf");
-        if template_path.exists() {
-            return Ok(()); // Don't overwrite existing templates
-        }
-
         let template_content = "# Dynamic Kitty Theme - Generated by iro
 
 # Background and foreground
@@ -283,18 +543,10 @@ inactive_border_color {{ surface }}
 bell_border_color     {{ error }}
 ";
 
-        std::fs::write(&template_path, template_content)
-            .context("Failed to write kitty template")?;
-        println!("  ✓ Created kitty template");
-        Ok(())
+        self.install("kitty.conf", template_content, "kitty template")
     }
 
     fn create_shell_colors_template(&self) -> Result<()> {
-        let template_path = self.templates_dir.join("shell_colors.sh");
-        if template_path.exists() {
-            return Ok(()); // Don't overwrite existing templates
-        }
-
         let template_content = r#"#!/bin/bash
 # Dynamic shell color exports - Generated by iro
 # Source this file in your .bashrc or .zshrc to apply colors to prompts
@@ -332,18 +584,201 @@ export SPACESHIP_EXIT_CODE_COLOR="{{ error }}"
 export FZF_DEFAULT_OPTS="--color=bg+:{{ surface }},bg:{{ background }},spinner:{{ accent }},hl:{{ colors.4 }},fg:{{ foreground }},header:{{ colors.4 }},info:{{ colors.3 }},pointer:{{ accent }},marker:{{ accent }},fg+:{{ foreground }},prompt:{{ colors.5 }},hl+:{{ colors.4 }}"
 "#;
 
-        std::fs::write(&template_path, template_content)
-            .context("Failed to write shell_colors template")?;
-        println!("  ✓ Created shell_colors template");
-        Ok(())
+        self.install("shell_colors.sh", template_content, "shell_colors template")
     }
 
-    fn create_rofi_template(&self) -> Result<()> {
-        let template_path = self.templates_dir.join("rofi.rasi");
-        if template_path.exists() {
-            return Ok(()); // Don't overwrite existing templates
-        }
+    fn create_fish_colors_template(&self) -> Result<()> {
+        let template_content = r#"# Dynamic shell color exports - Generated by iro
+# Source this file in ~/.config/fish/config.fish to apply colors to prompts
+
+# Base16 color exports
+set -gx BASE16_COLOR_00 "{{ colors.0 }}"
+set -gx BASE16_COLOR_01 "{{ colors.1 }}"
+set -gx BASE16_COLOR_02 "{{ colors.2 }}"
+set -gx BASE16_COLOR_03 "{{ colors.3 }}"
+set -gx BASE16_COLOR_04 "{{ colors.4 }}"
+set -gx BASE16_COLOR_05 "{{ colors.5 }}"
+set -gx BASE16_COLOR_06 "{{ colors.6 }}"
+set -gx BASE16_COLOR_07 "{{ colors.7 }}"
+set -gx BASE16_COLOR_08 "{{ colors.8 }}"
+set -gx BASE16_COLOR_09 "{{ colors.9 }}"
+set -gx BASE16_COLOR_0A "{{ colors.10 }}"
+set -gx BASE16_COLOR_0B "{{ colors.11 }}"
+set -gx BASE16_COLOR_0C "{{ colors.12 }}"
+set -gx BASE16_COLOR_0D "{{ colors.13 }}"
+set -gx BASE16_COLOR_0E "{{ colors.14 }}"
+set -gx BASE16_COLOR_0F "{{ colors.15 }}"
+
+# Syntax highlighting
+set -g fish_color_normal {{ foreground | no_hash }}
+set -g fish_color_command {{ accent | no_hash }}
+set -g fish_color_param {{ colors.6 | no_hash }}
+set -g fish_color_quote {{ colors.2 | no_hash }}
+set -g fish_color_error {{ error | no_hash }}
+set -g fish_color_comment {{ colors.8 | no_hash }}
+set -g fish_color_autosuggestion {{ colors.8 | no_hash }}
+
+# FZF colors
+set -gx FZF_DEFAULT_OPTS "--color=bg+:{{ surface }},bg:{{ background }},spinner:{{ accent }},hl:{{ colors.4 }},fg:{{ foreground }},header:{{ colors.4 }},info:{{ colors.3 }},pointer:{{ accent }},marker:{{ accent }},fg+:{{ foreground }},prompt:{{ colors.5 }},hl+:{{ colors.4 }}"
+"#;
+
+        self.install(
+            "shell_colors.fish",
+            template_content,
+            "fish colors template",
+        )
+    }
+
+    fn create_nushell_colors_template(&self) -> Result<()> {
+        let template_content = r#"# Dynamic shell color exports - Generated by iro
+# Source this file in your nushell env.nu to apply colors to prompts
+
+# Base16 color exports
+$env.BASE16_COLOR_00 = "{{ colors.0 }}"
+$env.BASE16_COLOR_01 = "{{ colors.1 }}"
+$env.BASE16_COLOR_02 = "{{ colors.2 }}"
+$env.BASE16_COLOR_03 = "{{ colors.3 }}"
+$env.BASE16_COLOR_04 = "{{ colors.4 }}"
+$env.BASE16_COLOR_05 = "{{ colors.5 }}"
+$env.BASE16_COLOR_06 = "{{ colors.6 }}"
+$env.BASE16_COLOR_07 = "{{ colors.7 }}"
+$env.BASE16_COLOR_08 = "{{ colors.8 }}"
+$env.BASE16_COLOR_09 = "{{ colors.9 }}"
+$env.BASE16_COLOR_0A = "{{ colors.10 }}"
+$env.BASE16_COLOR_0B = "{{ colors.11 }}"
+$env.BASE16_COLOR_0C = "{{ colors.12 }}"
+$env.BASE16_COLOR_0D = "{{ colors.13 }}"
+$env.BASE16_COLOR_0E = "{{ colors.14 }}"
+$env.BASE16_COLOR_0F = "{{ colors.15 }}"
+
+# FZF colors
+$env.FZF_DEFAULT_OPTS = "--color=bg+:{{ surface }},bg:{{ background }},spinner:{{ accent }},hl:{{ colors.4 }},fg:{{ foreground }},header:{{ colors.4 }},info:{{ colors.3 }},pointer:{{ accent }},marker:{{ accent }},fg+:{{ foreground }},prompt:{{ colors.5 }},hl+:{{ colors.4 }}"
+"#;
+
+        self.install(
+            "shell_colors.nu",
+            template_content,
+            "nushell colors template",
+        )
+    }
+
+    fn create_nvim_template(&self) -> Result<()> {
+        let template_content = r#"-- Dynamic color scheme - Generated by iro
+-- Load it with: local colors = require('iro_colors')
+
+-- Match :terminal buffers to the rest of the desktop
+vim.g.terminal_color_0 = "{{ colors.0 }}"
+vim.g.terminal_color_1 = "{{ colors.1 }}"
+vim.g.terminal_color_2 = "{{ colors.2 }}"
+vim.g.terminal_color_3 = "{{ colors.3 }}"
+vim.g.terminal_color_4 = "{{ colors.4 }}"
+vim.g.terminal_color_5 = "{{ colors.5 }}"
+vim.g.terminal_color_6 = "{{ colors.6 }}"
+vim.g.terminal_color_7 = "{{ colors.7 }}"
+vim.g.terminal_color_8 = "{{ colors.8 }}"
+vim.g.terminal_color_9 = "{{ colors.9 }}"
+vim.g.terminal_color_10 = "{{ colors.10 }}"
+vim.g.terminal_color_11 = "{{ colors.11 }}"
+vim.g.terminal_color_12 = "{{ colors.12 }}"
+vim.g.terminal_color_13 = "{{ colors.13 }}"
+vim.g.terminal_color_14 = "{{ colors.14 }}"
+vim.g.terminal_color_15 = "{{ colors.15 }}"
+
+return {
+  background = "{{ background }}",
+  foreground = "{{ foreground }}",
+  accent = "{{ accent }}",
+  secondary = "{{ secondary }}",
+  surface = "{{ surface }}",
+  error = "{{ error }}",
+  -- Terminal colors, colors[1] is color0 since Lua tables start at 1
+  colors = {
+    "{{ colors.0 }}",
+    "{{ colors.1 }}",
+    "{{ colors.2 }}",
+    "{{ colors.3 }}",
+    "{{ colors.4 }}",
+    "{{ colors.5 }}",
+    "{{ colors.6 }}",
+    "{{ colors.7 }}",
+    "{{ colors.8 }}",
+    "{{ colors.9 }}",
+    "{{ colors.10 }}",
+    "{{ colors.11 }}",
+    "{{ colors.12 }}",
+    "{{ colors.13 }}",
+    "{{ colors.14 }}",
+    "{{ colors.15 }}",
+  },
+}
+"#;
+
+        self.install("nvim.lua", template_content, "nvim template")
+    }
+
+    fn create_css_vars_template(&self) -> Result<()> {
+        let template_content = r#"/* Dynamic color variables - Generated by iro */
+:root {
+  --iro-bg: {{ background }};
+  --iro-fg: {{ foreground }};
+  --iro-accent: {{ accent }};
+  --iro-secondary: {{ secondary }};
+  --iro-surface: {{ surface }};
+  --iro-error: {{ error }};
+  --iro-color0: {{ colors.0 }};
+  --iro-color1: {{ colors.1 }};
+  --iro-color2: {{ colors.2 }};
+  --iro-color3: {{ colors.3 }};
+  --iro-color4: {{ colors.4 }};
+  --iro-color5: {{ colors.5 }};
+  --iro-color6: {{ colors.6 }};
+  --iro-color7: {{ colors.7 }};
+  --iro-color8: {{ colors.8 }};
+  --iro-color9: {{ colors.9 }};
+  --iro-color10: {{ colors.10 }};
+  --iro-color11: {{ colors.11 }};
+  --iro-color12: {{ colors.12 }};
+  --iro-color13: {{ colors.13 }};
+  --iro-color14: {{ colors.14 }};
+  --iro-color15: {{ colors.15 }};
+}
+"#;
+
+        self.install("styles.css", template_content, "CSS variables template")
+    }
+
+    fn create_scss_vars_template(&self) -> Result<()> {
+        let template_content = r#"// Dynamic color variables - Generated by iro
+// Use it with: @use "iro" as *;
+
+$iro-bg: {{ background }};
+$iro-fg: {{ foreground }};
+$iro-accent: {{ accent }};
+$iro-secondary: {{ secondary }};
+$iro-surface: {{ surface }};
+$iro-error: {{ error }};
+$iro-color0: {{ colors.0 }};
+$iro-color1: {{ colors.1 }};
+$iro-color2: {{ colors.2 }};
+$iro-color3: {{ colors.3 }};
+$iro-color4: {{ colors.4 }};
+$iro-color5: {{ colors.5 }};
+$iro-color6: {{ colors.6 }};
+$iro-color7: {{ colors.7 }};
+$iro-color8: {{ colors.8 }};
+$iro-color9: {{ colors.9 }};
+$iro-color10: {{ colors.10 }};
+$iro-color11: {{ colors.11 }};
+$iro-color12: {{ colors.12 }};
+$iro-color13: {{ colors.13 }};
+$iro-color14: {{ colors.14 }};
+$iro-color15: {{ colors.15 }};
+"#;
+
+        self.install("_iro.scss", template_content, "SCSS variables template")
+    }
 
+    fn create_rofi_template(&self) -> Result<()> {
         let template_content = r#"/* ═══════════════════════════════════════════════════════════════════ */
 /* DYNAMIC COLOR SCHEME - Generated by iro                              */
 /* ═══════════════════════════════════════════════════════════════════ */
@@ -387,18 +822,10 @@ export FZF_DEFAULT_OPTS="--color=bg+:{{ surface }},bg:{{ background }},spinner:{
 }
 "#;
 
-        std::fs::write(&template_path, template_content)
-            .context("Failed to write rofi template")?;
-        println!("  ✓ Created rofi template");
-        Ok(())
+        self.install("rofi.rasi", template_content, "rofi template")
     }
 
     fn create_quickshell_template(&self) -> Result<()> {
-        let template_path = self.templates_dir.join("quickshell-theme.qml");
-        if template_path.exists() {
-            return Ok(()); // Don't overwrite existing templates
-        }
-
         let template_content = r#"pragma Singleton
 import QtQuick
 
@@ -438,9 +865,207 @@ QtObject {
 }
 "#;
 
-        std::fs::write(&template_path, template_content)
-            .context("Failed to write quickshell template")?;
-        println!("  ✓ Created quickshell template");
-        Ok(())
+        self.install(
+            "quickshell-theme.qml",
+            template_content,
+            "quickshell template",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_generator::captured;
+
+    /// An engine reading `templates` from its own directory under the temp dir
+    fn with_templates(name: &str, templates: &[(&str, &str)]) -> TemplateEngine {
+        let dir =
+            std::env::temp_dir().join(format!("iro-templates-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in templates {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        TemplateEngine {
+            templates_dir: dir,
+            unwritten: Mutex::new(HashMap::new()),
+            semantic_ansi: false,
+        }
+    }
+
+    /// `name` with its partials pulled in, the directory removed afterwards
+    fn resolve(engine: TemplateEngine, name: &str) -> Result<String> {
+        let template = engine.read_template(name).unwrap();
+        let resolved = engine.resolve_partials(&template, &mut vec![name.to_string()]);
+        std::fs::remove_dir_all(engine.templates_dir()).unwrap();
+        resolved
+    }
+
+    #[test]
+    fn nested_partials_are_included() {
+        let engine = with_templates(
+            "nested",
+            &[
+                ("main.css", "/* main */\n{{> colors.css }}\nbody {}\n"),
+                (
+                    "colors.css",
+                    "{{> base.css }}\n@define-color accent #7aa2f7;\n",
+                ),
+                ("base.css", "@define-color base #1a1b26;\n"),
+            ],
+        );
+        assert_eq!(
+            resolve(engine, "main.css").unwrap(),
+            "/* main */\n\
+             @define-color base #1a1b26;\n\
+             @define-color accent #7aa2f7;\n\
+             body {}\n"
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let engine = with_templates("self-cycle", &[("loop.css", "a\n{{> loop.css }}\n")]);
+        assert_eq!(
+            resolve(engine, "loop.css").unwrap_err().to_string(),
+            "Template include cycle: loop.css -> loop.css"
+        );
+
+        let engine = with_templates(
+            "cycle",
+            &[
+                ("a.css", "{{> b.css }}\n"),
+                ("b.css", "{{> c.css }}\n"),
+                ("c.css", "{{> a.css }}\n"),
+            ],
+        );
+        assert_eq!(
+            resolve(engine, "a.css").unwrap_err().to_string(),
+            "Template include cycle: a.css -> b.css -> c.css -> a.css"
+        );
+    }
+
+    #[test]
+    fn missing_partials_are_errors() {
+        let engine = with_templates("missing", &[("main.css", "{{> missing.css }}\n")]);
+        assert_eq!(
+            resolve(engine, "main.css").unwrap_err().to_string(),
+            "Failed to read partial 'missing.css' included from main.css"
+        );
+    }
+
+    fn filter(value: &str, filter: &str) -> String {
+        TemplateEngine::apply_filter(value, filter).unwrap()
+    }
+
+    fn filter_error(value: &str, filter: &str) -> String {
+        TemplateEngine::apply_filter(value, filter)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn filters_transform_values() {
+        assert_eq!(filter("#7aa2f7", "upper"), "#7AA2F7");
+        assert_eq!(filter("#7AA2F7", "lower"), "#7aa2f7");
+        assert_eq!(filter("#7aa2f7", "no_hash"), "7aa2f7");
+        assert_eq!(filter("#7aa2f7", "rgb"), "122, 162, 247");
+        assert_eq!(filter("#808080", "lighten:20"), "#b3b3b3");
+        assert_eq!(filter("#808080", "darken:20"), "#4d4d4d");
+        assert_eq!(
+            filter("#808080", "lighten: 20"),
+            filter("#808080", "lighten:20")
+        );
+    }
+
+    #[test]
+    fn filters_chain_left_to_right() {
+        let vars = [("accent".to_string(), "#7aa2f7")];
+        let rendered = TemplateEngine::substitute_variables(
+            "test",
+            "{{ accent | no_hash | upper }} {{ accent|darken:10|no_hash }}",
+            &vars,
+        )
+        .unwrap();
+        assert_eq!(rendered, "7AA2F7 4a80f4");
+    }
+
+    #[test]
+    fn bad_filters_are_errors() {
+        assert_eq!(
+            filter_error("#7aa2f7", "lighten:lots"),
+            "Invalid amount for 'lighten': lots"
+        );
+        assert_eq!(
+            filter_error("not-a-color", "rgb"),
+            "Filter 'rgb' needs a hex color, got 'not-a-color'"
+        );
+        assert_eq!(
+            filter_error("not-a-color", "darken:20"),
+            "Filter 'darken' needs a hex color, got 'not-a-color'"
+        );
+        assert_eq!(
+            filter_error("#7aa2f7", "sparkle"),
+            "Unknown template filter 'sparkle'"
+        );
+        // Filters that take no argument don't accept one
+        assert_eq!(
+            filter_error("#7aa2f7", "upper:2"),
+            "Unknown template filter 'upper:2'"
+        );
+    }
+
+    #[test]
+    fn unknown_variables_render_empty_with_a_warning() {
+        let vars = [
+            ("accent".to_string(), "#7aa2f7"),
+            ("secondary".to_string(), "#bb9af7"),
+        ];
+        let (rendered, output) = captured(|| {
+            TemplateEngine::substitute_variables(
+                "waybar.css",
+                "@define-color secondary {{ secondayr }};\n@define-color accent {{ accent }};\n",
+                &vars,
+            )
+        });
+        assert_eq!(
+            rendered.unwrap(),
+            "@define-color secondary ;\n@define-color accent #7aa2f7;\n"
+        );
+        assert_eq!(
+            output,
+            "  ⚠ waybar.css: unknown variable 'secondayr', leaving it empty\n"
+        );
+    }
+
+    #[test]
+    fn named_colors_follow_the_slot_ordering() {
+        let scheme = ColorScheme {
+            background: "#000000".to_string(),
+            foreground: "#ffffff".to_string(),
+            colors: (0..16).map(|i| format!("#0000{:02x}", i)).collect(),
+            accent: "#7aa2f7".to_string(),
+            secondary: "#bb9af7".to_string(),
+            surface: "#1a1b26".to_string(),
+            error: "#f7768e".to_string(),
+        };
+        let template = "{{ red }} {{ green }} {{ yellow }} {{ blue }} {{ magenta }} {{ cyan }}";
+        let render = |semantic_ansi| {
+            let engine = with_templates("named", &[("named.txt", template)])
+                .with_semantic_ansi(semantic_ansi);
+            let rendered = engine.render_template("named.txt", &scheme).unwrap();
+            std::fs::remove_dir_all(engine.templates_dir()).unwrap();
+            rendered
+        };
+
+        assert_eq!(
+            render(false),
+            "#000001 #000003 #000002 #000005 #000006 #000004"
+        );
+        assert_eq!(
+            render(true),
+            "#000001 #000002 #000003 #000004 #000005 #000006"
+        );
     }
 }
@@ -0,0 +1,91 @@
+use crate::color_math::hex_to_rgb;
+use crate::ColorScheme;
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--no-color`
+static COLOR_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn ANSI colors off for the rest of the run
+pub fn disable_color() {
+    COLOR_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether terminal output may use ANSI color escapes. Off with `--no-color`, when
+/// `NO_COLOR` is set to anything non-empty (https://no-color.org) or stdout isn't a
+/// terminal.
+pub fn should_colorize() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !COLOR_DISABLED.load(Ordering::Relaxed) && !no_color && std::io::stdout().is_terminal()
+}
+
+/// A small truecolor block showing `hex`, followed by a space. Empty when colors are
+/// off or `hex` isn't a valid color, so callers can always prepend it.
+pub fn swatch(hex: &str) -> String {
+    match hex_to_rgb(hex) {
+        Ok(rgb) if should_colorize() => {
+            format!("\x1b[48;2;{};{};{}m  \x1b[0m ", rgb[0], rgb[1], rgb[2])
+        }
+        _ => String::new(),
+    }
+}
+
+/// OSC 4 for the 16 palette colors, then OSC 10/11 for the foreground and background
+pub fn osc_sequences(scheme: &ColorScheme) -> String {
+    let mut output = String::with_capacity(16 * 20 + 40);
+    for (i, color) in scheme.colors.iter().take(16).enumerate() {
+        output.push_str(&format!("\x1b]4;{};{}\x1b\\", i, color));
+    }
+    output.push_str(&format!("\x1b]10;{}\x1b\\", scheme.foreground));
+    output.push_str(&format!("\x1b]11;{}\x1b\\", scheme.background));
+    output
+}
+
+/// Send `osc_sequences` to every pseudo terminal in /dev/pts owned by the current
+/// user, so open terminals switch colors without a restart. Returns how many took
+/// the sequences; terminals we can't open are skipped.
+#[cfg(unix)]
+pub fn recolor_open_terminals(scheme: &ColorScheme) -> Result<usize> {
+    use anyhow::Context;
+    use std::io::Write;
+    use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+
+    // /proc/self belongs to our effective uid, which owns the ptys we opened
+    let uid = std::fs::metadata("/proc/self")
+        .context("Failed to read /proc/self")?
+        .uid();
+    let sequences = osc_sequences(scheme);
+
+    let mut updated = 0;
+    for entry in std::fs::read_dir("/dev/pts")
+        .context("Failed to read /dev/pts")?
+        .flatten()
+    {
+        // ptmx is the multiplexer, only the numbered entries are terminals
+        let is_pty = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()));
+        if !is_pty || !entry.metadata().is_ok_and(|m| m.uid() == uid) {
+            continue;
+        }
+
+        // Without O_NOCTTY, iro run without a terminal (from a keybind or the gui)
+        // would make the first pty it opens its controlling terminal
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(entry.path())
+            .and_then(|mut pty| pty.write_all(sequences.as_bytes()));
+        if written.is_ok() {
+            updated += 1;
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(not(unix))]
+pub fn recolor_open_terminals(_scheme: &ColorScheme) -> Result<usize> {
+    anyhow::bail!("Recoloring open terminals needs /dev/pts")
+}
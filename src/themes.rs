@@ -0,0 +1,70 @@
+use crate::ColorScheme;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// Save `scheme` as `~/.config/iro/themes/<name>.toml`, replacing any theme of that name
+pub fn save(name: &str, scheme: &ColorScheme) -> Result<PathBuf> {
+    let path = theme_path(name)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create themes directory")?;
+    }
+
+    std::fs::write(&path, scheme.to_toml()?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+pub fn load(name: &str) -> Result<ColorScheme> {
+    let path = theme_path(name)?;
+
+    if !path.exists() {
+        anyhow::bail!(
+            "No saved theme '{}'. Save one with --save-theme {} or see --list-themes",
+            name,
+            name
+        );
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    ColorScheme::from_toml(&content).with_context(|| format!("Invalid theme {}", path.display()))
+}
+
+/// Names of the saved themes, sorted
+pub fn list() -> Result<Vec<String>> {
+    let dir = themes_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn themes_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+    Ok(config_dir.join("iro").join("themes"))
+}
+
+/// Names become file names, so they're limited like profile names
+fn theme_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Invalid theme name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(themes_dir()?.join(format!("{}.toml", name)))
+}
@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+/// Where `binary` would run from, searching `PATH` like a shell does
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,784 @@
+use crate::cache::file_key;
+use crate::color_extractor::is_wallpaper_file;
+use crate::config::{WallpaperBackend, WallpaperConfig, WallpaperMode};
+use crate::util::find_in_path;
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Processed wallpapers kept in ~/.cache/iro/processed, the least recently used go
+/// first
+const KEEP_PROCESSED: usize = 16;
+
+/// Processing applied to a wallpaper before it is shown, to keep widgets readable.
+/// Colors are always extracted from the original.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Effects {
+    /// Blur sigma in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blur: Option<f32>,
+    /// How much darker to make it, 0.0 (unchanged) to 1.0 (black)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dim: Option<f32>,
+}
+
+impl Effects {
+    pub fn is_none(&self) -> bool {
+        self.blur.is_none() && self.dim.is_none()
+    }
+
+    /// Processed copy of `wallpaper` in ~/.cache/iro/processed, made on first use and
+    /// reused while the source file and settings stay the same
+    pub fn apply(&self, wallpaper: &Path) -> Result<PathBuf> {
+        let processed_dir = dirs::cache_dir()
+            .context("Failed to get cache directory")?
+            .join("iro")
+            .join("processed");
+        // Keep the original name in front so the file is recognizable
+        let stem = wallpaper.file_stem().unwrap_or_default().to_string_lossy();
+        // Bit patterns, so every distinct setting gets its own file
+        let settings = [self.blur, self.dim]
+            .map(|value| value.map_or("-".to_string(), |value| format!("{:08x}", value.to_bits())))
+            .join(" ");
+        let key = file_key(wallpaper, &settings)?;
+        let processed_path = processed_dir.join(format!("{}-{}.jpg", stem, key));
+        if processed_path.exists() {
+            // Mark it used, so pruning keeps it
+            if let Ok(file) = std::fs::File::options().write(true).open(&processed_path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            return Ok(processed_path);
+        }
+
+        let mut img = image::ImageReader::open(wallpaper)
+            .with_context(|| format!("Failed to open image {}", wallpaper.display()))?
+            .with_guessed_format()
+            .with_context(|| format!("Failed to read image {}", wallpaper.display()))?
+            .decode()
+            .with_context(|| format!("Failed to decode image {}", wallpaper.display()))?
+            .to_rgb8();
+
+        if let Some(sigma) = self.blur.filter(|sigma| *sigma > 0.0) {
+            // Box blur approximation of a Gaussian, much faster at wallpaper sizes
+            img = image::imageops::fast_blur(&img, sigma);
+        }
+        if let Some(dim) = self.dim {
+            let factor = 1.0 - dim;
+            for pixel in img.pixels_mut() {
+                for channel in pixel.0.iter_mut() {
+                    *channel = (*channel as f32 * factor).round() as u8;
+                }
+            }
+        }
+
+        std::fs::create_dir_all(&processed_dir)
+            .with_context(|| format!("Failed to create {}", processed_dir.display()))?;
+        // Encode next to it and rename, so an interrupted run or a second iro never
+        // leaves a half-written image behind under the final name
+        let partial_path = processed_path.with_extension(format!("{}.tmp", std::process::id()));
+        let encoded = std::fs::File::create(&partial_path)
+            .with_context(|| format!("Failed to write {}", partial_path.display()))
+            .and_then(|file| {
+                img.write_with_encoder(JpegEncoder::new_with_quality(
+                    std::io::BufWriter::new(file),
+                    92,
+                ))
+                .with_context(|| format!("Failed to encode {}", processed_path.display()))
+            })
+            .and_then(|()| {
+                std::fs::rename(&partial_path, &processed_path)
+                    .with_context(|| format!("Failed to write {}", processed_path.display()))
+            });
+        if let Err(e) = encoded {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(e);
+        }
+
+        println!(
+            "  ✓ Processed {}",
+            wallpaper.file_name().unwrap_or_default().to_string_lossy()
+        );
+        prune_processed(&processed_dir, KEEP_PROCESSED);
+        Ok(processed_path)
+    }
+}
+
+/// Delete all but the `keep` most recently used files in `dir`, including partial
+/// ones a crashed run left behind
+fn prune_processed(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let used = metadata.modified().ok()?;
+            metadata.is_file().then(|| (used, entry.path()))
+        })
+        .collect();
+    // Newest first
+    files.sort_by_key(|(used, _)| std::cmp::Reverse(*used));
+
+    for (_, path) in files.iter().skip(keep) {
+        if let Err(e) = std::fs::remove_file(path) {
+            println!("  ⚠ Failed to delete {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// A monitor as reported by `hyprctl monitors -j`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    pub name: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(default = "default_monitor_scale")]
+    pub scale: f32,
+}
+
+fn default_monitor_scale() -> f32 {
+    1.0
+}
+
+pub fn get_monitor_info() -> Result<Vec<MonitorInfo>> {
+    let output = Command::new("hyprctl")
+        .args(["monitors", "-j"])
+        .output()
+        .context("Failed to get monitors")?;
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse monitors JSON")
+}
+
+pub fn get_all_monitors() -> Result<Vec<String>> {
+    Ok(get_monitor_info()?.into_iter().map(|m| m.name).collect())
+}
+
+/// How deep `scan_wallpapers` descends below the wallpaper dir
+const MAX_SCAN_DEPTH: usize = 8;
+
+/// Images in `wallpaper_dir`, failing when the dir is missing or has none
+pub fn list_wallpapers(wallpaper_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    if !wallpaper_dir.exists() {
+        anyhow::bail!(
+            "Wallpaper directory not found: {}. Run 'iro --init' first, set wallpaper_dir in ~/.config/iro/config.toml or pass --wallpaper-dir",
+            wallpaper_dir.display()
+        );
+    }
+
+    let wallpapers = scan_wallpapers(wallpaper_dir, recursive);
+    if wallpapers.is_empty() {
+        anyhow::bail!("No wallpapers found in {}", wallpaper_dir.display());
+    }
+
+    Ok(wallpapers)
+}
+
+/// Images in `wallpaper_dir`, and with `recursive` its subdirectories down to
+/// `MAX_SCAN_DEPTH`, sorted by path. Unreadable directories are skipped, and each
+/// directory is visited once so symlink loops end.
+pub fn scan_wallpapers(wallpaper_dir: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut wallpapers = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(wallpaper_dir.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let canonical = std::fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !visited.insert(canonical) {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+            if path.is_dir() {
+                if recursive && depth < MAX_SCAN_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if path.is_file() && is_wallpaper_file(&path) {
+                wallpapers.push(path);
+            }
+        }
+    }
+
+    // read_dir order depends on the filesystem, sort so --seed picks the same file
+    wallpapers.sort();
+    wallpapers
+}
+
+/// Where the GUI moves deleted wallpapers, ~/.cache/iro/trash
+pub fn trash_dir() -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir().context("Failed to get cache directory")?;
+    Ok(cache_dir.join("iro").join("trash"))
+}
+
+/// Move `wallpaper` into the trash dir, keeping it recoverable, and return its new
+/// path. A name already in the trash gets a timestamp prefix.
+pub fn move_to_trash(wallpaper: &Path) -> Result<PathBuf> {
+    let name = wallpaper
+        .file_name()
+        .context("Wallpaper path has no file name")?;
+    let trash = trash_dir()?;
+    std::fs::create_dir_all(&trash).context("Failed to create trash directory")?;
+
+    let mut target = trash.join(name);
+    if target.exists() {
+        let secs = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        target = trash.join(format!("{}-{}", secs, name.to_string_lossy()));
+    }
+
+    // rename can't cross filesystems, fall back to copying
+    if std::fs::rename(wallpaper, &target).is_err() {
+        std::fs::copy(wallpaper, &target)
+            .with_context(|| format!("Failed to copy {} to the trash", wallpaper.display()))?;
+        std::fs::remove_file(wallpaper)
+            .with_context(|| format!("Failed to remove {}", wallpaper.display()))?;
+    }
+
+    Ok(target)
+}
+
+/// Show each (monitor, wallpaper) pair with the configured backend. `background`
+/// fills the bars around a wallpaper in contain mode, where the backend can.
+pub fn set_wallpaper_with_backend(
+    config: &WallpaperConfig,
+    assignments: &[(String, PathBuf)],
+    background: &str,
+) -> Result<()> {
+    let binary = config.backend.binary();
+    if find_in_path(binary).is_none() {
+        anyhow::bail!(
+            "{} not found in PATH, install it or pick another wallpaper.backend",
+            binary
+        );
+    }
+
+    match config.backend {
+        WallpaperBackend::Hyprpaper => {
+            set_with_hyprpaper(config.mode, assignments, config.unload_unused)
+        }
+        WallpaperBackend::Swww => set_with_swww(config, assignments, background),
+        WallpaperBackend::Swaybg => set_with_swaybg(config.mode, assignments, background),
+    }
+}
+
+/// hyprpaper has no fill color, so contain leaves black bars. Monitors without an
+/// assignment keep their hyprpaper.conf entries. With `unload_unused`, images left
+/// over from earlier changes are freed once every monitor is set.
+fn set_with_hyprpaper(
+    mode: WallpaperMode,
+    assignments: &[(String, PathBuf)],
+    unload_unused: bool,
+) -> Result<()> {
+    let fit = match mode {
+        WallpaperMode::Cover => "",
+        WallpaperMode::Contain => "contain:",
+        WallpaperMode::Tile => "tile:",
+    };
+
+    let config_dir = dirs::config_dir().context("Failed to get config directory")?;
+    let hyprpaper_conf = config_dir.join("hypr/hyprpaper.conf");
+
+    // Wallpapers to preload over IPC, once each
+    let mut preloaded: Vec<&str> = Vec::new();
+    for (_, wallpaper_path) in assignments {
+        let wallpaper_str = wallpaper_path.to_str().context("Invalid wallpaper path")?;
+        if !preloaded.contains(&wallpaper_str) {
+            preloaded.push(wallpaper_str);
+        }
+    }
+
+    let entries: Vec<(&str, String)> = assignments
+        .iter()
+        .map(|(monitor, path)| (monitor.as_str(), format!("{}{}", fit, path.display())))
+        .collect();
+    let existing = std::fs::read_to_string(&hyprpaper_conf).unwrap_or_default();
+    let config_content = merge_hyprpaper_conf(&existing, &entries);
+
+    // Write config (for persistence on restart)
+    std::fs::write(&hyprpaper_conf, &config_content).context("Failed to write hyprpaper.conf")?;
+
+    if !is_running("hyprpaper") {
+        spawn_detached(Command::new("hyprpaper")).context("Failed to start hyprpaper")?;
+        std::thread::sleep(std::time::Duration::from_millis(400));
+    }
+
+    // Set wallpapers via IPC (fast, no restart needed). Every preload has to finish
+    // before a monitor is pointed at it.
+    for (wallpaper_str, error) in preload_with_hyprpaper(&preloaded) {
+        println!("  ⚠ Failed to preload {}: {:#}", wallpaper_str, error);
+    }
+    for (monitor, wallpaper_path) in assignments {
+        let _ = Command::new("hyprctl")
+            .args([
+                "hyprpaper",
+                "wallpaper",
+                &format!("{},{}{}", monitor, fit, wallpaper_path.display()),
+            ])
+            .output();
+        print_set(monitor, wallpaper_path);
+    }
+
+    if unload_unused {
+        unload_unused_from_hyprpaper(&preloaded);
+    }
+
+    Ok(())
+}
+
+/// hyprpaper.conf with `entries` (monitor, `[fit:]path`) replacing the wallpapers of
+/// those monitors. Other monitors keep theirs, and the preloads are rebuilt from the
+/// wallpapers left in use. Settings other than `preload` and `wallpaper` are kept.
+fn merge_hyprpaper_conf(existing: &str, entries: &[(&str, String)]) -> String {
+    let mut settings: Vec<&str> = Vec::new();
+    let mut wallpapers: Vec<(&str, &str)> = Vec::new();
+    for line in existing.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            settings.push(line);
+            continue;
+        };
+        match key.trim() {
+            "preload" => {}
+            "wallpaper" => {
+                let (monitor, wallpaper) = value.trim().split_once(',').unwrap_or(("", ""));
+                let monitor = monitor.trim();
+                if !entries.iter().any(|(m, _)| *m == monitor) {
+                    wallpapers.push((monitor, wallpaper.trim()));
+                }
+            }
+            _ => settings.push(line),
+        }
+    }
+    while settings.last().is_some_and(|line| line.trim().is_empty()) {
+        settings.pop();
+    }
+    if settings.is_empty() {
+        settings = vec!["# Generated by iro", "splash = false", "ipc = on"];
+    }
+    wallpapers.extend(entries.iter().map(|(m, w)| (*m, w.as_str())));
+
+    let mut content = settings.join("\n");
+    content.push_str("\n\n");
+    let mut preloaded: Vec<&str> = Vec::new();
+    for (_, wallpaper) in &wallpapers {
+        let path = wallpaper
+            .strip_prefix("contain:")
+            .or_else(|| wallpaper.strip_prefix("tile:"))
+            .unwrap_or(wallpaper);
+        if !path.is_empty() && !preloaded.contains(&path) {
+            content.push_str(&format!("preload = {}\n", path));
+            preloaded.push(path);
+        }
+    }
+    content.push('\n');
+    for (monitor, wallpaper) in &wallpapers {
+        content.push_str(&format!("wallpaper = {},{}\n", monitor, wallpaper));
+    }
+    content
+}
+
+/// Unload every image hyprpaper holds that no monitor shows, so its memory doesn't grow
+/// with each wallpaper change. `in_use` are the wallpapers just set, kept even if
+/// hyprpaper doesn't list them as active yet; monitors this run left alone keep theirs.
+fn unload_unused_from_hyprpaper(in_use: &[&str]) {
+    let hyprctl = |command: &str| {
+        Command::new("hyprctl")
+            .args(["hyprpaper", command])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let (Some(loaded), Some(active)) = (hyprctl("listloaded"), hyprctl("listactive")) else {
+        println!("  ⚠ Failed to list hyprpaper's wallpapers, nothing unloaded");
+        return;
+    };
+    let active = active_hyprpaper_wallpapers(&active);
+
+    let mut unloaded = 0;
+    for wallpaper in loaded.lines().map(str::trim) {
+        // Anything but an absolute path is a status message such as "no wallpapers loaded"
+        if !wallpaper.starts_with('/') || in_use.contains(&wallpaper) || active.contains(&wallpaper)
+        {
+            continue;
+        }
+        let output = Command::new("hyprctl")
+            .args(["hyprpaper", "unload", wallpaper])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => unloaded += 1,
+            _ => println!("  ⚠ Failed to unload {}", wallpaper),
+        }
+    }
+
+    if unloaded > 0 {
+        println!("  ✓ Unloaded {} unused wallpaper(s)", unloaded);
+    }
+}
+
+/// Wallpapers in `hyprctl hyprpaper listactive` output, one "<monitor> = <path>" line
+/// per monitor
+fn active_hyprpaper_wallpapers(listactive: &str) -> Vec<&str> {
+    listactive
+        .lines()
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(_, wallpaper)| {
+            let wallpaper = wallpaper.trim();
+            wallpaper
+                .strip_prefix("contain:")
+                .or_else(|| wallpaper.strip_prefix("tile:"))
+                .unwrap_or(wallpaper)
+        })
+        .collect()
+}
+
+/// Preload `wallpapers` into hyprpaper concurrently, as decoding large images takes a
+/// while each. Returns the wallpapers that failed, so one bad file doesn't stop the rest.
+fn preload_with_hyprpaper<'a>(wallpapers: &[&'a str]) -> Vec<(&'a str, anyhow::Error)> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = wallpapers
+            .iter()
+            .map(|&wallpaper| {
+                let handle = scope.spawn(move || -> Result<()> {
+                    let output = Command::new("hyprctl")
+                        .args(["hyprpaper", "preload", wallpaper])
+                        .output()
+                        .context("Failed to run hyprctl")?;
+                    if !output.status.success() {
+                        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+                    }
+                    Ok(())
+                });
+                (wallpaper, handle)
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|(wallpaper, handle)| {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("preload thread panicked")));
+                result.err().map(|error| (wallpaper, error))
+            })
+            .collect()
+    })
+}
+
+fn set_with_swww(
+    config: &WallpaperConfig,
+    assignments: &[(String, PathBuf)],
+    background: &str,
+) -> Result<()> {
+    let resize = match config.mode {
+        WallpaperMode::Cover => "crop",
+        WallpaperMode::Contain => "fit",
+        WallpaperMode::Tile => {
+            anyhow::bail!("swww can't tile wallpapers, set wallpaper.mode to cover or contain")
+        }
+    };
+
+    // `swww query` only succeeds while the daemon is up
+    let daemon_running = Command::new("swww")
+        .arg("query")
+        .output()
+        .is_ok_and(|output| output.status.success());
+
+    if !daemon_running {
+        spawn_detached(Command::new("swww-daemon")).context("Failed to start swww-daemon")?;
+        std::thread::sleep(std::time::Duration::from_millis(400));
+    }
+
+    for (monitor, wallpaper_path) in assignments {
+        let output = Command::new("swww")
+            .arg("img")
+            .args(["-o", monitor])
+            .args(["--resize", resize])
+            .args(["--fill-color", background.trim_start_matches('#')])
+            .args(["--transition-type", &config.transition_type])
+            .args([
+                "--transition-duration",
+                &config.transition_duration.to_string(),
+            ])
+            .arg(wallpaper_path)
+            .output()
+            .context("Failed to run swww")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "swww img failed for {}: {}",
+                monitor,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        print_set(monitor, wallpaper_path);
+    }
+
+    Ok(())
+}
+
+fn set_with_swaybg(
+    mode: WallpaperMode,
+    assignments: &[(String, PathBuf)],
+    background: &str,
+) -> Result<()> {
+    let fit = match mode {
+        WallpaperMode::Cover => "fill",
+        WallpaperMode::Contain => "fit",
+        WallpaperMode::Tile => "tile",
+    };
+
+    // swaybg has no IPC, so the instances drawing on an assigned monitor are replaced.
+    // Whatever else they drew on is started again as it was.
+    let assigned: Vec<&str> = assignments.iter().map(|(m, _)| m.as_str()).collect();
+    for (pid, args) in swaybg_instances() {
+        let Some(kept) = swaybg_kept_args(&args, &assigned) else {
+            continue;
+        };
+        let _ = Command::new("kill").arg(pid.to_string()).output();
+        if !kept.is_empty() {
+            let mut command = Command::new("swaybg");
+            command.args(kept);
+            let _ = spawn_detached(command);
+        }
+    }
+
+    let mut command = Command::new("swaybg");
+    for (monitor, wallpaper_path) in assignments {
+        command
+            .args(["-o", monitor])
+            .arg("-i")
+            .arg(wallpaper_path)
+            .args(["-m", fit])
+            .args(["-c", background]);
+    }
+    spawn_detached(command).context("Failed to start swaybg")?;
+
+    for (monitor, wallpaper_path) in assignments {
+        print_set(monitor, wallpaper_path);
+    }
+    Ok(())
+}
+
+/// Running swaybg processes with their arguments, read from /proc so paths with
+/// spaces survive
+fn swaybg_instances() -> Vec<(u32, Vec<String>)> {
+    let Ok(output) = Command::new("pgrep").args(["-x", "swaybg"]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse::<u32>().ok())
+        .filter_map(|pid| {
+            let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+            let args = cmdline
+                .split(|&b| b == 0)
+                .skip(1)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            Some((pid, args))
+        })
+        .collect()
+}
+
+/// For a swaybg instance started with `args`: `None` when it draws on none of
+/// `assigned`, so it can keep running, otherwise the arguments that redraw the outputs
+/// it covered apart from `assigned` (empty when there are none). Options before the
+/// first `-o` apply to every output.
+fn swaybg_kept_args(args: &[String], assigned: &[&str]) -> Option<Vec<String>> {
+    let is_output = |arg: &str| arg == "-o" || arg == "--output";
+
+    let first_output = args.iter().position(|arg| is_output(arg));
+    let (common, rest) = args.split_at(first_output.unwrap_or(args.len()));
+
+    // Each `-o NAME ...` group up to the next `-o`
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut tokens = rest.iter();
+    while let Some(arg) = tokens.next() {
+        if is_output(arg) {
+            groups.push((tokens.next().cloned().unwrap_or_default(), Vec::new()));
+        } else if let Some((_, options)) = groups.last_mut() {
+            options.push(arg.clone());
+        }
+    }
+    // `-o *`, or no `-o` at all, covers every monitor
+    let wildcard = groups.is_empty() || groups.iter().any(|(m, _)| m == "*");
+    if !wildcard && !groups.iter().any(|(m, _)| assigned.contains(&m.as_str())) {
+        return None;
+    }
+    if wildcard {
+        let options = groups
+            .iter()
+            .find(|(m, _)| m == "*")
+            .map(|(_, options)| options.clone())
+            .unwrap_or_default();
+        groups.retain(|(m, _)| m != "*");
+        for monitor in get_all_monitors().unwrap_or_default() {
+            if !groups.iter().any(|(m, _)| *m == monitor) {
+                groups.push((monitor, options.clone()));
+            }
+        }
+    }
+
+    let mut kept = Vec::new();
+    for (monitor, options) in groups {
+        if assigned.contains(&monitor.as_str()) {
+            continue;
+        }
+        kept.extend(["-o".to_string(), monitor]);
+        kept.extend(common.iter().cloned());
+        kept.extend(options);
+    }
+    Some(kept)
+}
+
+fn is_running(process: &str) -> bool {
+    Command::new("pgrep")
+        .args(["-x", process])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Start `command` in the background with its output discarded
+pub fn spawn_detached(mut command: Command) -> std::io::Result<()> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+fn print_set(monitor: &str, wallpaper_path: &Path) {
+    println!(
+        "  ✓ Set {} on {}",
+        wallpaper_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        monitor
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn hyprpaper_conf_keeps_unassigned_monitors() {
+        let existing = "\
+# Generated by iro
+splash = false
+ipc = on
+
+preload = /walls/a.png
+preload = /walls/b.png
+
+wallpaper = DP-1,/walls/a.png
+wallpaper = HDMI-A-1,contain:/walls/b.png
+";
+        let merged = merge_hyprpaper_conf(existing, &[("DP-1", "/walls/c.png".to_string())]);
+        assert_eq!(
+            merged,
+            "\
+# Generated by iro
+splash = false
+ipc = on
+
+preload = /walls/b.png
+preload = /walls/c.png
+
+wallpaper = HDMI-A-1,contain:/walls/b.png
+wallpaper = DP-1,/walls/c.png
+"
+        );
+        // Applying the same assignment again changes nothing
+        assert_eq!(
+            merge_hyprpaper_conf(&merged, &[("DP-1", "/walls/c.png".to_string())]),
+            merged
+        );
+    }
+
+    #[test]
+    fn hyprpaper_conf_is_created_from_scratch() {
+        let merged = merge_hyprpaper_conf("", &[("DP-1", "tile:/walls/a.png".to_string())]);
+        assert_eq!(
+            merged,
+            "# Generated by iro\nsplash = false\nipc = on\n\n\
+             preload = /walls/a.png\n\nwallpaper = DP-1,tile:/walls/a.png\n"
+        );
+    }
+
+    #[test]
+    fn swaybg_instances_on_other_monitors_keep_running() {
+        let running = args("-o HDMI-A-1 -i /walls/a.png -m fill");
+        assert_eq!(swaybg_kept_args(&running, &["DP-1"]), None);
+    }
+
+    #[test]
+    fn swaybg_instances_are_restarted_without_the_assigned_monitors() {
+        let running =
+            args("-c #000000 -o DP-1 -i /walls/a.png -m fill -o HDMI-A-1 -i /walls/b.png -m fit");
+        assert_eq!(
+            swaybg_kept_args(&running, &["DP-1"]),
+            Some(args("-o HDMI-A-1 -c #000000 -i /walls/b.png -m fit"))
+        );
+        assert_eq!(
+            swaybg_kept_args(&running, &["DP-1", "HDMI-A-1"]),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn active_hyprpaper_wallpapers_are_parsed() {
+        let listactive = "DP-1 = /walls/a.png\nHDMI-A-1 = contain:/walls/b c.png\n";
+        assert_eq!(
+            active_hyprpaper_wallpapers(listactive),
+            ["/walls/a.png", "/walls/b c.png"]
+        );
+        assert!(active_hyprpaper_wallpapers("no wallpapers active").is_empty());
+    }
+
+    #[test]
+    fn pruning_keeps_the_most_recently_used() {
+        let dir = std::env::temp_dir().join(format!("iro-processed-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let now = SystemTime::now();
+        for (i, name) in ["a.jpg", "b.jpg", "c.jpg", "d.jpg.1234.tmp"]
+            .iter()
+            .enumerate()
+        {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(60 * i as u64))
+                .unwrap();
+        }
+        prune_processed(&dir, 2);
+
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["a.jpg", "b.jpg"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    color_extractor::is_wallpaper_file, reload::reload_applications, state, ColorExtractor,
+    ConfigGenerator,
+};
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Quiet time after the last event before re-theming, so a copy or a rotation
+/// script touching several files themes once
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Re-theme whenever the wallpaper at `target` changes, until interrupted.
+///
+/// A file target is watched through its parent directory, so replacing it by
+/// rename (or repointing a symlink) is seen too. A directory target themes from
+/// the image that was added or changed last. The wallpaper itself is left to
+/// whatever rotated it.
+pub fn run(target: &Path, recursive: bool, theme: &str, live: bool) -> Result<()> {
+    let watch_file = target.is_file();
+    let (watched, mode) = if watch_file {
+        let parent = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        (parent, RecursiveMode::NonRecursive)
+    } else if target.is_dir() {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        (target, mode)
+    } else {
+        anyhow::bail!("Watch target not found: {}", target.display());
+    };
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("Failed to start watcher")?;
+    watcher
+        .watch(watched, mode)
+        .with_context(|| format!("Failed to watch {}", watched.display()))?;
+
+    println!("👀 Watching {} (Ctrl-C to stop)", target.display());
+
+    while let Ok(event) = events.recv() {
+        let mut changed = Vec::new();
+        collect_changes(event, &mut changed);
+        // Keep collecting until the events settle
+        while let Ok(event) = events.recv_timeout(DEBOUNCE) {
+            collect_changes(event, &mut changed);
+        }
+
+        let wallpaper = if watch_file {
+            let name = target.file_name();
+            changed
+                .iter()
+                .any(|path| path.file_name() == name)
+                .then(|| target.to_path_buf())
+        } else {
+            changed
+                .into_iter()
+                .rev()
+                .find(|path| is_wallpaper_file(path))
+        };
+
+        // Skip files that were changed and then removed again
+        if let Some(wallpaper) = wallpaper.filter(|path| path.exists()) {
+            if let Err(e) = apply(&wallpaper, theme, live) {
+                println!("  ⚠ Failed to theme {}: {:#}", wallpaper.display(), e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add the paths of an event that created or changed a file
+fn collect_changes(event: notify::Result<notify::Event>, changed: &mut Vec<PathBuf>) {
+    match event {
+        Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+            changed.extend(event.paths);
+        }
+        Ok(_) => {}
+        Err(e) => println!("  ⚠ Watch error: {}", e),
+    }
+}
+
+/// Extract, write the configs and reload, reading the config afresh each time
+fn apply(wallpaper: &Path, theme: &str, live: bool) -> Result<()> {
+    println!(
+        "🎨 iro - Generating color scheme from: {}",
+        wallpaper.display()
+    );
+
+    let extractor = ColorExtractor::new()?;
+    let scheme = extractor.extract_colors(&wallpaper.to_path_buf(), theme)?;
+    for note in extractor.take_notes() {
+        eprintln!("{}", note);
+    }
+    ConfigGenerator::new()?.generate_configs(&scheme)?;
+
+    println!("🔄 Reloading applications...");
+    reload_applications(live.then_some(&scheme))?;
+
+    // Remember it for --apply-last, which also puts the wallpaper back
+    let applied = state::AppliedState::new(&[wallpaper.to_path_buf()], None, scheme)
+        .with_theme(theme, extractor.style());
+    if let Err(e) = applied.save() {
+        println!("  ⚠ Failed to save state: {}", e);
+    }
+    let recorded = state::History::load().and_then(|mut history| {
+        history.push(wallpaper);
+        history.save()
+    });
+    if let Err(e) = recorded {
+        println!("  ⚠ Failed to save history: {}", e);
+    }
+
+    println!("✅ Color scheme applied successfully!");
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Applying the same scheme twice must leave every config byte-identical, whatever
+//! the output mode.
+
+use iro::config::{OutputConfig, OutputMode};
+use iro::{ColorScheme, ConfigGenerator, IroConfig};
+use std::path::{Path, PathBuf};
+
+const HYPRLAND_CONF: &str = "\
+monitor = ,preferred,auto,1
+$terminal = kitty
+exec-once = waybar & hyprpaper
+
+input {
+    kb_layout = us
+    touchpad {
+        natural_scroll = true
+    }
+}
+
+general {
+    gaps_in = 5
+    col.active_border = $accent
+}
+
+bind = SUPER, Return, exec, $terminal
+";
+
+const KITTY_CONF: &str = "\
+font_family      JetBrains Mono
+font_size        11.0
+window_padding_width 8
+
+# ═══════════════════════════════════════════════════════════════════
+# TAB BAR
+# ═══════════════════════════════════════════════════════════════════
+tab_bar_style powerline
+";
+
+const WAYBAR_CSS: &str = "\
+* {
+    font-family: monospace;
+}
+
+window#waybar {
+    background: @base;
+}
+";
+
+/// The files the test compares, relative to the config dir
+const CONFIGS: [&str; 3] = ["hypr/hyprland.conf", "kitty/kitty.conf", "waybar/style.css"];
+
+fn scheme() -> ColorScheme {
+    ColorScheme {
+        background: "#1a1b26".to_string(),
+        foreground: "#c0caf5".to_string(),
+        colors: [
+            "#1a1b26", "#f7768e", "#9ece6a", "#e0af68", "#7aa2f7", "#bb9af7", "#7dcfff", "#c0caf5",
+            "#414868", "#ff899d", "#9fe044", "#faba4a", "#8db0ff", "#c7a9ff", "#a4daff", "#ffffff",
+        ]
+        .map(String::from)
+        .to_vec(),
+        accent: "#7aa2f7".to_string(),
+        secondary: "#bb9af7".to_string(),
+        surface: "#24283b".to_string(),
+        error: "#f7768e".to_string(),
+    }
+}
+
+/// Fresh config dir holding the fixtures, with HOME and XDG_CONFIG_HOME pointed at it
+fn setup(name: &str, modes: &[(&str, OutputMode)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("iro-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let config_dir = root.join(".config");
+    std::env::set_var("HOME", &root);
+    std::env::set_var("XDG_CONFIG_HOME", &config_dir);
+
+    for (path, content) in CONFIGS.iter().zip([HYPRLAND_CONF, KITTY_CONF, WAYBAR_CSS]) {
+        let path = config_dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let mut config = IroConfig::default();
+    for (output, mode) in modes {
+        config.outputs.insert(
+            output.to_string(),
+            OutputConfig {
+                mode: Some(*mode),
+                path: None,
+            },
+        );
+    }
+    config.save().unwrap();
+
+    root
+}
+
+fn snapshot(config_dir: &Path) -> Vec<String> {
+    CONFIGS
+        .iter()
+        .map(|path| std::fs::read_to_string(config_dir.join(path)).unwrap())
+        .collect()
+}
+
+fn assert_idempotent(name: &str, modes: &[(&str, OutputMode)]) {
+    let root = setup(name, modes);
+    let config_dir = root.join(".config");
+    let scheme = scheme();
+
+    let generator = ConfigGenerator::new().unwrap();
+    generator.generate_configs(&scheme).unwrap();
+    let first = snapshot(&config_dir);
+    generator.generate_configs(&scheme).unwrap();
+    let second = snapshot(&config_dir);
+
+    for ((path, first), second) in CONFIGS.iter().zip(&first).zip(&second) {
+        assert_eq!(
+            first, second,
+            "{} changed on the second run ({})",
+            path, name
+        );
+    }
+
+    // The colors go in alongside the user's settings, never in place of them
+    for (fixture, written) in [HYPRLAND_CONF, KITTY_CONF].iter().zip(&second) {
+        for line in fixture.lines() {
+            assert!(written.contains(line), "lost {:?} ({})", line, name);
+        }
+    }
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+// One test, so the setups don't race on the process-wide environment
+#[test]
+fn applying_twice_gives_identical_configs() {
+    assert_idempotent("default-modes", &[]);
+    assert_idempotent(
+        "inline",
+        &[
+            ("hyprland", OutputMode::Inline),
+            ("kitty", OutputMode::Inline),
+            ("waybar", OutputMode::Inline),
+        ],
+    );
+    assert_idempotent(
+        "include",
+        &[
+            ("hyprland", OutputMode::Include),
+            ("kitty", OutputMode::Include),
+            ("waybar", OutputMode::Include),
+        ],
+    );
+}
@@ -0,0 +1,57 @@
+//! A second extraction of the same wallpaper comes from the palette cache, without
+//! decoding the image again.
+
+use image::{Rgb, RgbImage};
+use std::fs::File;
+use std::path::Path;
+use std::process::Output;
+
+/// `iro --output json` on `wallpaper`, with every iro directory under `root`
+fn print_scheme(root: &Path, wallpaper: &Path, args: &[&str]) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_iro"))
+        .args(["--output", "json"])
+        .args(args)
+        .arg(wallpaper)
+        .env("HOME", root)
+        .env("XDG_CONFIG_HOME", root.join(".config"))
+        .env("XDG_CACHE_HOME", root.join(".cache"))
+        .env("XDG_DATA_HOME", root.join(".local/share"))
+        .env("XDG_STATE_HOME", root.join(".local/state"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn second_extraction_skips_the_image() {
+    let root = std::env::temp_dir().join(format!("iro-palette-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+
+    let path = root.join("wallpaper.png");
+    RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 160]))
+        .save(&path)
+        .unwrap();
+
+    let first = print_scheme(&root, &path, &[]);
+    assert!(first.status.success());
+
+    // Garbage of the same size and modification time: the cache key doesn't change,
+    // but decoding would fail
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    let size = std::fs::metadata(&path).unwrap().len() as usize;
+    std::fs::write(&path, vec![0_u8; size]).unwrap();
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    let second = print_scheme(&root, &path, &[]);
+    assert!(second.status.success());
+    assert_eq!(second.stdout, first.stdout);
+
+    // Without the cache the image really is unreadable now
+    assert!(!print_scheme(&root, &path, &["--no-cache"]).status.success());
+
+    std::fs::remove_dir_all(root).unwrap();
+}
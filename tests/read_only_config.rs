@@ -0,0 +1,57 @@
+//! Commands like `--dry-run` and `doctor` load the config read-only: an old file is
+//! upgraded in memory, and nothing is written next to it.
+
+use iro::config::{self, CONFIG_VERSION};
+use iro::{ColorScheme, IroConfig, TemplateEngine};
+
+const OLD_CONFIG: &str = "\
+wallpaper_dir = \"~/walls\"
+
+[palette]
+dark_saturation = 0.9
+";
+
+#[test]
+fn read_only_load_leaves_the_config_dir_alone() {
+    let root = std::env::temp_dir().join(format!("iro-read-only-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let config_dir = root.join(".config");
+    std::env::set_var("HOME", &root);
+    std::env::set_var("XDG_CONFIG_HOME", &config_dir);
+    config::read_only();
+
+    // A missing config isn't created
+    let config = IroConfig::load().unwrap();
+    assert_eq!(config.version, CONFIG_VERSION);
+    assert!(!config_dir.join("iro").exists());
+
+    // Default templates, partials included, render without being written
+    let templates = TemplateEngine::new().unwrap();
+    templates.create_default_templates().unwrap();
+    let scheme = ColorScheme {
+        background: "#1a1b26".to_string(),
+        foreground: "#c0caf5".to_string(),
+        colors: vec!["#7aa2f7".to_string(); 16],
+        accent: "#7aa2f7".to_string(),
+        secondary: "#bb9af7".to_string(),
+        surface: "#24283b".to_string(),
+        error: "#f7768e".to_string(),
+    };
+    let waybar = templates.render_template("waybar.css", &scheme).unwrap();
+    assert!(waybar.contains("@define-color base #1a1b26;"));
+    assert!(!config_dir.join("iro").exists());
+
+    // An old one is upgraded without being rewritten or backed up
+    let path = config_dir.join("iro").join("config.toml");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, OLD_CONFIG).unwrap();
+
+    let config = IroConfig::load().unwrap();
+    assert_eq!(config.version, CONFIG_VERSION);
+    assert_eq!(config.wallpaper_dir, "~/walls");
+    assert_eq!(config.palette.dark_saturation, None);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), OLD_CONFIG);
+    assert!(!path.with_extension("toml.bak").exists());
+
+    std::fs::remove_dir_all(root).unwrap();
+}